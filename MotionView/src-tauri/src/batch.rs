@@ -0,0 +1,649 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::csv::{opt_num, sibling, write_row, TableFormat};
+use crate::library::{self, EntrySource, LibraryQuery};
+use crate::plan::{read_saved_plan, Waypoint};
+use crate::run::{self, Run};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BatchOptions {
+    /// Where to measure the spread; the saved plan when not given.
+    pub checkpoints: Option<Vec<Waypoint>>,
+    /// A run reaches a checkpoint when it passes within this many inches.
+    pub near_radius: f64,
+    /// Ellipse size in standard deviations.
+    pub ellipse_sigma: f64,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            checkpoints: None,
+            near_radius: 6.0,
+            ellipse_sigma: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    /// Sample standard deviation.
+    pub std: f64,
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Distribution {
+        let mut v: Vec<f64> = values.into_iter().filter(|v| v.is_finite()).collect();
+        if v.is_empty() {
+            return Distribution::default();
+        }
+        v.sort_by(f64::total_cmp);
+        let n = v.len();
+        let mean = v.iter().sum::<f64>() / n as f64;
+        let var = if n > 1 {
+            v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        Distribution {
+            count: n,
+            mean,
+            std: var.sqrt(),
+            min: v[0],
+            median: if n % 2 == 1 {
+                v[n / 2]
+            } else {
+                (v[n / 2 - 1] + v[n / 2]) / 2.0
+            },
+            max: v[n - 1],
+        }
+    }
+}
+
+/// Semi-axes in inches; `rotation` is the compass heading of the major axis,
+/// in [0, 180).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Ellipse {
+    pub semi_major: f64,
+    pub semi_minor: f64,
+    pub rotation: f64,
+}
+
+/// Where a set of poses landed. Lengths in inches, angles in degrees.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PoseSpread {
+    pub count: usize,
+    pub mean_x: f64,
+    pub mean_y: f64,
+    pub cov_xx: f64,
+    pub cov_xy: f64,
+    pub cov_yy: f64,
+    pub ellipse: Ellipse,
+    /// Circular mean and standard deviation of the headings.
+    pub mean_theta: f64,
+    pub theta_std: f64,
+    /// Largest distance from the mean position.
+    pub max_distance: f64,
+}
+
+impl PoseSpread {
+    fn of(poses: &[BatchPose], sigma: f64) -> PoseSpread {
+        let n = poses.len();
+        if n == 0 {
+            return PoseSpread::default();
+        }
+        let mean_x = poses.iter().map(|p| p.x).sum::<f64>() / n as f64;
+        let mean_y = poses.iter().map(|p| p.y).sum::<f64>() / n as f64;
+        let (mut xx, mut xy, mut yy) = (0.0, 0.0, 0.0);
+        for p in poses {
+            let (dx, dy) = (p.x - mean_x, p.y - mean_y);
+            xx += dx * dx;
+            xy += dx * dy;
+            yy += dy * dy;
+        }
+        let d = if n > 1 { (n - 1) as f64 } else { 1.0 };
+        let (cov_xx, cov_xy, cov_yy) = (xx / d, xy / d, yy / d);
+
+        let mid = (cov_xx + cov_yy) / 2.0;
+        let r = ((cov_xx - cov_yy) / 2.0).hypot(cov_xy);
+        // Major axis angle from +x, turned into a compass heading.
+        let phi = 0.5 * (2.0 * cov_xy).atan2(cov_xx - cov_yy);
+        let ellipse = Ellipse {
+            semi_major: sigma * (mid + r).max(0.0).sqrt(),
+            semi_minor: sigma * (mid - r).max(0.0).sqrt(),
+            rotation: (90.0 - phi.to_degrees()).rem_euclid(180.0),
+        };
+
+        let (s, c) = poses.iter().fold((0.0, 0.0), |(s, c), p| {
+            let h = p.theta.to_radians();
+            (s + h.sin(), c + h.cos())
+        });
+        let len = s.hypot(c) / n as f64;
+        PoseSpread {
+            count: n,
+            mean_x,
+            mean_y,
+            cov_xx,
+            cov_xy,
+            cov_yy,
+            ellipse,
+            mean_theta: s.atan2(c).to_degrees().rem_euclid(360.0),
+            theta_std: (-2.0 * len.clamp(1e-12, 1.0).ln()).sqrt().to_degrees(),
+            max_distance: poses
+                .iter()
+                .map(|p| (p.x - mean_x).hypot(p.y - mean_y))
+                .fold(0.0, f64::max),
+        }
+    }
+}
+
+/// A pose in inches, `t` in ms since the run's first pose.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BatchPose {
+    pub t: u64,
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub index: usize,
+    pub name: String,
+    pub duration_ms: u64,
+    /// When the last checkpoint was reached, if it was.
+    pub completion_ms: Option<u64>,
+    pub final_pose: BatchPose,
+    /// Closest pose to each checkpoint, when within `near_radius`.
+    pub checkpoints: Vec<Option<BatchPose>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointStats {
+    pub index: usize,
+    pub waypoint: Waypoint,
+    pub reached: usize,
+    pub missed: usize,
+    pub spread: PoseSpread,
+    /// Distance of each reaching run from the waypoint.
+    pub error: Distribution,
+    pub time_ms: Distribution,
+}
+
+/// When a watch label first fired in each run, in ms since the run started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchTiming {
+    pub label: String,
+    pub runs: usize,
+    pub occurrences: usize,
+    pub first_ms: Distribution,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub runs: Vec<RunSummary>,
+    pub final_pose: PoseSpread,
+    pub duration_ms: Distribution,
+    pub completion_ms: Distribution,
+    pub checkpoints: Vec<CheckpointStats>,
+    pub watches: Vec<WatchTiming>,
+    /// Library entries that could not be used, with the reason.
+    #[serde(default)]
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchFormat {
+    #[default]
+    Json,
+    Csv,
+    Tsv,
+}
+
+fn summarize_run(
+    index: usize,
+    name: String,
+    run: &Run,
+    checkpoints: &[Waypoint],
+    near_radius: f64,
+) -> Result<RunSummary, String> {
+    let scale = run.inches_scale().map_err(|e| format!("{name}: {e}"))?;
+    let t0 = run
+        .poses
+        .first()
+        .ok_or_else(|| format!("{name}: run has no poses"))?
+        .t;
+    let at = |i: usize| {
+        let p = &run.poses[i];
+        BatchPose {
+            t: p.t.saturating_sub(t0),
+            x: p.x * scale,
+            y: p.y * scale,
+            theta: p.theta,
+        }
+    };
+
+    // Checkpoints are taken in order, so a path that crosses itself matches
+    // each visit to the right waypoint.
+    let mut from = 0;
+    let mut hits = Vec::with_capacity(checkpoints.len());
+    for w in checkpoints {
+        let dist = |i: usize| (run.poses[i].x * scale - w.x).hypot(run.poses[i].y * scale - w.y);
+        let closest = (from..run.poses.len()).min_by(|&a, &b| dist(a).total_cmp(&dist(b)));
+        match closest.filter(|&i| dist(i) <= near_radius) {
+            Some(i) => {
+                from = i;
+                hits.push(Some(at(i)));
+            }
+            None => hits.push(None),
+        }
+    }
+
+    let last = at(run.poses.len() - 1);
+    Ok(RunSummary {
+        index,
+        name,
+        duration_ms: last.t,
+        completion_ms: hits.last().copied().flatten().map(|p| p.t),
+        final_pose: last,
+        checkpoints: hits,
+    })
+}
+
+/// Statistics over `runs`, each paired with the name to report it under.
+pub fn analyze(runs: &[(String, Run)], opts: &BatchOptions) -> Result<BatchReport, String> {
+    if runs.is_empty() {
+        return Err("no runs to analyze".into());
+    }
+    if !(opts.near_radius.is_finite() && opts.near_radius >= 0.0) {
+        return Err("near_radius must not be negative".into());
+    }
+    let checkpoints = opts.checkpoints.as_deref().unwrap_or(&[]);
+    let summaries = runs
+        .iter()
+        .enumerate()
+        .map(|(i, (name, run))| summarize_run(i, name.clone(), run, checkpoints, opts.near_radius))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let checkpoint_stats = checkpoints
+        .iter()
+        .enumerate()
+        .map(|(index, w)| {
+            let hits: Vec<BatchPose> = summaries
+                .iter()
+                .filter_map(|s| s.checkpoints[index])
+                .collect();
+            CheckpointStats {
+                index,
+                waypoint: *w,
+                reached: hits.len(),
+                missed: summaries.len() - hits.len(),
+                spread: PoseSpread::of(&hits, opts.ellipse_sigma),
+                error: Distribution::of(hits.iter().map(|p| (p.x - w.x).hypot(p.y - w.y))),
+                time_ms: Distribution::of(hits.iter().map(|p| p.t as f64)),
+            }
+        })
+        .collect();
+
+    // label -> (first time in each run that has it, total occurrences)
+    let mut watches: BTreeMap<&str, (Vec<f64>, usize)> = BTreeMap::new();
+    for (_, run) in runs {
+        let t0 = run.poses[0].t;
+        let mut seen: BTreeMap<&str, u64> = BTreeMap::new();
+        for w in &run.watches {
+            let first = seen.entry(&w.label).or_insert(w.t);
+            *first = (*first).min(w.t);
+            watches.entry(&w.label).or_default().1 += 1;
+        }
+        for (label, t) in seen {
+            let entry = watches.entry(label).or_default();
+            entry.0.push(t.saturating_sub(t0) as f64);
+        }
+    }
+    let watches = watches
+        .into_iter()
+        .map(|(label, (firsts, occurrences))| WatchTiming {
+            label: label.to_string(),
+            runs: firsts.len(),
+            occurrences,
+            first_ms: Distribution::of(firsts),
+        })
+        .collect();
+
+    let finals: Vec<BatchPose> = summaries.iter().map(|s| s.final_pose).collect();
+    Ok(BatchReport {
+        final_pose: PoseSpread::of(&finals, opts.ellipse_sigma),
+        duration_ms: Distribution::of(summaries.iter().map(|s| s.duration_ms as f64)),
+        completion_ms: Distribution::of(
+            summaries
+                .iter()
+                .filter_map(|s| s.completion_ms.map(|t| t as f64)),
+        ),
+        checkpoints: checkpoint_stats,
+        watches,
+        runs: summaries,
+        skipped: Vec::new(),
+    })
+}
+
+fn distribution_fields(d: &Distribution) -> [String; 6] {
+    [
+        d.count.to_string(),
+        d.mean.to_string(),
+        d.std.to_string(),
+        d.min.to_string(),
+        d.median.to_string(),
+        d.max.to_string(),
+    ]
+}
+
+fn spread_fields(s: &PoseSpread) -> [String; 10] {
+    [
+        s.mean_x.to_string(),
+        s.mean_y.to_string(),
+        s.cov_xx.to_string(),
+        s.cov_xy.to_string(),
+        s.cov_yy.to_string(),
+        s.ellipse.semi_major.to_string(),
+        s.ellipse.semi_minor.to_string(),
+        s.ellipse.rotation.to_string(),
+        s.mean_theta.to_string(),
+        s.theta_std.to_string(),
+    ]
+}
+
+const SPREAD_COLUMNS: [&str; 10] = [
+    "mean_x",
+    "mean_y",
+    "cov_xx",
+    "cov_xy",
+    "cov_yy",
+    "semi_major",
+    "semi_minor",
+    "rotation",
+    "mean_theta",
+    "theta_std",
+];
+
+fn table(columns: &[&str], rows: Vec<Vec<String>>, format: TableFormat) -> String {
+    let delim = format.delimiter();
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+    write_row(&mut out, &header, delim);
+    for row in rows {
+        write_row(&mut out, &row, delim);
+    }
+    out
+}
+
+/// `<name>_runs`, `<name>_checkpoints` (row -1 is the final pose) and
+/// `<name>_watches` tables.
+fn report_tables(
+    report: &BatchReport,
+    path: &Path,
+    format: TableFormat,
+) -> Vec<(std::path::PathBuf, String)> {
+    let ext = format.extension();
+    let runs = report
+        .runs
+        .iter()
+        .map(|r| {
+            vec![
+                r.index.to_string(),
+                r.name.clone(),
+                r.duration_ms.to_string(),
+                opt_num(r.completion_ms.map(|t| t as f64)),
+                r.final_pose.x.to_string(),
+                r.final_pose.y.to_string(),
+                r.final_pose.theta.to_string(),
+                r.checkpoints.iter().flatten().count().to_string(),
+            ]
+        })
+        .collect();
+    let runs = table(
+        &[
+            "index",
+            "name",
+            "duration_ms",
+            "completion_ms",
+            "final_x",
+            "final_y",
+            "final_theta",
+            "checkpoints_reached",
+        ],
+        runs,
+        format,
+    );
+
+    let mut checkpoint_columns = vec!["checkpoint", "x", "y", "theta", "reached", "missed"];
+    checkpoint_columns.extend(SPREAD_COLUMNS);
+    checkpoint_columns.extend(["error_mean", "error_max", "time_mean_ms", "time_std_ms"]);
+    let mut checkpoints = vec![{
+        let s = &report.final_pose;
+        let mut row = vec![
+            "-1".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+        ];
+        row.extend([s.count.to_string(), "0".to_string()]);
+        row.extend(spread_fields(s));
+        row.extend(std::iter::repeat_n(String::new(), 4));
+        row
+    }];
+    for c in &report.checkpoints {
+        let mut row = vec![
+            c.index.to_string(),
+            c.waypoint.x.to_string(),
+            c.waypoint.y.to_string(),
+            c.waypoint.theta.to_string(),
+            c.reached.to_string(),
+            c.missed.to_string(),
+        ];
+        row.extend(spread_fields(&c.spread));
+        row.extend([
+            c.error.mean.to_string(),
+            c.error.max.to_string(),
+            c.time_ms.mean.to_string(),
+            c.time_ms.std.to_string(),
+        ]);
+        checkpoints.push(row);
+    }
+    let checkpoints = table(&checkpoint_columns, checkpoints, format);
+
+    let watches = report
+        .watches
+        .iter()
+        .map(|w| {
+            let mut row = vec![
+                w.label.clone(),
+                w.runs.to_string(),
+                w.occurrences.to_string(),
+            ];
+            row.extend(distribution_fields(&w.first_ms).into_iter().skip(1));
+            row
+        })
+        .collect();
+    let watches = table(
+        &[
+            "label",
+            "runs",
+            "occurrences",
+            "first_mean_ms",
+            "first_std_ms",
+            "first_min_ms",
+            "first_median_ms",
+            "first_max_ms",
+        ],
+        watches,
+        format,
+    );
+
+    vec![
+        (sibling(path, "_runs", ext), runs),
+        (sibling(path, "_checkpoints", ext), checkpoints),
+        (sibling(path, "_watches", ext), watches),
+    ]
+}
+
+/// Writes the report next to `path` and returns every file written.
+pub fn export(
+    report: &BatchReport,
+    path: &Path,
+    format: BatchFormat,
+) -> Result<Vec<String>, String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let files = match format {
+        BatchFormat::Json => vec![(
+            path.with_extension("json"),
+            serde_json::to_string_pretty(report).map_err(|e| e.to_string())?,
+        )],
+        BatchFormat::Csv => report_tables(report, path, TableFormat::Csv),
+        BatchFormat::Tsv => report_tables(report, path, TableFormat::Tsv),
+    };
+    let mut written = Vec::new();
+    for (dest, contents) in files {
+        std::fs::write(&dest, contents).map_err(|e| e.to_string())?;
+        written.push(dest.to_string_lossy().to_string());
+    }
+    Ok(written)
+}
+
+fn with_checkpoints(app: &AppHandle, mut options: BatchOptions) -> Result<BatchOptions, String> {
+    if options.checkpoints.is_none() {
+        options.checkpoints = Some(read_saved_plan(app)?);
+    }
+    Ok(options)
+}
+
+#[tauri::command]
+pub fn batch_run_stats(
+    app: AppHandle,
+    runs: Vec<Run>,
+    options: BatchOptions,
+) -> Result<BatchReport, String> {
+    let runs: Vec<(String, Run)> = runs
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let name = match r.meta.run_name.trim() {
+                "" => format!("run {}", i + 1),
+                n => n.to_string(),
+            };
+            (name, r)
+        })
+        .collect();
+    analyze(&runs, &with_checkpoints(&app, options)?)
+}
+
+/// Runs the newest `limit` library entries matching `query`. Plans and
+/// entries without poses are listed in `skipped`.
+#[tauri::command]
+pub fn batch_library_stats(
+    app: AppHandle,
+    query: LibraryQuery,
+    limit: Option<usize>,
+    options: BatchOptions,
+) -> Result<BatchReport, String> {
+    let entries = library::search_library(app.clone(), query)?;
+    let mut runs = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in entries {
+        if limit.is_some_and(|n| runs.len() >= n) {
+            break;
+        }
+        if entry.source == EntrySource::Plan || entry.pose_count == 0 {
+            skipped.push(format!("{}: no recorded poses", entry.name));
+            continue;
+        }
+        let loaded = std::fs::read_to_string(library::entry_path(&app, &entry.id)?)
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).map_err(|e| e.to_string()))
+            .and_then(|mut v| {
+                // Saved-paths documents also carry the plan.
+                if let Some(obj) = v.as_object_mut() {
+                    obj.remove("planned-path");
+                }
+                run::from_value(v).map_err(|e| e.to_string())
+            });
+        match loaded {
+            Ok(run) if !run.poses.is_empty() => runs.push((entry.name, run)),
+            Ok(_) => skipped.push(format!("{}: no recorded poses", entry.name)),
+            Err(e) => skipped.push(format!("{}: {e}", entry.name)),
+        }
+    }
+    let mut report = analyze(&runs, &with_checkpoints(&app, options)?)?;
+    report.skipped = skipped;
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn export_batch_stats(
+    report: BatchReport,
+    path: String,
+    format: Option<BatchFormat>,
+) -> Result<Vec<String>, String> {
+    export(&report, Path::new(&path), format.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spread(points: &[(f64, f64)]) -> PoseSpread {
+        let poses: Vec<BatchPose> = points
+            .iter()
+            .map(|&(x, y)| BatchPose {
+                t: 0,
+                x,
+                y,
+                theta: 0.0,
+            })
+            .collect();
+        PoseSpread::of(&poses, 2.0)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn ellipse_follows_the_covariance() {
+        // Covariance [[20, 16], [16, 20]] / 3: eigenvalues 12 and 4/3, with
+        // the major axis pointing north-east.
+        let s = spread(&[(3.0, 3.0), (-3.0, -3.0), (1.0, -1.0), (-1.0, 1.0)]);
+        assert!(close(s.cov_xx, 20.0 / 3.0) && close(s.cov_yy, 20.0 / 3.0));
+        assert!(close(s.cov_xy, 16.0 / 3.0));
+        assert!(close(s.ellipse.semi_major, 2.0 * 12f64.sqrt()));
+        assert!(close(s.ellipse.semi_minor, 2.0 * (4.0f64 / 3.0).sqrt()));
+        assert!(close(s.ellipse.rotation, 45.0));
+        assert!(close(s.max_distance, 18f64.sqrt()));
+
+        let mirrored = spread(&[(-3.0, 3.0), (3.0, -3.0), (-1.0, -1.0), (1.0, 1.0)]);
+        assert!(close(mirrored.ellipse.rotation, 135.0));
+
+        let upright = spread(&[(0.0, 2.0), (0.0, -2.0), (1.0, 0.0), (-1.0, 0.0)]);
+        assert!(close(upright.ellipse.rotation, 0.0));
+        assert!(close(
+            upright.ellipse.semi_major,
+            2.0 * (8.0f64 / 3.0).sqrt()
+        ));
+        assert!(close(
+            upright.ellipse.semi_minor,
+            2.0 * (2.0f64 / 3.0).sqrt()
+        ));
+    }
+}
@@ -0,0 +1,560 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::plan::{read_saved_plan, Waypoint};
+use crate::settings::{self, write_atomic_no_backup};
+
+/// First line of everything we generate. Files that start with it may be
+/// overwritten by `write_plan_to_pros_project`.
+const GENERATED_MARKER: &str = "// Generated by MotionView";
+const DEFAULT_HEADER_NAME: &str = "motionview_path.hpp";
+const MAX_SPEED: f64 = 127.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetLibrary {
+    LemLib,
+    EzTemplate,
+    OkApi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputKind {
+    /// Just the calls, to paste into an existing auton.
+    Snippet,
+    /// A self-contained header with an inline function.
+    Header,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    In,
+    Cm,
+    Mm,
+    Ft,
+    Tiles,
+}
+
+impl LengthUnit {
+    fn per_inch(self) -> f64 {
+        match self {
+            LengthUnit::In => 1.0,
+            LengthUnit::Cm => 2.54,
+            LengthUnit::Mm => 25.4,
+            LengthUnit::Ft => 1.0 / 12.0,
+            LengthUnit::Tiles => 1.0 / 24.0,
+        }
+    }
+
+    /// okapi unit literal suffix (EZ-Template uses okapi units too).
+    fn literal(self) -> &'static str {
+        match self {
+            LengthUnit::In => "_in",
+            LengthUnit::Cm => "_cm",
+            LengthUnit::Mm => "_mm",
+            LengthUnit::Ft => "_ft",
+            LengthUnit::Tiles => "_tile",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LengthUnit::In => "in",
+            LengthUnit::Cm => "cm",
+            LengthUnit::Mm => "mm",
+            LengthUnit::Ft => "ft",
+            LengthUnit::Tiles => "tiles",
+        }
+    }
+}
+
+/// Plans are drawn with compass headings (0° = +Y, clockwise), which is what
+/// LemLib, EZ-Template and okapi all use by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingConvention {
+    Compass,
+    /// 0° = +X, counter-clockwise.
+    Math,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CodegenOptions {
+    pub library: TargetLibrary,
+    pub output: OutputKind,
+    pub units: LengthUnit,
+    pub heading: HeadingConvention,
+    /// Per-move timeout. Only LemLib takes one per call.
+    pub timeout_ms: u32,
+    /// 0..=127
+    pub max_speed: f64,
+    pub chassis: String,
+    pub function_name: String,
+    /// Set the odometry pose to the first waypoint instead of driving to it.
+    pub set_initial_pose: bool,
+    pub precision: usize,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        CodegenOptions {
+            library: TargetLibrary::LemLib,
+            output: OutputKind::Snippet,
+            units: LengthUnit::In,
+            heading: HeadingConvention::Compass,
+            timeout_ms: 2000,
+            max_speed: MAX_SPEED,
+            chassis: "chassis".to_string(),
+            function_name: "motionview_path".to_string(),
+            set_initial_pose: true,
+            precision: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedCode {
+    pub code: String,
+    pub waypoints: usize,
+    pub warnings: Vec<String>,
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn check_options(opts: &CodegenOptions) -> Result<(), String> {
+    if !is_identifier(&opts.chassis) {
+        return Err(format!(
+            "chassis: {:?} is not a C++ identifier",
+            opts.chassis
+        ));
+    }
+    if !is_identifier(&opts.function_name) {
+        return Err(format!(
+            "function_name: {:?} is not a C++ identifier",
+            opts.function_name
+        ));
+    }
+    if !(opts.max_speed > 0.0 && opts.max_speed <= MAX_SPEED) {
+        return Err(format!(
+            "max_speed: expected 1 to 127, found {}",
+            opts.max_speed
+        ));
+    }
+    if opts.precision > 6 {
+        return Err("precision: at most 6 decimals".into());
+    }
+    Ok(())
+}
+
+/// Fixed decimals with trailing zeros trimmed, so 24.00 prints as 24.
+fn num(v: f64, precision: usize) -> String {
+    let s = format!("{v:.precision$}");
+    let s = if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    };
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s
+    }
+}
+
+struct Emitter<'a> {
+    opts: &'a CodegenOptions,
+}
+
+impl Emitter<'_> {
+    fn len(&self, inches: f64) -> String {
+        num(inches * self.opts.units.per_inch(), self.opts.precision)
+    }
+
+    fn heading(&self, compass_deg: f64) -> String {
+        let deg = match self.opts.heading {
+            HeadingConvention::Compass => compass_deg,
+            HeadingConvention::Math => 90.0 - compass_deg,
+        };
+        num(deg.rem_euclid(360.0), self.opts.precision)
+    }
+
+    fn len_lit(&self, inches: f64) -> String {
+        format!("{}{}", self.len(inches), self.opts.units.literal())
+    }
+
+    fn heading_lit(&self, compass_deg: f64) -> String {
+        format!("{}_deg", self.heading(compass_deg))
+    }
+
+    fn speed(&self) -> String {
+        num(self.opts.max_speed, 0)
+    }
+
+    fn lemlib(&self, waypoints: &[Waypoint], out: &mut Vec<String>) {
+        let c = &self.opts.chassis;
+        let (first, rest) = self.split(waypoints);
+        if let Some(w) = first {
+            out.push(format!(
+                "{c}.setPose({}, {}, {});",
+                self.len(w.x),
+                self.len(w.y),
+                self.heading(w.theta)
+            ));
+        }
+        for w in rest {
+            out.push(format!(
+                "{c}.moveToPose({}, {}, {}, {}, {{.maxSpeed = {}}});",
+                self.len(w.x),
+                self.len(w.y),
+                self.heading(w.theta),
+                self.opts.timeout_ms,
+                self.speed()
+            ));
+        }
+        out.push(format!("{c}.waitUntilDone();"));
+    }
+
+    fn ez_template(&self, waypoints: &[Waypoint], out: &mut Vec<String>) {
+        let c = &self.opts.chassis;
+        let (first, rest) = self.split(waypoints);
+        if let Some(w) = first {
+            out.push(format!(
+                "{c}.odom_xyt_set({}, {}, {});",
+                self.len_lit(w.x),
+                self.len_lit(w.y),
+                self.heading_lit(w.theta)
+            ));
+        }
+        for w in rest {
+            out.push(format!(
+                "{c}.pid_odom_set({{{{{}, {}, {}}}, fwd, {}}}, true);",
+                self.len_lit(w.x),
+                self.len_lit(w.y),
+                self.heading_lit(w.theta),
+                self.speed()
+            ));
+            out.push(format!("{c}.pid_wait();"));
+        }
+    }
+
+    fn okapi(&self, waypoints: &[Waypoint], out: &mut Vec<String>) {
+        let c = &self.opts.chassis;
+        out.push(format!(
+            "{c}->setDefaultStateMode(okapi::StateMode::CARTESIAN);"
+        ));
+        if self.opts.max_speed < MAX_SPEED {
+            out.push(format!(
+                "{c}->setMaxVelocity({c}->getMaxVelocity() * {} / 127.0);",
+                self.speed()
+            ));
+        }
+        let (first, rest) = self.split(waypoints);
+        if let Some(w) = first {
+            out.push(format!(
+                "{c}->setState({{{}, {}, {}}});",
+                self.len_lit(w.x),
+                self.len_lit(w.y),
+                self.heading_lit(w.theta)
+            ));
+        }
+        for w in rest {
+            out.push(format!(
+                "{c}->driveToPoint({{{}, {}}});",
+                self.len_lit(w.x),
+                self.len_lit(w.y)
+            ));
+            out.push(format!("{c}->turnToAngle({});", self.heading_lit(w.theta)));
+        }
+    }
+
+    fn split<'w>(&self, waypoints: &'w [Waypoint]) -> (Option<&'w Waypoint>, &'w [Waypoint]) {
+        match waypoints.split_first() {
+            Some((first, rest)) if self.opts.set_initial_pose => (Some(first), rest),
+            _ => (None, waypoints),
+        }
+    }
+}
+
+fn describe(opts: &CodegenOptions, count: usize) -> String {
+    let heading = match opts.heading {
+        HeadingConvention::Compass => "compass (0 = +Y, clockwise)",
+        HeadingConvention::Math => "math (0 = +X, counter-clockwise)",
+    };
+    format!(
+        "// {count} waypoints. Lengths: {}. Headings: degrees, {heading}.",
+        opts.units.name()
+    )
+}
+
+/// Turns a plan into C++ for the chosen library.
+pub fn generate(waypoints: &[Waypoint], opts: &CodegenOptions) -> Result<GeneratedCode, String> {
+    check_options(opts)?;
+    let mut warnings = Vec::new();
+    if waypoints.is_empty() {
+        warnings.push("the plan has no waypoints".to_string());
+    }
+    if opts.library != TargetLibrary::LemLib {
+        warnings.push(
+            "timeout_ms is not applied: this library ends motions with its own exit conditions"
+                .to_string(),
+        );
+    }
+
+    let emitter = Emitter { opts };
+    let mut body = Vec::new();
+    match opts.library {
+        TargetLibrary::LemLib => emitter.lemlib(waypoints, &mut body),
+        TargetLibrary::EzTemplate => emitter.ez_template(waypoints, &mut body),
+        TargetLibrary::OkApi => emitter.okapi(waypoints, &mut body),
+    }
+    let uses_literals = opts.library != TargetLibrary::LemLib;
+
+    let mut code = format!(
+        "{GENERATED_MARKER} from the planned path. Regenerate instead of editing.\n{}\n",
+        describe(opts, waypoints.len())
+    );
+    match opts.output {
+        OutputKind::Snippet => {
+            for line in &body {
+                code.push_str(line);
+                code.push('\n');
+            }
+        }
+        OutputKind::Header => {
+            let (include, chassis_decl) = match opts.library {
+                TargetLibrary::LemLib => ("lemlib/api.hpp", "lemlib::Chassis"),
+                TargetLibrary::EzTemplate => ("EZ-Template/api.hpp", "ez::Drive"),
+                TargetLibrary::OkApi => (
+                    "okapi/api.hpp",
+                    "std::shared_ptr<okapi::OdomChassisController>",
+                ),
+            };
+            code.push_str("#pragma once\n\n");
+            code.push_str(&format!("#include \"{include}\"  // IWYU pragma: keep\n\n"));
+            code.push_str(&format!("extern {chassis_decl} {};\n\n", opts.chassis));
+            code.push_str(&format!("inline void {}() {{\n", opts.function_name));
+            if uses_literals {
+                code.push_str("  using namespace okapi::literals;\n");
+            }
+            for line in &body {
+                code.push_str("  ");
+                code.push_str(line);
+                code.push('\n');
+            }
+            code.push_str("}\n");
+        }
+    }
+    Ok(GeneratedCode {
+        code,
+        waypoints: waypoints.len(),
+        warnings,
+    })
+}
+
+/// `include/<name>` inside the PROS project at `pros_dir`.
+fn project_header_path(pros_dir: &str, file_name: Option<&str>) -> Result<PathBuf, String> {
+    if pros_dir.is_empty() {
+        return Err("no PROS project directory is configured".into());
+    }
+    let project = Path::new(pros_dir);
+    if !project.is_dir() {
+        return Err(format!(
+            "PROS project directory {} is not available",
+            project.display()
+        ));
+    }
+    if !project.join("project.pros").is_file() {
+        return Err(format!(
+            "{} is not a PROS project (no project.pros)",
+            project.display()
+        ));
+    }
+    let name = file_name.map(str::trim).unwrap_or(DEFAULT_HEADER_NAME);
+    let plain = Path::new(name).file_name().and_then(|n| n.to_str()) == Some(name);
+    if name.is_empty() || !plain {
+        return Err(format!("{name:?} is not a plain file name"));
+    }
+    let name = if name.ends_with(".hpp") || name.ends_with(".h") {
+        name.to_string()
+    } else {
+        format!("{name}.hpp")
+    };
+    let dir = project.join("include");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+/// Refuses to replace a file MotionView didn't generate unless `overwrite`.
+fn write_header(path: &Path, code: String, overwrite: bool) -> Result<(), String> {
+    if path.exists() && !overwrite {
+        let existing = std::fs::read_to_string(path).unwrap_or_default();
+        if !existing.starts_with(GENERATED_MARKER) {
+            return Err(format!(
+                "{} already exists and was not generated by MotionView",
+                path.display()
+            ));
+        }
+    }
+    write_atomic_no_backup(path, code)
+}
+
+/// Generates code for `waypoints`, or for the saved plan when none are given.
+#[tauri::command]
+pub fn generate_plan_code(
+    app: AppHandle,
+    options: CodegenOptions,
+    waypoints: Option<Vec<Waypoint>>,
+) -> Result<GeneratedCode, String> {
+    let waypoints = match waypoints {
+        Some(w) => w,
+        None => read_saved_plan(&app)?,
+    };
+    generate(&waypoints, &options)
+}
+
+/// Writes the plan as a header into the PROS project's `include/` dir.
+/// Refuses to replace a file MotionView didn't generate unless `overwrite`.
+/// Returns the path written.
+#[tauri::command]
+pub fn write_plan_to_pros_project(
+    app: AppHandle,
+    options: CodegenOptions,
+    waypoints: Option<Vec<Waypoint>>,
+    file_name: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<String, String> {
+    let settings = settings::load_settings(&app)?.unwrap_or_default();
+    let path = project_header_path(&settings.pros_dir, file_name.as_deref())?;
+    let options = CodegenOptions {
+        output: OutputKind::Header,
+        ..options
+    };
+    let generated = generate_plan_code(app, options, waypoints)?;
+    write_header(&path, generated.code, overwrite.unwrap_or(false))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: [Waypoint; 2] = [
+        Waypoint {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+        },
+        Waypoint {
+            x: 24.0,
+            y: 48.0,
+            theta: 90.0,
+        },
+    ];
+
+    fn body(opts: &CodegenOptions) -> Vec<String> {
+        let generated = generate(&PLAN, opts).unwrap();
+        assert_eq!(generated.waypoints, 2);
+        let lines: Vec<String> = generated.code.lines().map(String::from).collect();
+        assert!(lines[0].starts_with(GENERATED_MARKER));
+        lines[1..].to_vec()
+    }
+
+    #[test]
+    fn lemlib_snippet_and_header() {
+        assert_eq!(
+            body(&CodegenOptions::default()),
+            [
+                "// 2 waypoints. Lengths: in. Headings: degrees, compass (0 = +Y, clockwise).",
+                "chassis.setPose(0, 0, 0);",
+                "chassis.moveToPose(24, 48, 90, 2000, {.maxSpeed = 127});",
+                "chassis.waitUntilDone();",
+            ]
+        );
+
+        let header = CodegenOptions {
+            output: OutputKind::Header,
+            ..CodegenOptions::default()
+        };
+        let generated = generate(&PLAN, &header).unwrap();
+        assert!(generated.warnings.is_empty());
+        let code = generated.code;
+        assert!(code.contains("#pragma once\n\n#include \"lemlib/api.hpp\""));
+        assert!(code.contains("extern lemlib::Chassis chassis;\n"));
+        assert!(code.contains("inline void motionview_path() {\n  chassis.setPose(0, 0, 0);\n"));
+        assert!(code.ends_with("  chassis.waitUntilDone();\n}\n"));
+    }
+
+    #[test]
+    fn ez_template_snippet_in_tiles() {
+        let opts = CodegenOptions {
+            library: TargetLibrary::EzTemplate,
+            units: LengthUnit::Tiles,
+            ..CodegenOptions::default()
+        };
+        assert_eq!(
+            body(&opts),
+            [
+                "// 2 waypoints. Lengths: tiles. Headings: degrees, compass (0 = +Y, clockwise).",
+                "chassis.odom_xyt_set(0_tile, 0_tile, 0_deg);",
+                "chassis.pid_odom_set({{1_tile, 2_tile, 90_deg}, fwd, 127}, true);",
+                "chassis.pid_wait();",
+            ]
+        );
+        assert_eq!(generate(&PLAN, &opts).unwrap().warnings.len(), 1);
+    }
+
+    #[test]
+    fn okapi_snippet_with_math_headings() {
+        let opts = CodegenOptions {
+            library: TargetLibrary::OkApi,
+            heading: HeadingConvention::Math,
+            max_speed: 100.0,
+            ..CodegenOptions::default()
+        };
+        assert_eq!(
+            body(&opts),
+            [
+                "// 2 waypoints. Lengths: in. Headings: degrees, math (0 = +X, counter-clockwise).",
+                "chassis->setDefaultStateMode(okapi::StateMode::CARTESIAN);",
+                "chassis->setMaxVelocity(chassis->getMaxVelocity() * 100 / 127.0);",
+                "chassis->setState({0_in, 0_in, 90_deg});",
+                "chassis->driveToPoint({24_in, 48_in});",
+                "chassis->turnToAngle(0_deg);",
+            ]
+        );
+    }
+
+    #[test]
+    fn header_never_replaces_a_hand_written_file() {
+        let project = std::env::temp_dir().join(format!("mv-codegen-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&project);
+        std::fs::create_dir_all(&project).unwrap();
+        let pros_dir = project.to_string_lossy().to_string();
+        assert!(project_header_path(&pros_dir, None).is_err());
+        std::fs::write(project.join("project.pros"), "{}").unwrap();
+        assert!(project_header_path(&pros_dir, Some("../auton.hpp")).is_err());
+
+        let path = project_header_path(&pros_dir, Some("auton")).unwrap();
+        assert_eq!(path, project.join("include").join("auton.hpp"));
+        std::fs::write(&path, "// my auton\n").unwrap();
+        let code = generate(&PLAN, &CodegenOptions::default()).unwrap().code;
+        let err = write_header(&path, code.clone(), false).unwrap_err();
+        assert!(err.contains("was not generated by MotionView"), "{err}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "// my auton\n");
+
+        write_header(&path, code.clone(), true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), code);
+        // Once it is ours, regenerating needs no overwrite.
+        write_header(&path, code, false).unwrap();
+        std::fs::remove_dir_all(&project).unwrap();
+    }
+}
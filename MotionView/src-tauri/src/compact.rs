@@ -0,0 +1,552 @@
+//! Compact binary run container (`.mvrun`).
+//!
+//! Layout, all integers LEB128 varints unless noted:
+//!
+//! ```text
+//! "MVRB" | format u8 | header json (len + bytes: version, meta, thinning)
+//! pose count | 6 channel scales (u8 each) | poses...
+//! string table (count + strings) | watch count | watches...
+//! ```
+//!
+//! Each pose channel (x, y, theta, l_vel, r_vel, speed) is stored as a zigzag
+//! delta of `round(v * 10^scale)`. The scale is the smallest number of decimals
+//! that reproduces every value in the run bit-for-bit, so converting back to
+//! JSON is lossless; a channel that no scale fits is stored as raw `f64`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::RunEvent;
+use crate::run::{self, Run, RunMeta, Thinning};
+use crate::telemetry::{wheel_speed, Pose, Watch};
+
+pub const MAGIC: &[u8; 4] = b"MVRB";
+const FORMAT_VERSION: u8 = 1;
+const MAX_SCALE: u8 = 6;
+const RAW_F64: u8 = 0xFF;
+/// `speed` equals `wheel_speed(l_vel, r_vel)` for every pose and is omitted.
+const DERIVED: u8 = 0xFE;
+
+const HAS_L_VEL: u8 = 1 << 0;
+const HAS_R_VEL: u8 = 1 << 1;
+const HAS_NEG_ZERO: u8 = 1 << 2;
+
+const CHANNELS: usize = 6;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u64,
+    meta: RunMeta,
+    thinning: Option<Thinning>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    events: Vec<RunEvent>,
+}
+
+fn channels(p: &Pose) -> [Option<f64>; CHANNELS] {
+    [
+        Some(p.x),
+        Some(p.y),
+        Some(p.theta),
+        p.l_vel,
+        p.r_vel,
+        Some(p.speed),
+    ]
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    loop {
+        let byte = (v & 0x7F) as u8;
+        v >>= 7;
+        if v == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn quantize(v: f64, scale: u8) -> Option<i64> {
+    let factor = 10f64.powi(scale as i32);
+    let q = (v * factor).round();
+    // Keep well inside the range where f64 holds integers exactly.
+    if q.abs() >= 9.0e15 || q / factor != v {
+        return None;
+    }
+    Some(q as i64)
+}
+
+fn pick_scale(values: impl Iterator<Item = f64> + Clone) -> u8 {
+    (0..=MAX_SCALE)
+        .find(|s| values.clone().all(|v| quantize(v, *s).is_some()))
+        .unwrap_or(RAW_F64)
+}
+
+pub fn encode(run: &Run) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    let header = Header {
+        version: run.version,
+        meta: run.meta.clone(),
+        thinning: run.thinning.clone(),
+        events: run.events.clone(),
+    };
+    write_str(
+        &mut out,
+        &serde_json::to_string(&header).map_err(|e| e.to_string())?,
+    );
+
+    let poses = &run.poses;
+    let mut scales = [0u8; CHANNELS];
+    for (c, scale) in scales.iter_mut().enumerate() {
+        // Negative zero is flagged per pose, so it only needs to quantize as 0.
+        *scale = pick_scale(poses.iter().filter_map(move |p| channels(p)[c]));
+    }
+    if poses
+        .iter()
+        .all(|p| p.speed.to_bits() == wheel_speed(p.l_vel, p.r_vel).to_bits())
+    {
+        scales[5] = DERIVED;
+    }
+
+    write_varint(&mut out, poses.len() as u64);
+    out.extend_from_slice(&scales);
+    let mut prev_t = 0u64;
+    let mut prev_q = [0i64; CHANNELS];
+    for p in poses {
+        let values = channels(p);
+        let mut flags = 0u8;
+        if p.l_vel.is_some() {
+            flags |= HAS_L_VEL;
+        }
+        if p.r_vel.is_some() {
+            flags |= HAS_R_VEL;
+        }
+        let mut neg_zero = 0u8;
+        for (c, v) in values.iter().enumerate() {
+            if matches!(v, Some(v) if *v == 0.0 && v.is_sign_negative()) {
+                neg_zero |= 1 << c;
+            }
+        }
+        if neg_zero != 0 {
+            flags |= HAS_NEG_ZERO;
+        }
+        out.push(flags);
+        if neg_zero != 0 {
+            out.push(neg_zero);
+        }
+        // Signed, so an unsorted run still round-trips.
+        write_varint(&mut out, zigzag(p.t as i64 - prev_t as i64));
+        prev_t = p.t;
+        for (c, v) in values.iter().enumerate() {
+            let v = match v {
+                Some(v) => *v,
+                None => continue,
+            };
+            match scales[c] {
+                DERIVED => {}
+                RAW_F64 => out.extend_from_slice(&v.to_le_bytes()),
+                scale => {
+                    let q = quantize(v, scale).ok_or("quantization failed")?;
+                    write_varint(&mut out, zigzag(q - prev_q[c]));
+                    prev_q[c] = q;
+                }
+            }
+        }
+    }
+
+    let mut strings: Vec<&str> = Vec::new();
+    let mut index: HashMap<&str, u64> = HashMap::new();
+    let mut ids: Vec<(u64, u64)> = Vec::with_capacity(run.watches.len());
+    for w in &run.watches {
+        let mut pair = [0u64; 2];
+        for (slot, s) in pair.iter_mut().zip([w.level.as_str(), w.label.as_str()]) {
+            *slot = *index.entry(s).or_insert_with(|| {
+                strings.push(s);
+                strings.len() as u64 - 1
+            });
+        }
+        ids.push((pair[0], pair[1]));
+    }
+    write_varint(&mut out, strings.len() as u64);
+    for s in &strings {
+        write_str(&mut out, s);
+    }
+    write_varint(&mut out, run.watches.len() as u64);
+    let mut prev_t = 0u64;
+    for (w, (level, label)) in run.watches.iter().zip(ids) {
+        write_varint(&mut out, zigzag(w.t as i64 - prev_t as i64));
+        prev_t = w.t;
+        write_varint(&mut out, level);
+        write_varint(&mut out, label);
+        write_str(&mut out, &w.value);
+    }
+    Ok(out)
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self
+            .buf
+            .get(self.pos)
+            .ok_or_else(|| format!("unexpected end of file at byte {}", self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&[u8], String> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or_else(|| format!("unexpected end of file at byte {}", self.pos))?;
+        let out = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= ((b & 0x7F) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(format!("varint too long at byte {}", self.pos))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.varint()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Ok(f64::from_le_bytes(b))
+    }
+}
+
+fn step(prev: u64, delta: u64) -> Result<u64, String> {
+    (prev as i64)
+        .checked_add(unzigzag(delta))
+        .and_then(|t| u64::try_from(t).ok())
+        .ok_or_else(|| "timestamp out of range".to_string())
+}
+
+pub fn decode(buf: &[u8]) -> Result<Run, String> {
+    if !is_compact(buf) {
+        return Err("not a MotionView binary run".into());
+    }
+    let mut r = Reader { buf, pos: 4 };
+    let format = r.byte()?;
+    if format != FORMAT_VERSION {
+        return Err(format!("unsupported binary run format {format}"));
+    }
+    let header: Header = serde_json::from_str(&r.string()?).map_err(|e| e.to_string())?;
+
+    let count = r.varint()? as usize;
+    let mut scales = [0u8; CHANNELS];
+    scales.copy_from_slice(r.bytes(CHANNELS)?);
+    // Each pose takes at least two bytes; don't trust a huge count blindly.
+    let mut poses = Vec::with_capacity(count.min(buf.len() / 2));
+    let mut prev_t = 0u64;
+    let mut prev_q = [0i64; CHANNELS];
+    for _ in 0..count {
+        let flags = r.byte()?;
+        let neg_zero = if flags & HAS_NEG_ZERO != 0 {
+            r.byte()?
+        } else {
+            0
+        };
+        let t = step(prev_t, r.varint()?)?;
+        prev_t = t;
+        let mut values = [None; CHANNELS];
+        for (c, slot) in values.iter_mut().enumerate() {
+            let present = match c {
+                3 => flags & HAS_L_VEL != 0,
+                4 => flags & HAS_R_VEL != 0,
+                _ => true,
+            };
+            if !present || scales[c] == DERIVED {
+                continue;
+            }
+            let v = match scales[c] {
+                RAW_F64 => r.f64()?,
+                scale => {
+                    prev_q[c] = prev_q[c].wrapping_add(unzigzag(r.varint()?));
+                    prev_q[c] as f64 / 10f64.powi(scale as i32)
+                }
+            };
+            *slot = Some(if neg_zero & (1 << c) != 0 { -0.0 } else { v });
+        }
+        let mut pose = Pose::new(
+            t,
+            values[0].unwrap_or(0.0),
+            values[1].unwrap_or(0.0),
+            values[2].unwrap_or(0.0),
+            values[3],
+            values[4],
+        );
+        if let Some(speed) = values[5] {
+            pose.speed = speed;
+        }
+        poses.push(pose);
+    }
+
+    let string_count = r.varint()? as usize;
+    let strings = (0..string_count)
+        .map(|_| r.string())
+        .collect::<Result<Vec<_>, _>>()?;
+    let lookup = |i: u64| -> Result<String, String> {
+        strings
+            .get(i as usize)
+            .cloned()
+            .ok_or_else(|| format!("string index {i} out of range"))
+    };
+    let count = r.varint()? as usize;
+    let mut watches = Vec::with_capacity(count.min(buf.len() / 4));
+    let mut prev_t = 0u64;
+    for _ in 0..count {
+        let t = step(prev_t, r.varint()?)?;
+        prev_t = t;
+        let level = lookup(r.varint()?)?;
+        let label = lookup(r.varint()?)?;
+        let value = r.string()?;
+        watches.push(Watch {
+            t,
+            level,
+            label,
+            value,
+        });
+    }
+    if r.pos != buf.len() {
+        return Err(format!("{} trailing bytes after run", buf.len() - r.pos));
+    }
+
+    Ok(Run {
+        version: header.version,
+        meta: header.meta,
+        thinning: header.thinning,
+        poses,
+        watches,
+        events: header.events,
+    })
+}
+
+pub fn is_compact(buf: &[u8]) -> bool {
+    buf.starts_with(MAGIC)
+}
+
+/// Encodes and proves the result decodes to the same JSON before it is used.
+pub fn encode_checked(run: &Run) -> Result<Vec<u8>, String> {
+    let bytes = encode(run)?;
+    let back = decode(&bytes)?;
+    let a = serde_json::to_string(run).map_err(|e| e.to_string())?;
+    let b = serde_json::to_string(&back).map_err(|e| e.to_string())?;
+    if a != b {
+        return Err("binary encoding did not round-trip; keeping JSON".into());
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvertReport {
+    pub output: String,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+}
+
+fn report(input: &Path, output: &Path) -> Result<ConvertReport, String> {
+    let size = |p: &Path| {
+        std::fs::metadata(p)
+            .map(|m| m.len())
+            .map_err(|e| e.to_string())
+    };
+    Ok(ConvertReport {
+        output: output.to_string_lossy().to_string(),
+        input_bytes: size(input)?,
+        output_bytes: size(output)?,
+    })
+}
+
+#[tauri::command]
+pub fn convert_run_to_compact(
+    path: String,
+    out_path: Option<String>,
+) -> Result<ConvertReport, String> {
+    let input = Path::new(&path);
+    let output = out_path
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| input.with_extension("mvrun"));
+    let run = run::read_run_file(input)?;
+    let bytes = encode_checked(&run)?;
+    std::fs::write(&output, bytes).map_err(|e| e.to_string())?;
+    report(input, &output)
+}
+
+#[tauri::command]
+pub fn convert_compact_to_json(
+    path: String,
+    out_path: Option<String>,
+) -> Result<ConvertReport, String> {
+    let input = Path::new(&path);
+    let output = out_path
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| input.with_extension("json"));
+    let run = run::read_run_file(input)?;
+    run::write_run_file(&output, &run)?;
+    report(input, &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(p: &Pose) -> Vec<Option<u64>> {
+        let mut out: Vec<Option<u64>> = channels(p).iter().map(|v| v.map(f64::to_bits)).collect();
+        out.push(Some(p.t));
+        out
+    }
+
+    fn round_trip(run: &Run) -> Run {
+        let back = decode(&encode(run).unwrap()).unwrap();
+        assert_eq!(back.poses.len(), run.poses.len());
+        for (a, b) in run.poses.iter().zip(&back.poses) {
+            assert_eq!(bits(a), bits(b), "{a:?} came back as {b:?}");
+        }
+        assert_eq!(back.watches, run.watches);
+        assert_eq!(back.meta, run.meta);
+        back
+    }
+
+    fn watch(t: u64, label: &str, value: &str) -> Watch {
+        Watch {
+            t,
+            level: "INFO".into(),
+            label: label.into(),
+            value: value.into(),
+        }
+    }
+
+    fn run(poses: Vec<Pose>, watches: Vec<Watch>) -> Run {
+        let meta = RunMeta {
+            run_name: "skills".into(),
+            ..RunMeta::default()
+        };
+        Run::new(meta, poses, watches)
+    }
+
+    #[test]
+    fn round_trips_typical_run() {
+        let poses = (0..50)
+            .map(|i| {
+                let f = i as f64;
+                Pose::new(
+                    i * 10,
+                    f * 0.25 - 3.0,
+                    36.12 - f,
+                    271.3,
+                    Some(63.5),
+                    Some(-f),
+                )
+            })
+            .collect();
+        let watches = vec![
+            watch(0, "Auton", "start"),
+            watch(120, "Pose", "(12.00, -3.50, 90.00)"),
+            watch(120, "Auton", "done"),
+        ];
+        round_trip(&run(poses, watches));
+    }
+
+    #[test]
+    fn keeps_negative_zero() {
+        let mut p = Pose::new(5, -0.0, 0.0, -0.0, Some(-0.0), None);
+        p.speed = -0.0;
+        let back = round_trip(&run(
+            vec![p, Pose::new(10, 1.5, -0.0, 0.0, None, None)],
+            vec![],
+        ));
+        assert!(back.poses[0].x.is_sign_negative());
+        assert!(!back.poses[0].y.is_sign_negative());
+    }
+
+    #[test]
+    fn keeps_huge_and_awkward_values() {
+        let values = [
+            1e300,
+            -f64::MAX,
+            f64::MIN_POSITIVE,
+            0.1 + 0.2,
+            9.0e15,
+            123456.789012,
+        ];
+        let poses = values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| Pose::new(i as u64, v, -v, v, Some(v), None))
+            .collect();
+        round_trip(&run(poses, vec![]));
+    }
+
+    #[test]
+    fn keeps_unsorted_times() {
+        let poses = vec![
+            Pose::new(u64::MAX / 4, 0.0, 0.0, 0.0, None, None),
+            Pose::new(3, 1.0, 1.0, 1.0, None, None),
+            Pose::new(7, 2.0, 2.0, 2.0, None, None),
+        ];
+        round_trip(&run(poses, vec![watch(9, "a", ""), watch(1, "b", "")]));
+    }
+
+    #[test]
+    fn rejects_damaged_input() {
+        let bytes = encode(&run(vec![Pose::new(0, 1.0, 2.0, 3.0, None, None)], vec![])).unwrap();
+        assert!(is_compact(&bytes));
+        assert!(!is_compact(b"{\"version\":1}"));
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(decode(&extra).is_err());
+        let mut future = bytes;
+        future[4] = FORMAT_VERSION + 1;
+        assert!(decode(&future).is_err());
+    }
+
+    #[test]
+    fn checked_encoding_matches_json() {
+        let r = run(
+            vec![Pose::new(0, -0.0, 1e300, 359.99, Some(1.0), Some(2.0))],
+            vec![watch(0, "x", "y")],
+        );
+        let back = decode(&encode_checked(&r).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&back).unwrap(),
+            serde_json::to_string(&r).unwrap()
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use serde::{Deserialize, Serialize};
+
+use crate::deviation::ErrorStats;
+use crate::plan::angle_delta;
+use crate::run::{read_run_file, Run};
+use crate::telemetry::Pose;
+
+/// Largest DTW cost matrix we build, in cells.
+const MAX_DTW_CELLS: usize = 16_000_000;
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum RunAlignment {
+    /// Line the runs up at their first pose.
+    #[default]
+    Start,
+    /// Line the runs up at the first watch with this label (and value).
+    Watch {
+        label: String,
+        #[serde(default)]
+        value: Option<String>,
+    },
+    /// Dynamic time warping on position, so a slower attempt along the same
+    /// path still lines up.
+    Dtw {
+        /// Max index distance from the diagonal, in samples.
+        #[serde(default)]
+        window: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CompareOptions {
+    pub align: RunAlignment,
+    /// Index of the run the others are compared with.
+    pub reference: usize,
+    /// Runs are resampled to this interval before comparing.
+    pub sample_ms: u64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            align: RunAlignment::default(),
+            reference: 0,
+            sample_ms: 20,
+        }
+    }
+}
+
+/// Another run minus the reference run at one reference sample. Lengths in
+/// inches, heading in degrees, speed on the run's wheel scale.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffSample {
+    /// Index of the reference sample.
+    pub index: usize,
+    /// Reference run time.
+    pub t: u64,
+    /// Matching time in the other run.
+    pub other_t: u64,
+    pub dx: f64,
+    pub dy: f64,
+    pub distance: f64,
+    pub heading: f64,
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedRun {
+    /// Index into the runs given.
+    pub run: usize,
+    /// Other anchor minus reference anchor; `None` for DTW, where the offset
+    /// changes along the run.
+    pub offset_ms: Option<i64>,
+    /// Share of reference samples that found a match.
+    pub coverage: f64,
+    pub distance: ErrorStats,
+    pub heading: ErrorStats,
+    pub speed: ErrorStats,
+    pub samples: Vec<DiffSample>,
+}
+
+/// How far the runs spread at one reference sample.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpreadSample {
+    pub t: u64,
+    pub runs: usize,
+    pub mean_distance: f64,
+    pub max_distance: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunComparison {
+    pub reference: usize,
+    pub distance: ErrorStats,
+    pub heading: ErrorStats,
+    pub speed: ErrorStats,
+    pub runs: Vec<ComparedRun>,
+    pub spread: Vec<SpreadSample>,
+}
+
+/// A pose in inches at a resampled time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Sampled {
+    pub t: u64,
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+    pub speed: f64,
+}
+
+/// Interpolates the run at `t`, scaled to inches; `None` outside the run.
+pub(crate) fn pose_at(poses: &[Pose], scale: f64, t: u64) -> Option<Sampled> {
+    let (first, last) = (poses.first()?, poses.last()?);
+    if t < first.t || t > last.t {
+        return None;
+    }
+    let i = poses.partition_point(|p| p.t < t);
+    let b = &poses[i];
+    let a = if i == 0 { b } else { &poses[i - 1] };
+    let span = b.t.saturating_sub(a.t);
+    let f = if span > 0 {
+        (t - a.t) as f64 / span as f64
+    } else {
+        1.0
+    };
+    Some(Sampled {
+        t,
+        x: (a.x + (b.x - a.x) * f) * scale,
+        y: (a.y + (b.y - a.y) * f) * scale,
+        theta: (a.theta + angle_delta(a.theta, b.theta) * f).rem_euclid(360.0),
+        speed: a.speed + (b.speed - a.speed) * f,
+    })
+}
+
+fn resample(poses: &[Pose], scale: f64, from: u64, step: u64) -> Vec<Sampled> {
+    let end = poses.last().map_or(0, |p| p.t);
+    (0..)
+        .map(|k| from + k * step)
+        .take_while(|&t| t <= end)
+        .filter_map(|t| pose_at(poses, scale, t))
+        .collect()
+}
+
+fn diff(index: usize, reference: &Sampled, other: &Sampled) -> DiffSample {
+    let (dx, dy) = (other.x - reference.x, other.y - reference.y);
+    DiffSample {
+        index,
+        t: reference.t,
+        other_t: other.t,
+        dx,
+        dy,
+        distance: dx.hypot(dy),
+        heading: angle_delta(reference.theta, other.theta),
+        speed: other.speed - reference.speed,
+    }
+}
+
+fn anchor(run: &Run, index: usize, align: &RunAlignment) -> Result<u64, String> {
+    let first = run
+        .poses
+        .first()
+        .ok_or_else(|| format!("run {index} has no poses"))?;
+    match align {
+        RunAlignment::Watch { label, value } => run
+            .watches
+            .iter()
+            .find(|w| w.label == *label && value.as_ref().is_none_or(|v| w.value == *v))
+            .map(|w| w.t)
+            .ok_or_else(|| format!("run {index} has no watch {label:?}")),
+        _ => Ok(first.t),
+    }
+}
+
+/// Matches each reference sample with one sample of `other` along the
+/// cheapest warping path (distance between positions).
+fn dtw(
+    reference: &[Sampled],
+    other: &[Sampled],
+    window: Option<usize>,
+) -> Result<Vec<(usize, usize)>, String> {
+    let (n, m) = (reference.len(), other.len());
+    if n == 0 || m == 0 {
+        return Ok(Vec::new());
+    }
+    if n * m > MAX_DTW_CELLS {
+        return Err(format!(
+            "runs are too long to warp ({n} x {m} samples); raise sample_ms"
+        ));
+    }
+    // The band follows the diagonal of an n by m matrix, and must be at
+    // least wide enough to reach the far corner.
+    let band = window.map(|w| w.max(n.abs_diff(m)));
+    let allowed = |i: usize, j: usize| band.is_none_or(|w| (i * m / n).abs_diff(j) <= w);
+    let cost =
+        |i: usize, j: usize| (reference[i].x - other[j].x).hypot(reference[i].y - other[j].y);
+
+    let mut acc = vec![f64::INFINITY; n * m];
+    for i in 0..n {
+        for j in 0..m {
+            if !allowed(i, j) {
+                continue;
+            }
+            let best = match (i, j) {
+                (0, 0) => 0.0,
+                (0, _) => acc[j - 1],
+                (_, 0) => acc[(i - 1) * m],
+                _ => acc[(i - 1) * m + j - 1]
+                    .min(acc[(i - 1) * m + j])
+                    .min(acc[i * m + j - 1]),
+            };
+            acc[i * m + j] = best + cost(i, j);
+        }
+    }
+    if !acc[n * m - 1].is_finite() {
+        return Err("no warping path fits in the window".into());
+    }
+
+    let (mut i, mut j) = (n - 1, m - 1);
+    let mut path = vec![(i, j)];
+    while i > 0 || j > 0 {
+        (i, j) = match (i, j) {
+            (0, _) => (0, j - 1),
+            (_, 0) => (i - 1, 0),
+            _ => [(i - 1, j - 1), (i - 1, j), (i, j - 1)]
+                .into_iter()
+                .min_by(|a, b| acc[a.0 * m + a.1].total_cmp(&acc[b.0 * m + b.1]))
+                .unwrap_or((i - 1, j - 1)),
+        };
+        path.push((i, j));
+    }
+    path.reverse();
+
+    // Keep the closest match for each reference sample.
+    let mut matched: Vec<(usize, usize)> = Vec::with_capacity(n);
+    for (i, j) in path {
+        match matched.last_mut() {
+            Some(last) if last.0 == i => {
+                if cost(i, j) < cost(last.0, last.1) {
+                    last.1 = j;
+                }
+            }
+            _ => matched.push((i, j)),
+        }
+    }
+    Ok(matched)
+}
+
+/// Compares every run with the reference run. Positions are converted to
+/// inches using each run's `meta.units`.
+pub fn compare(runs: &[Run], opts: &CompareOptions) -> Result<RunComparison, String> {
+    if runs.len() < 2 {
+        return Err("need at least two runs to compare".into());
+    }
+    if opts.reference >= runs.len() {
+        return Err(format!(
+            "reference {} is not one of the runs",
+            opts.reference
+        ));
+    }
+    if opts.sample_ms == 0 {
+        return Err("sample_ms must be at least 1".into());
+    }
+    let mut anchors = Vec::with_capacity(runs.len());
+    let mut scales = Vec::with_capacity(runs.len());
+    for (i, run) in runs.iter().enumerate() {
+        anchors.push(anchor(run, i, &opts.align)?);
+        scales.push(run.inches_scale().map_err(|e| format!("run {i}: {e}"))?);
+    }
+
+    let r = opts.reference;
+    let reference = resample(&runs[r].poses, scales[r], anchors[r], opts.sample_ms);
+    let mut compared = Vec::new();
+    for (i, run) in runs.iter().enumerate() {
+        if i == r {
+            continue;
+        }
+        let (offset_ms, samples) = match &opts.align {
+            RunAlignment::Dtw { window } => {
+                let other = resample(&run.poses, scales[i], anchors[i], opts.sample_ms);
+                let samples: Vec<DiffSample> = dtw(&reference, &other, *window)?
+                    .into_iter()
+                    .map(|(a, b)| diff(a, &reference[a], &other[b]))
+                    .collect();
+                (None, samples)
+            }
+            _ => {
+                let offset = anchors[i] as i64 - anchors[r] as i64;
+                let samples = reference
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, s)| {
+                        let t = u64::try_from(s.t as i64 + offset).ok()?;
+                        pose_at(&run.poses, scales[i], t).map(|o| diff(k, s, &o))
+                    })
+                    .collect();
+                (Some(offset), samples)
+            }
+        };
+        compared.push(ComparedRun {
+            run: i,
+            offset_ms,
+            coverage: if reference.is_empty() {
+                0.0
+            } else {
+                samples.len() as f64 / reference.len() as f64
+            },
+            distance: ErrorStats::of(samples.iter().map(|s| s.distance)),
+            heading: ErrorStats::of(samples.iter().map(|s| s.heading)),
+            speed: ErrorStats::of(samples.iter().map(|s| s.speed)),
+            samples,
+        });
+    }
+
+    // Each run has at most one sample per reference sample.
+    let mut by_sample = vec![Vec::new(); reference.len()];
+    for d in compared.iter().flat_map(|c| &c.samples) {
+        by_sample[d.index].push(d.distance);
+    }
+    let spread = reference
+        .iter()
+        .zip(by_sample)
+        .filter_map(|(s, distances)| {
+            if distances.is_empty() {
+                return None;
+            }
+            Some(SpreadSample {
+                t: s.t,
+                runs: distances.len(),
+                mean_distance: distances.iter().sum::<f64>() / distances.len() as f64,
+                max_distance: distances.iter().copied().fold(0.0, f64::max),
+            })
+        })
+        .collect();
+
+    let all = || compared.iter().flat_map(|c| &c.samples);
+    Ok(RunComparison {
+        reference: r,
+        distance: ErrorStats::of(all().map(|s| s.distance)),
+        heading: ErrorStats::of(all().map(|s| s.heading)),
+        speed: ErrorStats::of(all().map(|s| s.speed)),
+        spread,
+        runs: compared,
+    })
+}
+
+#[tauri::command]
+pub fn compare_runs(runs: Vec<Run>, options: CompareOptions) -> Result<RunComparison, String> {
+    compare(&runs, &options)
+}
+
+#[tauri::command]
+pub fn compare_run_files(
+    paths: Vec<String>,
+    options: CompareOptions,
+) -> Result<RunComparison, String> {
+    let runs = paths
+        .iter()
+        .map(|p| read_run_file(std::path::Path::new(p)).map_err(|e| format!("{p}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    compare(&runs, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::RunMeta;
+
+    /// An S-ish path driven from `start` over `duration` ms.
+    fn run(start: u64, duration: u64) -> Run {
+        let poses = (0..=duration / 10)
+            .map(|k| {
+                let u = (k * 10) as f64 / duration as f64;
+                let x = 24.0 * (u * std::f64::consts::PI).sin();
+                Pose::new(start + k * 10, x, 48.0 * u, 0.0, None, None)
+            })
+            .collect();
+        Run::new(RunMeta::default(), poses, Vec::new())
+    }
+
+    #[test]
+    fn dtw_lines_up_a_slower_copy() {
+        let runs = [run(0, 2000), run(5000, 3000)];
+        let opts = CompareOptions {
+            align: RunAlignment::Dtw { window: None },
+            ..CompareOptions::default()
+        };
+        let result = compare(&runs, &opts).unwrap();
+        let other = &result.runs[0];
+        assert_eq!((other.run, other.offset_ms, other.coverage), (1, None, 1.0));
+        assert_eq!(other.samples.len(), 101);
+        assert!(other.distance.max < 0.3, "{:?}", other.distance);
+        assert!(other.distance.mean_abs < 0.15, "{:?}", other.distance);
+        let last = other.samples.last().unwrap();
+        assert_eq!((last.t, last.other_t), (2000, 8000));
+
+        // Lined up at the start instead, the slower run falls behind.
+        let result = compare(&runs, &CompareOptions::default()).unwrap();
+        assert_eq!(result.runs[0].offset_ms, Some(5000));
+        assert!(result.runs[0].distance.max > 10.0);
+    }
+
+    #[test]
+    fn spread_uses_every_run_at_each_sample() {
+        let runs = [run(0, 2000), run(0, 2000), run(0, 3000)];
+        let result = compare(&runs, &CompareOptions::default()).unwrap();
+        assert_eq!(result.spread.len(), 101);
+        for (k, s) in result.spread.iter().enumerate() {
+            let far = result.runs[1].samples[k].distance;
+            assert_eq!((s.t, s.runs), (20 * k as u64, 2));
+            assert_eq!(s.max_distance, far);
+            assert!((s.mean_distance - far / 2.0).abs() < 1e-12);
+        }
+    }
+}
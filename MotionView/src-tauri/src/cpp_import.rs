@@ -0,0 +1,700 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::plan::Waypoint;
+use crate::settings;
+
+const SOURCE_EXTS: [&str; 5] = ["cpp", "cc", "cxx", "hpp", "h"];
+const FILE_SCOPE: &str = "<file scope>";
+
+/// Chassis calls we know how to turn into waypoints. Anything else is ignored.
+const KNOWN_CALLS: [&str; 18] = [
+    // LemLib
+    "setPose",
+    "moveToPoint",
+    "moveToPose",
+    "turnToHeading",
+    "turnToPoint",
+    "swingToHeading",
+    // EZ-Template
+    "odom_xyt_set",
+    "pid_odom_set",
+    "pid_drive_set",
+    "pid_turn_set",
+    "pid_turn_relative_set",
+    "pid_swing_set",
+    "pid_swing_relative_set",
+    // okapi
+    "setState",
+    "driveToPoint",
+    "turnToAngle",
+    "moveDistance",
+    "turnAngle",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Routine {
+    pub file: String,
+    pub function: String,
+    pub line: usize,
+    /// Number of calls that contributed to the waypoints.
+    pub calls: usize,
+    pub waypoints: Vec<Waypoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedCall {
+    pub file: String,
+    pub line: usize,
+    pub call: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CppImport {
+    pub project: String,
+    pub files_scanned: usize,
+    pub routines: Vec<Routine>,
+    pub skipped: Vec<SkippedCall>,
+}
+
+/// Blanks out comments and string/char literals (keeping newlines) so the
+/// scanner can't be fooled by braces or calls inside them.
+fn blank_comments(src: &str) -> String {
+    let b = src.as_bytes();
+    let mut out = String::with_capacity(src.len());
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i];
+        if c == b'/' && b.get(i + 1) == Some(&b'/') {
+            while i < b.len() && b[i] != b'\n' {
+                out.push(' ');
+                i += 1;
+            }
+        } else if c == b'/' && b.get(i + 1) == Some(&b'*') {
+            out.push_str("  ");
+            i += 2;
+            while i < b.len() && !(b[i] == b'*' && b.get(i + 1) == Some(&b'/')) {
+                out.push(if b[i] == b'\n' { '\n' } else { ' ' });
+                i += 1;
+            }
+            if i < b.len() {
+                out.push_str("  ");
+                i += 2;
+            }
+        } else if c == b'"' || c == b'\'' {
+            out.push(c as char);
+            i += 1;
+            while i < b.len() && b[i] != c && b[i] != b'\n' {
+                if b[i] == b'\\' && i + 1 < b.len() {
+                    out.push(' ');
+                    i += 1;
+                }
+                out.push(' ');
+                i += 1;
+            }
+            if i < b.len() && b[i] == c {
+                out.push(c as char);
+                i += 1;
+            }
+        } else {
+            // Non-ASCII bytes become spaces so byte offsets stay char offsets.
+            out.push(if c.is_ascii() { c as char } else { ' ' });
+            i += 1;
+        }
+    }
+    out
+}
+
+fn is_ident_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// Name of the function whose body starts with the `{` at `open`, if the text
+/// before it looks like `name(...)` (plus trailing qualifiers).
+fn function_name_before(src: &[u8], open: usize) -> Option<String> {
+    let text = std::str::from_utf8(&src[..open]).ok()?;
+    let mut head = text.trim_end();
+    loop {
+        let trimmed = ["const", "override", "noexcept", "final"]
+            .iter()
+            .find_map(|q| head.strip_suffix(q))
+            .map(str::trim_end);
+        match trimmed {
+            Some(t) => head = t,
+            None => break,
+        }
+    }
+    let head = head.strip_suffix(')')?;
+    let mut depth = 1;
+    let mut start = None;
+    for (i, c) in head.bytes().enumerate().rev() {
+        match c {
+            b')' => depth += 1,
+            b'(' => {
+                depth -= 1;
+                if depth == 0 {
+                    start = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let before = head[..start?].trim_end().as_bytes();
+    let end = before.len();
+    let mut s = end;
+    while s > 0 && (is_ident_byte(before[s - 1]) || before[s - 1] == b':') {
+        s -= 1;
+    }
+    let name = std::str::from_utf8(&before[s..end]).ok()?;
+    let last = name.rsplit("::").next().unwrap_or(name);
+    if name.is_empty()
+        || last.is_empty()
+        || ["if", "for", "while", "switch", "catch", "return"].contains(&last)
+    {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// Splits on commas that aren't nested inside (), {} or [].
+fn split_top_level(s: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut cur = String::new();
+    for c in s.chars() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(cur.trim().to_string());
+                cur.clear();
+                continue;
+            }
+            _ => {}
+        }
+        cur.push(c);
+    }
+    if !cur.trim().is_empty() {
+        parts.push(cur.trim().to_string());
+    }
+    parts
+}
+
+/// Elements of a `{a, b, c}` initializer, or None if `s` isn't one.
+fn brace_items(s: &str) -> Option<Vec<String>> {
+    let inner = s.trim().strip_prefix('{')?.strip_suffix('}')?;
+    Some(split_top_level(inner))
+}
+
+/// A numeric literal with an optional okapi unit suffix, e.g. `-24.5_in`.
+fn literal(expr: &str) -> Option<(f64, &str)> {
+    let e = expr.trim();
+    let e = e
+        .strip_prefix('(')
+        .and_then(|x| x.strip_suffix(')'))
+        .unwrap_or(e)
+        .trim();
+    let (num, unit) = match e.find('_') {
+        Some(i) => (&e[..i], &e[i + 1..]),
+        None => (e, ""),
+    };
+    let num = num.trim_end_matches(['f', 'F']);
+    let v: f64 = num.parse().ok()?;
+    v.is_finite().then_some((v, unit))
+}
+
+fn length(expr: &str) -> Result<f64, String> {
+    let (v, unit) = literal(expr).ok_or_else(|| format!("{expr:?} is not a literal length"))?;
+    let per_inch = match unit {
+        "" | "in" => 1.0,
+        "cm" => 2.54,
+        "mm" => 25.4,
+        "m" => 0.0254,
+        "ft" => 1.0 / 12.0,
+        "tile" => 1.0 / 24.0,
+        other => return Err(format!("unknown length unit _{other}")),
+    };
+    Ok(v / per_inch)
+}
+
+fn angle(expr: &str) -> Result<f64, String> {
+    let (v, unit) = literal(expr).ok_or_else(|| format!("{expr:?} is not a literal angle"))?;
+    match unit {
+        "" | "deg" => Ok(v),
+        "rad" => Ok(v.to_degrees()),
+        other => Err(format!("unknown angle unit _{other}")),
+    }
+}
+
+fn arg(args: &[String], i: usize) -> Result<&str, String> {
+    args.get(i)
+        .map(String::as_str)
+        .ok_or_else(|| format!("expected at least {} arguments", i + 1))
+}
+
+/// Heading of travel from one point to another, compass convention.
+fn heading_to(from: (f64, f64), to: (f64, f64)) -> f64 {
+    (to.0 - from.0)
+        .atan2(to.1 - from.1)
+        .to_degrees()
+        .rem_euclid(360.0)
+}
+
+fn reversed(args: &[String]) -> bool {
+    args.iter().any(|a| {
+        let a: String = a.chars().filter(|c| !c.is_whitespace()).collect();
+        a.contains(".forwards=false") || a == "rev" || a == "ez::rev"
+    })
+}
+
+/// Replays chassis calls for one routine, tracking where the robot is.
+struct Replay {
+    waypoints: Vec<Waypoint>,
+    calls: usize,
+}
+
+impl Replay {
+    fn pos(&self) -> (f64, f64, f64) {
+        self.waypoints
+            .last()
+            .map_or((0.0, 0.0, 0.0), |w| (w.x, w.y, w.theta))
+    }
+
+    fn set_pose(&mut self, x: f64, y: f64, theta: f64) {
+        // A pose reset replaces a starting point nobody drove from.
+        if self.waypoints.len() == 1 {
+            self.waypoints.clear();
+        }
+        self.waypoints.push(Waypoint { x, y, theta });
+    }
+
+    fn drive_to(&mut self, x: f64, y: f64, theta: Option<f64>, backwards: bool) {
+        let (px, py, _) = self.pos();
+        let travel = heading_to((px, py), (x, y));
+        let theta = theta.unwrap_or(if backwards {
+            (travel + 180.0).rem_euclid(360.0)
+        } else {
+            travel
+        });
+        if self.waypoints.is_empty() {
+            self.waypoints.push(Waypoint {
+                x: 0.0,
+                y: 0.0,
+                theta: 0.0,
+            });
+        }
+        self.waypoints.push(Waypoint { x, y, theta });
+    }
+
+    fn drive(&mut self, dist: f64) {
+        let (x, y, theta) = self.pos();
+        let r = theta.to_radians();
+        self.drive_to(
+            x + dist * r.sin(),
+            y + dist * r.cos(),
+            Some(theta),
+            dist < 0.0,
+        );
+    }
+
+    fn turn_to(&mut self, theta: f64) {
+        let theta = theta.rem_euclid(360.0);
+        match self.waypoints.last_mut() {
+            Some(w) => w.theta = theta,
+            None => self.waypoints.push(Waypoint {
+                x: 0.0,
+                y: 0.0,
+                theta,
+            }),
+        }
+    }
+
+    fn apply(&mut self, name: &str, args: &[String]) -> Result<(), String> {
+        match name {
+            "setPose" | "odom_xyt_set" => {
+                let (x, y, t) = (
+                    length(arg(args, 0)?)?,
+                    length(arg(args, 1)?)?,
+                    angle(arg(args, 2)?)?,
+                );
+                self.set_pose(x, y, t);
+            }
+            "setState" => {
+                let p = brace_items(arg(args, 0)?).ok_or("expected {x, y, theta}")?;
+                let t = p.get(2).map(|a| angle(a)).transpose()?.unwrap_or(0.0);
+                self.set_pose(length(arg(&p, 0)?)?, length(arg(&p, 1)?)?, t);
+            }
+            "moveToPoint" => {
+                let (x, y) = (length(arg(args, 0)?)?, length(arg(args, 1)?)?);
+                self.drive_to(x, y, None, reversed(&args[2.min(args.len())..]));
+            }
+            "moveToPose" => {
+                let (x, y, t) = (
+                    length(arg(args, 0)?)?,
+                    length(arg(args, 1)?)?,
+                    angle(arg(args, 2)?)?,
+                );
+                self.drive_to(x, y, Some(t), false);
+            }
+            "driveToPoint" => {
+                let p = brace_items(arg(args, 0)?).ok_or("expected {x, y}")?;
+                let backwards = args.get(1).is_some_and(|a| a.trim() == "true");
+                self.drive_to(length(arg(&p, 0)?)?, length(arg(&p, 1)?)?, None, backwards);
+            }
+            "pid_odom_set" => {
+                let outer =
+                    brace_items(arg(args, 0)?).ok_or("expected {{x, y, theta}, dir, speed}")?;
+                // Either a single `{{x, y[, t]}, dir, speed}` or a list of them.
+                let moves = match outer.first().and_then(|f| brace_items(f)) {
+                    Some(first)
+                        if first
+                            .first()
+                            .is_some_and(|p| p.trim_start().starts_with('{')) =>
+                    {
+                        outer
+                            .iter()
+                            .map(|m| brace_items(m).ok_or("expected {{x, y}, dir, speed}"))
+                            .collect::<Result<Vec<_>, _>>()?
+                    }
+                    _ => vec![outer],
+                };
+                for m in moves {
+                    let p = brace_items(arg(&m, 0)?).ok_or("expected {x, y[, theta]}")?;
+                    let t = p.get(2).map(|a| angle(a)).transpose()?;
+                    let backwards = reversed(&m[1.min(m.len())..]);
+                    self.drive_to(length(arg(&p, 0)?)?, length(arg(&p, 1)?)?, t, backwards);
+                }
+            }
+            "pid_drive_set" | "moveDistance" => self.drive(length(arg(args, 0)?)?),
+            "turnToHeading" | "pid_turn_set" | "turnToAngle" => self.turn_to(angle(arg(args, 0)?)?),
+            "swingToHeading" => self.turn_to(angle(arg(args, 0)?)?),
+            "pid_swing_set" => self.turn_to(angle(arg(args, 1)?)?),
+            "pid_turn_relative_set" | "turnAngle" => {
+                let d = angle(arg(args, 0)?)?;
+                self.turn_to(self.pos().2 + d);
+            }
+            "pid_swing_relative_set" => {
+                let d = angle(arg(args, 1)?)?;
+                self.turn_to(self.pos().2 + d);
+            }
+            "turnToPoint" => {
+                let (x, y) = (length(arg(args, 0)?)?, length(arg(args, 1)?)?);
+                let (px, py, _) = self.pos();
+                self.turn_to(heading_to((px, py), (x, y)));
+            }
+            _ => return Err(format!("{name} is not supported")),
+        }
+        self.calls += 1;
+        Ok(())
+    }
+}
+
+struct Block {
+    function: Option<(String, usize)>,
+}
+
+/// Finds chassis calls in one file and replays them per enclosing function.
+pub fn scan_source(file: &str, src: &str) -> (Vec<Routine>, Vec<SkippedCall>) {
+    let clean = blank_comments(src);
+    let b = clean.as_bytes();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut routines: Vec<(Routine, Replay)> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'\n' => line += 1,
+            b'{' => {
+                let in_function = blocks.iter().any(|bl| bl.function.is_some());
+                let function = if in_function {
+                    None
+                } else {
+                    function_name_before(b, i).map(|n| (n, line))
+                };
+                blocks.push(Block { function });
+            }
+            b'}' => {
+                blocks.pop();
+            }
+            b'.' | b'>' if i + 1 < b.len() && (b[i] == b'.' || (i > 0 && b[i - 1] == b'-')) => {
+                let start = i + 1;
+                let mut end = start;
+                while end < b.len() && is_ident_byte(b[end]) {
+                    end += 1;
+                }
+                let name = &clean[start..end];
+                let mut open = end;
+                while open < b.len() && b[open].is_ascii_whitespace() && b[open] != b'\n' {
+                    open += 1;
+                }
+                if !KNOWN_CALLS.contains(&name) || b.get(open) != Some(&b'(') {
+                    i += 1;
+                    continue;
+                }
+                let mut depth = 0;
+                let mut close = open;
+                while close < b.len() {
+                    match b[close] {
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    close += 1;
+                }
+                if close >= b.len() {
+                    break;
+                }
+                let args = split_top_level(&clean[open + 1..close]);
+                let call_text = src
+                    .get(start..=close)
+                    .unwrap_or(&clean[start..=close])
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let (function, fn_line) = blocks
+                    .iter()
+                    .find_map(|bl| bl.function.clone())
+                    .unwrap_or_else(|| (FILE_SCOPE.to_string(), 0));
+                let idx = match routines.iter().position(|(r, _)| r.function == function) {
+                    Some(idx) => idx,
+                    None => {
+                        routines.push((
+                            Routine {
+                                file: file.to_string(),
+                                function,
+                                line: fn_line,
+                                calls: 0,
+                                waypoints: Vec::new(),
+                            },
+                            Replay {
+                                waypoints: Vec::new(),
+                                calls: 0,
+                            },
+                        ));
+                        routines.len() - 1
+                    }
+                };
+                if let Err(reason) = routines[idx].1.apply(name, &args) {
+                    skipped.push(SkippedCall {
+                        file: file.to_string(),
+                        line,
+                        call: call_text,
+                        reason,
+                    });
+                }
+                line += clean[i..=close].matches('\n').count();
+                i = close + 1;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let routines = routines
+        .into_iter()
+        .filter(|(_, replay)| replay.calls > 0)
+        .map(|(mut r, replay)| {
+            r.calls = replay.calls;
+            r.waypoints = replay.waypoints;
+            r
+        })
+        .collect();
+    (routines, skipped)
+}
+
+fn collect_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_sources(&path, out);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SOURCE_EXTS.contains(&e))
+        {
+            out.push(path);
+        }
+    }
+}
+
+/// Scans `src/` of the PROS project (the configured one unless `dir` is
+/// given) for chassis calls with literal coordinates.
+#[tauri::command]
+pub fn import_pros_autons(app: AppHandle, dir: Option<String>) -> Result<CppImport, String> {
+    let project = match dir.filter(|d| !d.trim().is_empty()) {
+        Some(d) => PathBuf::from(d),
+        None => {
+            let settings = settings::load_settings(&app)?.unwrap_or_default();
+            if settings.pros_dir.is_empty() {
+                return Err("no PROS project directory is configured".into());
+            }
+            PathBuf::from(settings.pros_dir)
+        }
+    };
+    if !project.is_dir() {
+        return Err(format!(
+            "PROS project directory {} is not available",
+            project.display()
+        ));
+    }
+    let src_dir = project.join("src");
+    if !src_dir.is_dir() {
+        return Err(format!("{} has no src/ directory", project.display()));
+    }
+    let mut files = Vec::new();
+    collect_sources(&src_dir, &mut files);
+    files.sort();
+
+    let mut routines = Vec::new();
+    let mut skipped = Vec::new();
+    for path in &files {
+        let src = match std::fs::read(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                skipped.push(SkippedCall {
+                    file: path.to_string_lossy().to_string(),
+                    line: 0,
+                    call: String::new(),
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let name = path
+            .strip_prefix(&project)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+        let (r, s) = scan_source(&name, &src);
+        routines.extend(r);
+        skipped.extend(s);
+    }
+    Ok(CppImport {
+        project: project.to_string_lossy().to_string(),
+        files_scanned: files.len(),
+        routines,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(r: &Routine) -> Vec<(f64, f64, f64)> {
+        let round = |v: f64| (v * 100.0).round() / 100.0;
+        r.waypoints
+            .iter()
+            .map(|w| (round(w.x), round(w.y), round(w.theta)))
+            .collect()
+    }
+
+    #[test]
+    fn replays_lemlib_routine() {
+        let src = r#"
+#include "main.h"
+
+void skills() {
+    chassis.setPose(-48, -24, 90);
+    chassis.moveToPoint(-24, -24, 2000);
+    chassis.turnToHeading(0, 1000);
+    chassis.moveToPose(-24, 0, 45, 2000, {.lead = 0.3});
+    chassis.moveToPoint(-24, -24, 2000, {.forwards = false});
+}
+"#;
+        let (routines, skipped) = scan_source("src/autons.cpp", src);
+        assert!(skipped.is_empty(), "{skipped:?}");
+        assert_eq!(routines.len(), 1);
+        let r = &routines[0];
+        assert_eq!((r.function.as_str(), r.line, r.calls), ("skills", 4, 5));
+        assert_eq!(
+            points(r),
+            [
+                (-48.0, -24.0, 90.0),
+                (-24.0, -24.0, 0.0),
+                (-24.0, 0.0, 45.0),
+                (-24.0, -24.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn replays_ez_template_relative_moves() {
+        let src = "void drive_example() {\n  chassis.odom_xyt_set(0_in, 0_in, 0_deg);\n  chassis.pid_drive_set(24_in, DRIVE_SPEED);\n  chassis.pid_turn_relative_set(90_deg, TURN_SPEED);\n  chassis.pid_drive_set(-12_in, DRIVE_SPEED);\n  chassis.pid_odom_set({{{0_in, 48_in}, fwd, 110}, {{24_in, 48_in}, rev, 110}});\n}\n";
+        let (routines, skipped) = scan_source("autons.cpp", src);
+        assert!(skipped.is_empty(), "{skipped:?}");
+        assert_eq!(
+            points(&routines[0]),
+            [
+                (0.0, 0.0, 0.0),
+                (0.0, 24.0, 90.0),
+                (-12.0, 24.0, 90.0),
+                (0.0, 48.0, 26.57),
+                (24.0, 48.0, 270.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_calls_in_comments_and_strings() {
+        let src = r#"
+void red_left() {
+    // chassis.moveToPoint(100, 100, 1000);
+    /* chassis.setPose(5, 5, 5); { */
+    pros::lcd::print(0, "chassis.moveToPoint(1, 2, 3)");
+    chassis.moveToPoint(0, 24, 1000);
+}
+"#;
+        let (routines, _) = scan_source("a.cpp", src);
+        assert_eq!(routines[0].function, "red_left");
+        assert_eq!(points(&routines[0]), [(0.0, 0.0, 0.0), (0.0, 24.0, 0.0)]);
+    }
+
+    #[test]
+    fn skips_non_literal_arguments() {
+        let src = "void auton() {\n  chassis.moveToPoint(target.x, 24, 1000);\n  chassis.moveToPoint(0, 24_furlong, 1000);\n  chassis.moveToPoint(12, 12, 1000);\n}\n";
+        let (routines, skipped) = scan_source("a.cpp", src);
+        assert_eq!(routines[0].calls, 1);
+        let lines: Vec<usize> = skipped.iter().map(|s| s.line).collect();
+        assert_eq!(lines, [2, 3]);
+        assert_eq!(skipped[0].call, "moveToPoint(target.x, 24, 1000)");
+        assert!(skipped[1].reason.contains("_furlong"));
+    }
+
+    #[test]
+    fn splits_routines_by_function() {
+        let src = "void a() {\n  if (x) {\n    chassis.moveToPoint(0, 10, 500);\n  }\n}\n\nvoid b() const {\n  chassis.moveToPoint(10, 0, 500);\n}\n";
+        let (routines, _) = scan_source("a.cpp", src);
+        let names: Vec<(&str, usize)> = routines
+            .iter()
+            .map(|r| (r.function.as_str(), r.line))
+            .collect();
+        assert_eq!(names, [("a", 1), ("b", 7)]);
+    }
+
+    #[test]
+    fn converts_literal_units() {
+        assert_eq!(length("(-24.5_in)").unwrap(), -24.5);
+        assert_eq!(length("2_ft").unwrap(), 24.0);
+        assert_eq!(length("1_tile").unwrap(), 24.0);
+        assert!((length("254_mm").unwrap() - 10.0).abs() < 1e-9);
+        assert_eq!(angle("90.0f").unwrap(), 90.0);
+        assert!((angle("3.14159265358979_rad").unwrap() - 180.0).abs() < 1e-9);
+        assert!(length("kTileSize").is_err());
+    }
+}
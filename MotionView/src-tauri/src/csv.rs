@@ -0,0 +1,679 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::run::{self, Run, RunMeta};
+use crate::telemetry::{LineError, Pose, Watch};
+
+const POSE_COLUMNS: [&str; 7] = ["t", "x", "y", "theta", "l_vel", "r_vel", "speed"];
+const WATCH_COLUMNS: [&str; 4] = ["t", "level", "label", "value"];
+const LONG_COLUMNS: [&str; 11] = [
+    "kind", "t", "x", "y", "theta", "l_vel", "r_vel", "speed", "level", "label", "value",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    #[default]
+    Csv,
+    Tsv,
+}
+
+impl TableFormat {
+    pub(crate) fn delimiter(self) -> char {
+        match self {
+            TableFormat::Csv => ',',
+            TableFormat::Tsv => '\t',
+        }
+    }
+
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Tsv => "tsv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableLayout {
+    /// `<name>_poses.csv` and `<name>_watches.csv`.
+    #[default]
+    Separate,
+    /// One table with a `kind` column, rows ordered by time.
+    Long,
+}
+
+fn escape(field: &str, delim: char) -> String {
+    if field.contains(delim) || field.contains('"') || field.contains('\n') || field.contains('\r')
+    {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub(crate) fn write_row(out: &mut String, fields: &[String], delim: char) {
+    let row: Vec<String> = fields.iter().map(|f| escape(f, delim)).collect();
+    out.push_str(&row.join(&delim.to_string()));
+    out.push('\n');
+}
+
+fn header(cols: &[&str]) -> Vec<String> {
+    cols.iter().map(|c| c.to_string()).collect()
+}
+
+pub(crate) fn opt_num(v: Option<f64>) -> String {
+    v.map(|n| n.to_string()).unwrap_or_default()
+}
+
+fn pose_fields(p: &Pose) -> Vec<String> {
+    vec![
+        p.t.to_string(),
+        p.x.to_string(),
+        p.y.to_string(),
+        p.theta.to_string(),
+        opt_num(p.l_vel),
+        opt_num(p.r_vel),
+        p.speed.to_string(),
+    ]
+}
+
+fn watch_fields(w: &Watch) -> Vec<String> {
+    vec![
+        w.t.to_string(),
+        w.level.clone(),
+        w.label.clone(),
+        w.value.clone(),
+    ]
+}
+
+pub fn poses_table(poses: &[Pose], format: TableFormat) -> String {
+    let delim = format.delimiter();
+    let mut out = String::new();
+    write_row(&mut out, &header(&POSE_COLUMNS), delim);
+    for p in poses {
+        write_row(&mut out, &pose_fields(p), delim);
+    }
+    out
+}
+
+pub fn watches_table(watches: &[Watch], format: TableFormat) -> String {
+    let delim = format.delimiter();
+    let mut out = String::new();
+    write_row(&mut out, &header(&WATCH_COLUMNS), delim);
+    for w in watches {
+        write_row(&mut out, &watch_fields(w), delim);
+    }
+    out
+}
+
+pub fn long_table(run: &Run, format: TableFormat) -> String {
+    let delim = format.delimiter();
+    let mut rows: Vec<(u64, Vec<String>)> = Vec::with_capacity(run.poses.len() + run.watches.len());
+    for p in &run.poses {
+        let mut fields = vec!["pose".to_string()];
+        fields.extend(pose_fields(p));
+        fields.extend([String::new(), String::new(), String::new()]);
+        rows.push((p.t, fields));
+    }
+    for w in &run.watches {
+        let mut fields = vec!["watch".to_string(), w.t.to_string()];
+        fields.extend(std::iter::repeat_n(String::new(), 6));
+        fields.extend([w.level.clone(), w.label.clone(), w.value.clone()]);
+        rows.push((w.t, fields));
+    }
+    // Stable, so a watch stays after the pose that shares its tick.
+    rows.sort_by_key(|(t, _)| *t);
+    let mut out = String::new();
+    write_row(&mut out, &header(&LONG_COLUMNS), delim);
+    for (_, fields) in rows {
+        write_row(&mut out, &fields, delim);
+    }
+    out
+}
+
+pub(crate) fn sibling(base: &Path, suffix: &str, ext: &str) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("run")
+        .to_string();
+    base.with_file_name(format!("{stem}{suffix}.{ext}"))
+}
+
+/// Writes the run next to `path` and returns every file written.
+pub fn export(
+    run: &Run,
+    path: &Path,
+    format: TableFormat,
+    layout: TableLayout,
+) -> Result<Vec<String>, String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let ext = format.extension();
+    let files = match layout {
+        TableLayout::Long => vec![(path.with_extension(ext), long_table(run, format))],
+        TableLayout::Separate => vec![
+            (
+                sibling(path, "_poses", ext),
+                poses_table(&run.poses, format),
+            ),
+            (
+                sibling(path, "_watches", ext),
+                watches_table(&run.watches, format),
+            ),
+        ],
+    };
+    let mut written = Vec::new();
+    for (dest, contents) in files {
+        std::fs::write(&dest, contents).map_err(|e| e.to_string())?;
+        written.push(dest.to_string_lossy().to_string());
+    }
+    Ok(written)
+}
+
+/// Splits one record, honouring double-quoted fields. Quoted newlines are not
+/// supported; nothing MotionView exports contains them.
+fn split_record(line: &str, delim: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    cur.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                cur.push(c);
+            }
+        } else if c == '"' && cur.is_empty() {
+            quoted = true;
+        } else if c == delim {
+            fields.push(std::mem::take(&mut cur));
+        } else {
+            cur.push(c);
+        }
+    }
+    fields.push(cur);
+    fields
+}
+
+fn detect_delimiter(header_line: &str) -> char {
+    ['\t', ';', ',']
+        .into_iter()
+        .max_by_key(|d| header_line.matches(*d).count())
+        .unwrap_or(',')
+}
+
+/// Which header name feeds each field. Anything left `None` is auto-detected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub kind: Option<String>,
+    pub t: Option<String>,
+    pub x: Option<String>,
+    pub y: Option<String>,
+    pub theta: Option<String>,
+    pub l_vel: Option<String>,
+    pub r_vel: Option<String>,
+    pub speed: Option<String>,
+    pub level: Option<String>,
+    pub label: Option<String>,
+    pub value: Option<String>,
+}
+
+/// Header aliases per field, compared after `normalize_header`.
+const ALIASES: [(&str, &[&str]); 11] = [
+    ("kind", &["kind", "type", "row_type"]),
+    (
+        "t",
+        &["t", "time", "ms", "millis", "timestamp", "time_ms", "t_ms"],
+    ),
+    ("x", &["x", "pos_x", "x_pos"]),
+    ("y", &["y", "pos_y", "y_pos"]),
+    (
+        "theta",
+        &["theta", "heading", "angle", "h", "theta_deg", "heading_deg"],
+    ),
+    (
+        "l_vel",
+        &["l_vel", "left", "left_vel", "lvel", "left_velocity"],
+    ),
+    (
+        "r_vel",
+        &["r_vel", "right", "right_vel", "rvel", "right_velocity"],
+    ),
+    ("speed", &["speed", "speed_raw", "vel", "velocity"]),
+    ("level", &["level", "lvl", "severity"]),
+    ("label", &["label", "name", "watch"]),
+    ("value", &["value", "val", "message"]),
+];
+
+const UNIT_SUFFIXES: [&str; 5] = ["in", "ft", "mm", "cm", "m"];
+
+/// Splits `X (mm)`, `x [in]` or `x_mm` into a lowercase name and unit.
+fn split_unit(raw: &str) -> (String, Option<String>) {
+    let s = raw.trim().to_lowercase();
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let (Some(a), true) = (s.find(open), s.ends_with(close)) {
+            let unit = s[a + 1..s.len() - 1].trim().to_string();
+            let name = s[..a].trim().replace([' ', '-'], "_");
+            return (name, Some(unit));
+        }
+    }
+    let name = s.replace([' ', '-'], "_");
+    if let Some((head, unit)) = name.rsplit_once('_') {
+        if UNIT_SUFFIXES.contains(&unit) || unit == "s" {
+            return (head.to_string(), Some(unit.to_string()));
+        }
+    }
+    (name, None)
+}
+
+fn normalize_header(raw: &str) -> String {
+    raw.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+struct Columns {
+    index: std::collections::HashMap<&'static str, usize>,
+    time_in_seconds: bool,
+    units: Option<String>,
+}
+
+fn resolve_columns(headers: &[String], mapping: &ColumnMapping) -> Result<Columns, String> {
+    let overrides = [
+        ("kind", &mapping.kind),
+        ("t", &mapping.t),
+        ("x", &mapping.x),
+        ("y", &mapping.y),
+        ("theta", &mapping.theta),
+        ("l_vel", &mapping.l_vel),
+        ("r_vel", &mapping.r_vel),
+        ("speed", &mapping.speed),
+        ("level", &mapping.level),
+        ("label", &mapping.label),
+        ("value", &mapping.value),
+    ];
+    let mut index = std::collections::HashMap::new();
+    for (field, name) in overrides {
+        if let Some(name) = name {
+            let i = headers
+                .iter()
+                .position(|h| h.trim() == name.trim())
+                .ok_or_else(|| format!("mapped column {name:?} for {field} not found in header"))?;
+            index.insert(field, i);
+        }
+    }
+
+    let mut time_in_seconds = false;
+    let mut units = None;
+    for (i, h) in headers.iter().enumerate() {
+        let plain = normalize_header(h);
+        let (stem, unit) = split_unit(h);
+        for (field, aliases) in ALIASES {
+            if index.contains_key(field) {
+                continue;
+            }
+            let hit = aliases.contains(&plain.as_str()) || aliases.contains(&stem.as_str());
+            if !hit {
+                continue;
+            }
+            index.insert(field, i);
+            match (field, unit.as_deref()) {
+                ("t", Some("s")) | ("t", Some("sec")) => time_in_seconds = true,
+                ("x", Some(u)) | ("y", Some(u)) if UNIT_SUFFIXES.contains(&u) => {
+                    units.get_or_insert_with(|| u.to_string());
+                }
+                _ => {}
+            }
+            break;
+        }
+    }
+    if !index.contains_key("t") {
+        return Err("no time column found (expected one of t, time, ms, timestamp)".into());
+    }
+    Ok(Columns {
+        index,
+        time_in_seconds,
+        units,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CsvImport {
+    pub run: Run,
+    /// Length unit read from headers like `x (mm)`, if any.
+    pub detected_units: Option<String>,
+    pub skipped: Vec<LineError>,
+}
+
+fn cell<'a>(row: &'a [String], cols: &Columns, field: &str) -> Option<&'a str> {
+    cols.index
+        .get(field)
+        .and_then(|i| row.get(*i))
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+}
+
+fn num(row: &[String], cols: &Columns, field: &str) -> Result<Option<f64>, String> {
+    match cell(row, cols, field) {
+        None => Ok(None),
+        Some(s) => s
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(Some)
+            .ok_or_else(|| format!("{field}: expected number, found {s:?}")),
+    }
+}
+
+fn row_time(row: &[String], cols: &Columns) -> Result<u64, String> {
+    let t = num(row, cols, "t")?.ok_or_else(|| "t: missing value".to_string())?;
+    let ms = if cols.time_in_seconds { t * 1000.0 } else { t };
+    if ms < 0.0 {
+        return Err(format!("t: expected non-negative time, found {t}"));
+    }
+    Ok(ms.round() as u64)
+}
+
+enum Row {
+    Pose(Pose),
+    Watch(Watch),
+}
+
+fn parse_row(row: &[String], cols: &Columns) -> Result<Row, String> {
+    let t = row_time(row, cols)?;
+    let kind = cell(row, cols, "kind").map(str::to_lowercase);
+    let is_watch = match kind.as_deref() {
+        Some("watch") => true,
+        Some("pose") | Some("data") => false,
+        Some(other) => return Err(format!("kind: unknown row kind {other:?}")),
+        // Without a kind column, a table with x/y is a pose table.
+        None => !cols.index.contains_key("x"),
+    };
+    if is_watch {
+        return Ok(Row::Watch(Watch {
+            t,
+            level: cell(row, cols, "level").unwrap_or("INFO").to_string(),
+            label: cell(row, cols, "label").unwrap_or("").replace(':', ""),
+            value: cols
+                .index
+                .get("value")
+                .and_then(|i| row.get(*i))
+                .cloned()
+                .unwrap_or_default(),
+        }));
+    }
+    let x = num(row, cols, "x")?.ok_or_else(|| "x: missing value".to_string())?;
+    let y = num(row, cols, "y")?.ok_or_else(|| "y: missing value".to_string())?;
+    let theta = num(row, cols, "theta")?.unwrap_or(0.0);
+    let l_vel = num(row, cols, "l_vel")?;
+    let r_vel = num(row, cols, "r_vel")?;
+    let mut pose = Pose::new(t, x, y, theta, l_vel, r_vel);
+    if let Some(speed) = num(row, cols, "speed")? {
+        pose.speed = speed;
+    }
+    Ok(Row::Pose(pose))
+}
+
+/// Parses one table, appending into `poses`/`watches`.
+fn parse_table(
+    text: &str,
+    mapping: &ColumnMapping,
+    poses: &mut Vec<Pose>,
+    watches: &mut Vec<Watch>,
+    skipped: &mut Vec<LineError>,
+) -> Result<Option<String>, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    let (_, header_line) = lines.next().ok_or_else(|| "table is empty".to_string())?;
+    let delim = detect_delimiter(header_line);
+    let headers = split_record(header_line, delim);
+    let cols = resolve_columns(&headers, mapping)?;
+    for (i, line) in lines {
+        let row = split_record(line, delim);
+        match parse_row(&row, &cols) {
+            Ok(Row::Pose(p)) => poses.push(p),
+            Ok(Row::Watch(w)) => watches.push(w),
+            Err(message) => skipped.push(LineError {
+                line: i + 1,
+                text: line.to_string(),
+                message,
+            }),
+        }
+    }
+    Ok(cols.units)
+}
+
+pub fn import(
+    paths: &[PathBuf],
+    mapping: &ColumnMapping,
+    units: Option<String>,
+) -> Result<CsvImport, String> {
+    let mut poses = Vec::new();
+    let mut watches = Vec::new();
+    let mut skipped = Vec::new();
+    let mut detected_units = None;
+    for path in paths {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let found = parse_table(&text, mapping, &mut poses, &mut watches, &mut skipped)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        if detected_units.is_none() {
+            detected_units = found;
+        }
+    }
+    if poses.is_empty() {
+        return Err("no pose rows found".into());
+    }
+    poses.sort_by_key(|p| p.t);
+    watches.sort_by_key(|w| w.t);
+
+    let run_name = paths
+        .first()
+        .and_then(|p| p.file_stem())
+        .and_then(|s| s.to_str())
+        .map(|s| {
+            s.trim_end_matches("_poses")
+                .trim_end_matches("_watches")
+                .to_string()
+        })
+        .unwrap_or_default();
+    let meta = RunMeta {
+        run_name,
+        units: units
+            .or_else(|| detected_units.clone())
+            .unwrap_or_else(|| "in".to_string()),
+        ..RunMeta::default()
+    };
+    let run = Run::new(meta, poses, watches);
+    // Same checks as a JSON run, so an odd unit can't slip through.
+    let v = serde_json::to_value(&run).map_err(|e| e.to_string())?;
+    run::validate(&v).map_err(|e| e.to_string())?;
+    Ok(CsvImport {
+        run,
+        detected_units,
+        skipped,
+    })
+}
+
+#[tauri::command]
+pub fn export_run_csv(
+    run: Run,
+    path: String,
+    format: Option<TableFormat>,
+    layout: Option<TableLayout>,
+) -> Result<Vec<String>, String> {
+    export(
+        &run,
+        Path::new(&path),
+        format.unwrap_or_default(),
+        layout.unwrap_or_default(),
+    )
+}
+
+/// Exports the robot path currently held in `saved-paths.json`.
+#[tauri::command]
+pub fn export_saved_paths_csv(
+    app: AppHandle,
+    path: String,
+    format: Option<TableFormat>,
+    layout: Option<TableLayout>,
+) -> Result<Vec<String>, String> {
+    let saved = crate::settings::saved_paths_path(&app)?;
+    let parse = |s: &str| serde_json::from_str::<serde_json::Value>(s).map_err(|e| e.to_string());
+    let mut v = crate::settings::read_with_backup(&saved, parse)?
+        .ok_or_else(|| "no saved paths to export".to_string())?;
+    // saved-paths.json also carries the plan, which is not part of a run.
+    if let Some(obj) = v.as_object_mut() {
+        obj.remove("planned-path");
+    }
+    let run = run::from_value(v).map_err(|e| e.to_string())?;
+    export(
+        &run,
+        Path::new(&path),
+        format.unwrap_or_default(),
+        layout.unwrap_or_default(),
+    )
+}
+
+/// Imports one long-format table, or a poses table plus an optional watches
+/// table, back into a run.
+#[tauri::command]
+pub fn import_run_csv(
+    paths: Vec<String>,
+    mapping: Option<ColumnMapping>,
+    units: Option<String>,
+) -> Result<CsvImport, String> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    import(&paths, &mapping.unwrap_or_default(), units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(line: &str) -> Vec<String> {
+        split_record(line, detect_delimiter(line))
+    }
+
+    fn parse(text: &str) -> (Vec<Pose>, Vec<Watch>, Vec<LineError>, Option<String>) {
+        let (mut poses, mut watches, mut skipped) = (Vec::new(), Vec::new(), Vec::new());
+        let units = parse_table(
+            text,
+            &ColumnMapping::default(),
+            &mut poses,
+            &mut watches,
+            &mut skipped,
+        )
+        .unwrap();
+        (poses, watches, skipped, units)
+    }
+
+    #[test]
+    fn splits_units_from_headers() {
+        assert_eq!(split_unit("X (mm)"), ("x".into(), Some("mm".into())));
+        assert_eq!(split_unit("y [in]"), ("y".into(), Some("in".into())));
+        assert_eq!(split_unit("pos_x_cm"), ("pos_x".into(), Some("cm".into())));
+        assert_eq!(split_unit("time_s"), ("time".into(), Some("s".into())));
+        assert_eq!(split_unit("Left Vel"), ("left_vel".into(), None));
+    }
+
+    #[test]
+    fn detects_delimiter_from_header() {
+        assert_eq!(detect_delimiter("t,x,y"), ',');
+        assert_eq!(detect_delimiter("t\tx\ty"), '\t');
+        assert_eq!(detect_delimiter("t;x;y,z"), ';');
+    }
+
+    #[test]
+    fn resolves_aliases_and_units() {
+        let cols = resolve_columns(
+            &headers("Time (s),X (mm),Y (mm),Heading,Left,Right"),
+            &ColumnMapping::default(),
+        )
+        .unwrap();
+        assert!(cols.time_in_seconds);
+        assert_eq!(cols.units.as_deref(), Some("mm"));
+        let at = |f: &str| cols.index.get(f).copied();
+        assert_eq!(
+            [
+                at("t"),
+                at("x"),
+                at("y"),
+                at("theta"),
+                at("l_vel"),
+                at("r_vel")
+            ],
+            [Some(0), Some(1), Some(2), Some(3), Some(4), Some(5)]
+        );
+        assert_eq!(at("speed"), None);
+    }
+
+    #[test]
+    fn mapping_overrides_detection() {
+        let mapping = ColumnMapping {
+            t: Some("clock".into()),
+            x: Some("east".into()),
+            ..ColumnMapping::default()
+        };
+        let cols = resolve_columns(&headers("clock,east,x,y"), &mapping).unwrap();
+        assert_eq!(cols.index["t"], 0);
+        assert_eq!(cols.index["x"], 1);
+        assert_eq!(cols.index["y"], 3);
+
+        let missing = ColumnMapping {
+            t: Some("when".into()),
+            ..ColumnMapping::default()
+        };
+        assert!(resolve_columns(&headers("t,x,y"), &missing).is_err());
+        assert!(resolve_columns(&headers("x,y,theta"), &ColumnMapping::default()).is_err());
+    }
+
+    #[test]
+    fn seconds_are_converted_to_millis() {
+        let (poses, _, skipped, _) = parse("time_s,x_ft,y_ft\n1.5,1,2\n1.525,1,2\n");
+        assert!(skipped.is_empty());
+        assert_eq!(poses.iter().map(|p| p.t).collect::<Vec<_>>(), [1500, 1525]);
+    }
+
+    #[test]
+    fn bad_rows_are_skipped_with_line_numbers() {
+        let (poses, _, skipped, _) = parse("t,x,y\n0,1,2\n\n10,abc,2\n-5,0,0\n20,NaN,1\n30,3,4\n");
+        assert_eq!(poses.len(), 2);
+        let lines: Vec<usize> = skipped.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [4, 5, 6]);
+        assert!(skipped[0].message.starts_with("x:"));
+    }
+
+    #[test]
+    fn long_table_round_trips() {
+        let mut pose = Pose::new(100, -12.5, 3.25, 271.3, Some(-40.0), None);
+        pose.speed = 20.0;
+        let watch = Watch {
+            t: 100,
+            level: "WARN".into(),
+            label: "Pose".into(),
+            value: "(12.00, \"far\", 90.00)".into(),
+        };
+        let run = Run::new(RunMeta::default(), vec![pose.clone()], vec![watch.clone()]);
+        for format in [TableFormat::Csv, TableFormat::Tsv] {
+            let (poses, watches, skipped, units) = parse(&long_table(&run, format));
+            assert!(skipped.is_empty(), "{skipped:?}");
+            assert_eq!(units, None);
+            assert_eq!(poses, std::slice::from_ref(&pose));
+            assert_eq!(watches, std::slice::from_ref(&watch));
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::plan::{angle_delta, read_saved_plan, Waypoint};
+
+const BEZIER_STEPS: usize = 256;
+const MIN_LEG: f64 = 1e-6;
+/// Most points a smoothed path may have.
+const MAX_POINTS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurveKind {
+    /// Cubic Hermite spline with tangents along each waypoint's heading.
+    Hermite,
+    /// Cubic Bezier whose control points sit `lead` times the leg length out
+    /// along each waypoint's heading, like a boomerang controller's carrot.
+    Bezier,
+    /// Shortest turn-straight-turn (or turn-turn-turn) path at `turn_radius`.
+    Dubins,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CurveOptions {
+    pub kind: CurveKind,
+    /// Distance between samples, in inches.
+    pub spacing: f64,
+    /// Hermite tangent length as a multiple of the leg length.
+    pub tension: f64,
+    pub lead: f64,
+    /// Dubins turning radius in inches. Splines get a warning when they turn
+    /// tighter than this.
+    pub turn_radius: f64,
+    /// Drive a leg backwards when its start heading faces away from the
+    /// next waypoint.
+    pub allow_reverse: bool,
+}
+
+impl Default for CurveOptions {
+    fn default() -> Self {
+        CurveOptions {
+            kind: CurveKind::Hermite,
+            spacing: 0.5,
+            tension: 1.0,
+            // LemLib's default moveToPose lead.
+            lead: 0.6,
+            turn_radius: 12.0,
+            allow_reverse: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CurvePoint {
+    pub x: f64,
+    pub y: f64,
+    /// Robot heading, so it points backwards on reversed legs.
+    pub theta: f64,
+    /// Arc length from the first waypoint.
+    pub s: f64,
+    /// Change of theta per inch travelled, in radians. Positive turns
+    /// clockwise, the way theta increases.
+    pub curvature: f64,
+    /// Index of the waypoint this point is heading to.
+    pub waypoint: usize,
+    pub reversed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmoothPath {
+    pub points: Vec<CurvePoint>,
+    pub length: f64,
+    pub max_curvature: f64,
+    pub warnings: Vec<String>,
+}
+
+/// A point along one leg, with the heading of travel (compass degrees).
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    x: f64,
+    y: f64,
+    heading: f64,
+    curvature: f64,
+    s: f64,
+}
+
+fn check_options(opts: &CurveOptions) -> Result<(), String> {
+    let positive = [
+        ("spacing", opts.spacing),
+        ("tension", opts.tension),
+        ("lead", opts.lead),
+        ("turn_radius", opts.turn_radius),
+    ];
+    for (name, value) in positive {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(format!("{name} must be a positive number"));
+        }
+    }
+    Ok(())
+}
+
+/// Unit vector for a compass heading (0° = +Y, clockwise).
+fn direction(heading: f64) -> (f64, f64) {
+    let h = heading.to_radians();
+    (h.sin(), h.cos())
+}
+
+fn compass_heading(dx: f64, dy: f64) -> f64 {
+    dx.atan2(dy).to_degrees().rem_euclid(360.0)
+}
+
+fn steps(length: f64, spacing: f64) -> Result<usize, String> {
+    let n = (length / spacing).ceil();
+    if n > MAX_POINTS as f64 {
+        return Err(format!(
+            "a {length:.0} in leg needs more than {MAX_POINTS} points at {spacing} in spacing; raise spacing"
+        ));
+    }
+    Ok((n as usize).max(1))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bezier([(f64, f64); 4]);
+
+impl Bezier {
+    fn point(&self, t: f64) -> (f64, f64) {
+        let [p0, p1, p2, p3] = self.0;
+        let u = 1.0 - t;
+        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+        (
+            a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+            a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+        )
+    }
+
+    fn sample(&self, t: f64, s: f64) -> Sample {
+        let [p0, p1, p2, p3] = self.0;
+        let u = 1.0 - t;
+        let (a, b, c) = (3.0 * u * u, 6.0 * u * t, 3.0 * t * t);
+        let dx = a * (p1.0 - p0.0) + b * (p2.0 - p1.0) + c * (p3.0 - p2.0);
+        let dy = a * (p1.1 - p0.1) + b * (p2.1 - p1.1) + c * (p3.1 - p2.1);
+        let ddx = 6.0 * u * (p2.0 - 2.0 * p1.0 + p0.0) + 6.0 * t * (p3.0 - 2.0 * p2.0 + p1.0);
+        let ddy = 6.0 * u * (p2.1 - 2.0 * p1.1 + p0.1) + 6.0 * t * (p3.1 - 2.0 * p2.1 + p1.1);
+        let speed = dx.hypot(dy);
+        let (x, y) = self.point(t);
+        Sample {
+            x,
+            y,
+            heading: compass_heading(dx, dy),
+            curvature: if speed > MIN_LEG {
+                (dy * ddx - dx * ddy) / speed.powi(3)
+            } else {
+                0.0
+            },
+            s,
+        }
+    }
+
+    /// Samples evenly spaced by arc length, found through a length table.
+    fn samples(&self, spacing: f64) -> Result<Vec<Sample>, String> {
+        let mut table = vec![(0.0, 0.0)];
+        let mut prev = self.point(0.0);
+        for i in 1..=BEZIER_STEPS {
+            let t = i as f64 / BEZIER_STEPS as f64;
+            let p = self.point(t);
+            let s = table[i - 1].1 + (p.0 - prev.0).hypot(p.1 - prev.1);
+            table.push((t, s));
+            prev = p;
+        }
+        let length = table[BEZIER_STEPS].1;
+        let n = steps(length, spacing)?;
+        Ok((0..=n)
+            .map(|k| {
+                let s = length * k as f64 / n as f64;
+                let i = table.partition_point(|e| e.1 < s).clamp(1, BEZIER_STEPS);
+                let ((t0, s0), (t1, s1)) = (table[i - 1], table[i]);
+                let f = if s1 > s0 { (s - s0) / (s1 - s0) } else { 0.0 };
+                self.sample(t0 + (t1 - t0) * f, s)
+            })
+            .collect())
+    }
+}
+
+/// Control points for a leg whose ends travel along `h0` and `h1`, with the
+/// inner control points `arm` inches out.
+fn bezier_leg(a: Waypoint, b: Waypoint, h0: f64, h1: f64, arm: f64) -> Bezier {
+    let (d0, d1) = (direction(h0), direction(h1));
+    Bezier([
+        (a.x, a.y),
+        (a.x + arm * d0.0, a.y + arm * d0.1),
+        (b.x - arm * d1.0, b.y - arm * d1.1),
+        (b.x, b.y),
+    ])
+}
+
+/// Dubins segments turn left (1), go straight (0) or turn right (-1).
+/// Angles here are math radians (0 = +X, counter-clockwise).
+#[derive(Debug, Clone)]
+struct Dubins {
+    start: (f64, f64, f64),
+    radius: f64,
+    segments: [(i8, f64); 3],
+}
+
+impl Dubins {
+    /// Standard closed-form Dubins words, picking the shortest that exists.
+    fn shortest(start: (f64, f64, f64), end: (f64, f64, f64), radius: f64) -> Option<Dubins> {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let d = dx.hypot(dy) / radius;
+        let theta = dy.atan2(dx);
+        let alpha = (start.2 - theta).rem_euclid(TAU);
+        let beta = (end.2 - theta).rem_euclid(TAU);
+        let (sa, sb, ca, cb) = (alpha.sin(), beta.sin(), alpha.cos(), beta.cos());
+        let cab = (alpha - beta).cos();
+        let m = |v: f64| v.rem_euclid(TAU);
+
+        let lsl = || {
+            let p2 = 2.0 + d * d - 2.0 * cab + 2.0 * d * (sa - sb);
+            let tmp = (cb - ca).atan2(d + sa - sb);
+            (p2 >= 0.0).then(|| ([1, 0, 1], m(tmp - alpha), p2.sqrt(), m(beta - tmp)))
+        };
+        let rsr = || {
+            let p2 = 2.0 + d * d - 2.0 * cab + 2.0 * d * (sb - sa);
+            let tmp = (ca - cb).atan2(d - sa + sb);
+            (p2 >= 0.0).then(|| ([-1, 0, -1], m(alpha - tmp), p2.sqrt(), m(tmp - beta)))
+        };
+        let lsr = || {
+            let p2 = -2.0 + d * d + 2.0 * cab + 2.0 * d * (sa + sb);
+            (p2 >= 0.0).then(|| {
+                let p = p2.sqrt();
+                let tmp = (-ca - cb).atan2(d + sa + sb) - (-2.0f64).atan2(p);
+                ([1, 0, -1], m(tmp - alpha), p, m(tmp - beta))
+            })
+        };
+        let rsl = || {
+            let p2 = -2.0 + d * d + 2.0 * cab - 2.0 * d * (sa + sb);
+            (p2 >= 0.0).then(|| {
+                let p = p2.sqrt();
+                let tmp = (ca + cb).atan2(d - sa - sb) - 2.0f64.atan2(p);
+                ([-1, 0, 1], m(alpha - tmp), p, m(beta - tmp))
+            })
+        };
+        let rlr = || {
+            let tmp = (6.0 - d * d + 2.0 * cab + 2.0 * d * (sa - sb)) / 8.0;
+            (tmp.abs() <= 1.0).then(|| {
+                let phi = (ca - cb).atan2(d - sa + sb);
+                let p = m(TAU - tmp.acos());
+                let t = m(alpha - phi + p / 2.0);
+                ([-1, 1, -1], t, p, m(alpha - beta - t + p))
+            })
+        };
+        let lrl = || {
+            let tmp = (6.0 - d * d + 2.0 * cab + 2.0 * d * (sb - sa)) / 8.0;
+            (tmp.abs() <= 1.0).then(|| {
+                let phi = (ca - cb).atan2(d + sa - sb);
+                let p = m(TAU - tmp.acos());
+                let t = m(-alpha - phi + p / 2.0);
+                ([1, -1, 1], t, p, m(beta - alpha - t + p))
+            })
+        };
+
+        [lsl(), rsr(), lsr(), rsl(), rlr(), lrl()]
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| (a.1 + a.2 + a.3).total_cmp(&(b.1 + b.2 + b.3)))
+            .map(|(kinds, t, p, q)| Dubins {
+                start,
+                radius,
+                segments: [
+                    (kinds[0], t * radius),
+                    (kinds[1], p * radius),
+                    (kinds[2], q * radius),
+                ],
+            })
+    }
+
+    fn length(&self) -> f64 {
+        self.segments.iter().map(|s| s.1).sum()
+    }
+
+    fn sample(&self, s: f64) -> Sample {
+        let (mut x, mut y, mut h) = self.start;
+        let mut rest = s;
+        let mut turn = 0;
+        for &(kind, length) in &self.segments {
+            let ds = rest.min(length);
+            if kind == 0 {
+                x += ds * h.cos();
+                y += ds * h.sin();
+            } else {
+                let k = kind as f64;
+                let h1 = h + k * ds / self.radius;
+                x += k * self.radius * (h1.sin() - h.sin());
+                y += k * self.radius * (h.cos() - h1.cos());
+                h = h1;
+            }
+            turn = kind;
+            rest -= ds;
+            if rest <= 0.0 {
+                break;
+            }
+        }
+        Sample {
+            x,
+            y,
+            heading: (FRAC_PI_2 - h).to_degrees().rem_euclid(360.0),
+            // Left turns lower the compass heading.
+            curvature: -(turn as f64) / self.radius,
+            s,
+        }
+    }
+
+    fn samples(&self, spacing: f64) -> Result<Vec<Sample>, String> {
+        let length = self.length();
+        let n = steps(length, spacing)?;
+        Ok((0..=n)
+            .map(|k| self.sample(length * k as f64 / n as f64))
+            .collect())
+    }
+}
+
+fn math_pose(x: f64, y: f64, heading: f64) -> (f64, f64, f64) {
+    (x, y, (90.0 - heading).to_radians().rem_euclid(TAU))
+}
+
+/// Builds a smooth path through the waypoints. Each leg leaves a waypoint
+/// along its theta and arrives along the next one's, unlike the straight
+/// segments planning playback draws.
+pub fn smooth(waypoints: &[Waypoint], opts: &CurveOptions) -> Result<SmoothPath, String> {
+    check_options(opts)?;
+    let first = *waypoints.first().ok_or("plan has no waypoints")?;
+    let mut points = vec![CurvePoint {
+        x: first.x,
+        y: first.y,
+        theta: first.theta.rem_euclid(360.0),
+        s: 0.0,
+        curvature: 0.0,
+        waypoint: 0,
+        reversed: false,
+    }];
+    let mut warnings = Vec::new();
+    let max_curvature = 1.0 / opts.turn_radius;
+
+    for (i, pair) in waypoints.windows(2).enumerate() {
+        let (a, b) = (pair[0], pair[1]);
+        let chord = (b.x - a.x).hypot(b.y - a.y);
+        if chord <= MIN_LEG {
+            continue;
+        }
+        let reversed = opts.allow_reverse
+            && angle_delta(a.theta, compass_heading(b.x - a.x, b.y - a.y)).abs() > 90.0;
+        let flip = if reversed { 180.0 } else { 0.0 };
+        let (h0, h1) = (a.theta + flip, b.theta + flip);
+
+        let samples = match opts.kind {
+            CurveKind::Hermite => {
+                bezier_leg(a, b, h0, h1, opts.tension * chord / 3.0).samples(opts.spacing)?
+            }
+            CurveKind::Bezier => {
+                bezier_leg(a, b, h0, h1, opts.lead * chord).samples(opts.spacing)?
+            }
+            CurveKind::Dubins => Dubins::shortest(
+                math_pose(a.x, a.y, h0),
+                math_pose(b.x, b.y, h1),
+                opts.turn_radius,
+            )
+            .ok_or_else(|| format!("no Dubins path to waypoint {}", i + 1))?
+            .samples(opts.spacing)?,
+        };
+        if points.len() + samples.len() > MAX_POINTS {
+            return Err(format!(
+                "the path needs more than {MAX_POINTS} points at {} in spacing; raise spacing",
+                opts.spacing
+            ));
+        }
+
+        let tightest = samples
+            .iter()
+            .map(|p| p.curvature.abs())
+            .fold(0.0, f64::max);
+        if opts.kind != CurveKind::Dubins && tightest > max_curvature + MIN_LEG {
+            warnings.push(format!(
+                "leg to waypoint {} turns tighter than the {} in turning radius (down to {:.1} in)",
+                i + 1,
+                opts.turn_radius,
+                1.0 / tightest
+            ));
+        }
+
+        let offset = points.last().map_or(0.0, |p| p.s);
+        points.extend(samples.iter().skip(1).map(|p| CurvePoint {
+            x: p.x,
+            y: p.y,
+            theta: (p.heading + flip).rem_euclid(360.0),
+            s: offset + p.s,
+            curvature: p.curvature,
+            waypoint: i + 1,
+            reversed,
+        }));
+    }
+
+    Ok(SmoothPath {
+        length: points.last().map_or(0.0, |p| p.s),
+        max_curvature: points.iter().map(|p| p.curvature.abs()).fold(0.0, f64::max),
+        points,
+        warnings,
+    })
+}
+
+/// Smooths `waypoints`, or the saved plan when none are given.
+#[tauri::command]
+pub fn smooth_plan(
+    app: AppHandle,
+    options: CurveOptions,
+    waypoints: Option<Vec<Waypoint>>,
+) -> Result<SmoothPath, String> {
+    let waypoints = match waypoints {
+        Some(w) => w,
+        None => read_saved_plan(&app)?,
+    };
+    smooth(&waypoints, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wp(x: f64, y: f64, theta: f64) -> Waypoint {
+        Waypoint { x, y, theta }
+    }
+
+    #[test]
+    fn refuses_paths_with_too_many_points() {
+        let opts = CurveOptions {
+            spacing: 1e-9,
+            ..CurveOptions::default()
+        };
+        let err = smooth(&[wp(0.0, 0.0, 0.0), wp(0.0, 48.0, 0.0)], &opts).unwrap_err();
+        assert!(err.contains("raise spacing"), "{err}");
+
+        // Each leg fits, but together they don't.
+        let opts = CurveOptions {
+            spacing: 0.0001,
+            ..CurveOptions::default()
+        };
+        let plan = [wp(0.0, 0.0, 0.0), wp(0.0, 60.0, 0.0), wp(0.0, 120.0, 0.0)];
+        let err = smooth(&plan, &opts).unwrap_err();
+        assert!(err.contains("the path needs more than"), "{err}");
+    }
+
+    #[test]
+    fn dubins_lsl_length() {
+        // Quarter turn left, 24 in west, quarter turn left: 12π + 24.
+        let opts = CurveOptions {
+            kind: CurveKind::Dubins,
+            allow_reverse: false,
+            ..CurveOptions::default()
+        };
+        let path = smooth(&[wp(0.0, 0.0, 0.0), wp(-48.0, 0.0, 180.0)], &opts).unwrap();
+        assert!((path.length - (12.0 * std::f64::consts::PI + 24.0)).abs() < 1e-9);
+        assert!((path.max_curvature - 1.0 / 12.0).abs() < 1e-12);
+        let end = path.points.last().unwrap();
+        assert!((end.x + 48.0).abs() < 1e-9 && end.y.abs() < 1e-9);
+        assert!(angle_delta(end.theta, 180.0).abs() < 1e-9);
+        assert!(path.warnings.is_empty());
+    }
+
+    #[test]
+    fn hermite_passes_through_waypoints_along_their_theta() {
+        let plan = [
+            wp(0.0, 0.0, 0.0),
+            wp(24.0, 24.0, 90.0),
+            wp(48.0, 0.0, 180.0),
+        ];
+        let path = smooth(&plan, &CurveOptions::default()).unwrap();
+        for (i, w) in plan.iter().enumerate().skip(1) {
+            let end = path.points.iter().rfind(|p| p.waypoint == i).unwrap();
+            assert!(
+                (end.x - w.x).abs() < 1e-9 && (end.y - w.y).abs() < 1e-9,
+                "{i}"
+            );
+            assert!(angle_delta(end.theta, w.theta).abs() < 1e-6, "{i}");
+        }
+        let second = path.points.iter().find(|p| p.waypoint == 1).unwrap();
+        assert!(angle_delta(second.theta, 0.0).abs() < 5.0);
+        assert!(path.points.windows(2).all(|w| w[1].s > w[0].s));
+        assert!(path.points.iter().all(|p| !p.reversed));
+    }
+
+    #[test]
+    fn curvature_is_positive_turning_clockwise() {
+        let right = smooth(
+            &[wp(0.0, 0.0, 0.0), wp(24.0, 24.0, 90.0)],
+            &CurveOptions::default(),
+        )
+        .unwrap();
+        assert!(right.points.iter().skip(1).all(|p| p.curvature > 0.0));
+
+        let left = smooth(
+            &[wp(0.0, 0.0, 0.0), wp(-24.0, 24.0, 270.0)],
+            &CurveOptions::default(),
+        )
+        .unwrap();
+        assert!(left.points.iter().skip(1).all(|p| p.curvature < 0.0));
+    }
+}
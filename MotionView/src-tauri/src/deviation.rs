@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::plan::{angle_delta, read_saved_plan, Waypoint};
+use crate::run::Run;
+use crate::settings;
+use crate::trajectory::{self, ProfileOptions, Trajectory};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Alignment {
+    /// Compare each run pose with the plan point the same distance along.
+    #[default]
+    ArcLength,
+    /// Compare each run pose with where the profiled plan would be at the
+    /// same time since the start of the run.
+    Time,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DeviationOptions {
+    pub align: Alignment,
+    /// A waypoint counts as reached within this many inches.
+    pub near_radius: f64,
+    /// Used to time the plan, and for the expected heading along it.
+    pub profile: ProfileOptions,
+}
+
+impl Default for DeviationOptions {
+    fn default() -> Self {
+        DeviationOptions {
+            align: Alignment::default(),
+            near_radius: 3.0,
+            profile: ProfileOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ErrorStats {
+    pub rms: f64,
+    pub max: f64,
+    pub mean_abs: f64,
+}
+
+impl ErrorStats {
+    /// `max` is the largest magnitude.
+    pub fn of(values: impl IntoIterator<Item = f64>) -> ErrorStats {
+        let (mut n, mut sum_sq, mut sum_abs, mut max) = (0usize, 0.0, 0.0, 0.0f64);
+        for v in values {
+            n += 1;
+            sum_sq += v * v;
+            sum_abs += v.abs();
+            max = max.max(v.abs());
+        }
+        if n == 0 {
+            return ErrorStats::default();
+        }
+        ErrorStats {
+            rms: (sum_sq / n as f64).sqrt(),
+            max,
+            mean_abs: sum_abs / n as f64,
+        }
+    }
+}
+
+/// Errors for one run pose. Lengths in inches, angles in degrees.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviationSample {
+    pub t: u64,
+    pub index: usize,
+    pub ref_x: f64,
+    pub ref_y: f64,
+    pub ref_theta: f64,
+    /// Distance off the plan segment; positive is to the right of travel.
+    pub cross_track: f64,
+    /// Positive when ahead of the reference point.
+    pub along_track: f64,
+    pub distance: f64,
+    /// Run heading minus planned heading.
+    pub heading_error: f64,
+    /// Plan segment `segment` -> `segment + 1` the reference point is on.
+    pub segment: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaypointStats {
+    pub index: usize,
+    pub waypoint: Waypoint,
+    pub closest_distance: f64,
+    pub closest_t: u64,
+    pub closest_index: usize,
+    /// Run heading minus waypoint theta at the closest pose.
+    pub heading_error: f64,
+    /// Total time spent within `near_radius`.
+    pub time_near_ms: u64,
+    pub reached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentStats {
+    pub segment: usize,
+    pub samples: usize,
+    pub duration_ms: u64,
+    pub cross_track: ErrorStats,
+    pub along_track: ErrorStats,
+    pub heading: ErrorStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviationReport {
+    pub align: Alignment,
+    pub plan_length: f64,
+    pub plan_duration_ms: u64,
+    pub run_length: f64,
+    pub run_duration_ms: u64,
+    pub cross_track: ErrorStats,
+    pub along_track: ErrorStats,
+    pub heading: ErrorStats,
+    pub segments: Vec<SegmentStats>,
+    pub waypoints: Vec<WaypointStats>,
+    pub samples: Vec<DeviationSample>,
+}
+
+/// A plan pose to compare against: position, heading and profile time.
+#[derive(Debug, Clone, Copy)]
+struct Reference {
+    x: f64,
+    y: f64,
+    theta: f64,
+    t: f64,
+}
+
+/// Looks up the profiled plan by time (ms) or by distance along it,
+/// interpolating between profile samples. At a turn in place the distance
+/// lookup takes the arriving pose.
+fn reference_at(trajectory: &Trajectory, keys: &[f64], key: f64) -> Reference {
+    let poses = &trajectory.poses;
+    let i = keys.partition_point(|&k| k < key).min(poses.len() - 1);
+    let b = &poses[i];
+    if i == 0 || keys[i] <= key {
+        return Reference {
+            x: b.x,
+            y: b.y,
+            theta: b.theta,
+            t: b.t as f64,
+        };
+    }
+    let a = &poses[i - 1];
+    let span = keys[i] - keys[i - 1];
+    let f = if span > 0.0 {
+        (key - keys[i - 1]) / span
+    } else {
+        1.0
+    };
+    Reference {
+        x: a.x + (b.x - a.x) * f,
+        y: a.y + (b.y - a.y) * f,
+        theta: (a.theta + angle_delta(a.theta, b.theta) * f).rem_euclid(360.0),
+        t: a.t as f64 + (b.t as f64 - a.t as f64) * f,
+    }
+}
+
+/// The plan segment being driven (or turned into) at profile time `t`.
+fn segment_at(trajectory: &Trajectory, waypoints: usize, t: f64) -> Option<usize> {
+    if waypoints < 2 {
+        return None;
+    }
+    let target = trajectory
+        .moves
+        .iter()
+        .find(|m| t <= m.end_ms as f64)
+        .or(trajectory.moves.last())
+        .map_or(1, |m| m.waypoint);
+    Some(target.clamp(1, waypoints - 1) - 1)
+}
+
+fn cumulative_distance(points: impl Iterator<Item = (f64, f64)>) -> Vec<f64> {
+    let mut out = Vec::new();
+    let mut prev: Option<(f64, f64)> = None;
+    let mut total = 0.0;
+    for p in points {
+        if let Some(q) = prev {
+            total += (p.0 - q.0).hypot(p.1 - q.1);
+        }
+        out.push(total);
+        prev = Some(p);
+    }
+    out
+}
+
+/// Compares a run with a plan. Run positions are converted to inches using
+/// `meta.units`; `track_width` is only used to profile the plan.
+pub fn analyze(
+    run: &Run,
+    waypoints: &[Waypoint],
+    opts: &DeviationOptions,
+    track_width: f64,
+) -> Result<DeviationReport, String> {
+    if run.poses.is_empty() {
+        return Err("run has no poses".into());
+    }
+    if !(opts.near_radius.is_finite() && opts.near_radius >= 0.0) {
+        return Err("near_radius must not be negative".into());
+    }
+    let scale = run.inches_scale()?;
+    let trajectory = trajectory::profile(waypoints, &opts.profile, track_width)?;
+
+    let keys: Vec<f64> = match opts.align {
+        Alignment::Time => trajectory.poses.iter().map(|p| p.t as f64).collect(),
+        Alignment::ArcLength => cumulative_distance(trajectory.poses.iter().map(|p| (p.x, p.y))),
+    };
+    let run_xy: Vec<(f64, f64)> = run
+        .poses
+        .iter()
+        .map(|p| (p.x * scale, p.y * scale))
+        .collect();
+    let run_s = cumulative_distance(run_xy.iter().copied());
+    let t0 = run.poses[0].t;
+
+    let samples: Vec<DeviationSample> = run
+        .poses
+        .iter()
+        .enumerate()
+        .map(|(index, pose)| {
+            let (x, y) = run_xy[index];
+            let key = match opts.align {
+                Alignment::Time => pose.t.saturating_sub(t0) as f64,
+                Alignment::ArcLength => run_s[index],
+            };
+            let r = reference_at(&trajectory, &keys, key);
+            let segment = segment_at(&trajectory, waypoints.len(), r.t);
+            // Error measured in the frame of the plan segment, or of the
+            // planned heading when the segment has no length.
+            let along_dir = segment
+                .map(|s| (waypoints[s], waypoints[s + 1]))
+                .map(|(a, b)| (b.x - a.x, b.y - a.y))
+                .filter(|d| d.0.hypot(d.1) > 1e-9)
+                .map(|d| (d.0 / d.0.hypot(d.1), d.1 / d.0.hypot(d.1)))
+                .unwrap_or_else(|| {
+                    let h = r.theta.to_radians();
+                    (h.sin(), h.cos())
+                });
+            let (ex, ey) = (x - r.x, y - r.y);
+            DeviationSample {
+                t: pose.t,
+                index,
+                ref_x: r.x,
+                ref_y: r.y,
+                ref_theta: r.theta,
+                cross_track: ex * along_dir.1 - ey * along_dir.0,
+                along_track: ex * along_dir.0 + ey * along_dir.1,
+                distance: ex.hypot(ey),
+                heading_error: angle_delta(r.theta, pose.theta),
+                segment,
+            }
+        })
+        .collect();
+
+    let dt = |i: usize| {
+        run.poses
+            .get(i + 1)
+            .map_or(0, |n| n.t.saturating_sub(run.poses[i].t))
+    };
+    let waypoint_stats = waypoints
+        .iter()
+        .enumerate()
+        .map(|(index, w)| {
+            let dist = |i: usize| (run_xy[i].0 - w.x).hypot(run_xy[i].1 - w.y);
+            let closest = (0..run_xy.len())
+                .min_by(|&a, &b| dist(a).total_cmp(&dist(b)))
+                .unwrap_or(0);
+            let time_near_ms = (0..run_xy.len())
+                .filter(|&i| dist(i) <= opts.near_radius)
+                .map(dt)
+                .sum();
+            WaypointStats {
+                index,
+                waypoint: *w,
+                closest_distance: dist(closest),
+                closest_t: run.poses[closest].t,
+                closest_index: closest,
+                heading_error: angle_delta(w.theta, run.poses[closest].theta),
+                time_near_ms,
+                reached: dist(closest) <= opts.near_radius,
+            }
+        })
+        .collect();
+
+    let segments = (0..waypoints.len().saturating_sub(1))
+        .filter_map(|segment| {
+            let on: Vec<&DeviationSample> = samples
+                .iter()
+                .filter(|s| s.segment == Some(segment))
+                .collect();
+            if on.is_empty() {
+                return None;
+            }
+            Some(SegmentStats {
+                segment,
+                samples: on.len(),
+                duration_ms: on.iter().map(|s| dt(s.index)).sum(),
+                cross_track: ErrorStats::of(on.iter().map(|s| s.cross_track)),
+                along_track: ErrorStats::of(on.iter().map(|s| s.along_track)),
+                heading: ErrorStats::of(on.iter().map(|s| s.heading_error)),
+            })
+        })
+        .collect();
+
+    Ok(DeviationReport {
+        align: opts.align,
+        plan_length: trajectory.distance,
+        plan_duration_ms: trajectory.duration_ms,
+        run_length: run_s.last().copied().unwrap_or(0.0),
+        run_duration_ms: run.poses[run.poses.len() - 1].t.saturating_sub(t0),
+        cross_track: ErrorStats::of(samples.iter().map(|s| s.cross_track)),
+        along_track: ErrorStats::of(samples.iter().map(|s| s.along_track)),
+        heading: ErrorStats::of(samples.iter().map(|s| s.heading_error)),
+        segments,
+        waypoints: waypoint_stats,
+        samples,
+    })
+}
+
+/// Compares `run` with `waypoints`, or with the saved plan when none are
+/// given.
+#[tauri::command]
+pub fn analyze_run_deviation(
+    app: AppHandle,
+    run: Run,
+    waypoints: Option<Vec<Waypoint>>,
+    options: DeviationOptions,
+) -> Result<DeviationReport, String> {
+    let waypoints = match waypoints {
+        Some(w) => w,
+        None => read_saved_plan(&app)?,
+    };
+    let track_width = match options.profile.track_width {
+        Some(w) => w,
+        None => settings::load_settings(&app)?.unwrap_or_default().robot_w,
+    };
+    analyze(&run, &waypoints, &options, track_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::RunMeta;
+    use crate::telemetry::Pose;
+
+    fn wp(x: f64, y: f64, theta: f64) -> Waypoint {
+        Waypoint { x, y, theta }
+    }
+
+    /// A run along x = `x` from `from_y` to `to_y`, one pose per inch.
+    fn run_along(x: f64, from_y: f64, to_y: f64, theta: f64) -> Run {
+        let n = (to_y - from_y).abs() as u64;
+        let poses = (0..=n)
+            .map(|k| {
+                let y = from_y + (to_y - from_y) * k as f64 / n as f64;
+                Pose::new(k * 20, x, y, theta, None, None)
+            })
+            .collect();
+        Run::new(RunMeta::default(), poses, Vec::new())
+    }
+
+    #[test]
+    fn cross_track_is_positive_right_of_the_plan() {
+        let plan = [wp(0.0, 0.0, 0.0), wp(0.0, 48.0, 0.0)];
+        let report = analyze(
+            &run_along(2.0, 0.0, 48.0, 0.0),
+            &plan,
+            &DeviationOptions::default(),
+            12.0,
+        )
+        .unwrap();
+        assert_eq!(report.samples.len(), 49);
+        for s in &report.samples {
+            assert!((s.cross_track - 2.0).abs() < 1e-9, "{s:?}");
+            assert!(s.along_track.abs() < 1e-9, "{s:?}");
+            assert_eq!(s.segment, Some(0));
+        }
+        assert!((report.cross_track.rms - 2.0).abs() < 1e-9);
+        assert!(report.waypoints.iter().all(|w| w.reached));
+
+        // Driving the other way, the same side is on the left.
+        let plan = [wp(0.0, 48.0, 180.0), wp(0.0, 0.0, 180.0)];
+        let report = analyze(
+            &run_along(2.0, 48.0, 0.0, 180.0),
+            &plan,
+            &DeviationOptions::default(),
+            12.0,
+        )
+        .unwrap();
+        assert!(report
+            .samples
+            .iter()
+            .all(|s| (s.cross_track + 2.0).abs() < 1e-9));
+    }
+}
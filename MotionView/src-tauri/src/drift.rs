@@ -0,0 +1,458 @@
+use serde::{Deserialize, Serialize};
+
+use crate::compare::pose_at;
+use crate::deviation::ErrorStats;
+use crate::plan::angle_delta;
+use crate::run::Run;
+
+type Point = (f64, f64);
+
+/// Where the robot really is when a watch fires. Any coordinate left out is
+/// not checked, e.g. a wall touch that only fixes `x`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferencePoint {
+    pub label: String,
+    /// Only watches with this value count, when given.
+    #[serde(default)]
+    pub value: Option<String>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub theta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DriftModelKind {
+    /// Error grows linearly with time, per axis.
+    #[default]
+    Linear,
+    /// Odometry is scaled and rotated about the start, as from a mis-sized
+    /// tracking wheel or an IMU that reads slightly off.
+    ScaleRotation,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DriftOptions {
+    pub model: DriftModelKind,
+    /// Take the first pose as correct, so the fitted error is zero there.
+    pub anchor_start: bool,
+    /// Also return the run with the fitted drift removed.
+    pub correct: bool,
+}
+
+impl Default for DriftOptions {
+    fn default() -> Self {
+        DriftOptions {
+            model: DriftModelKind::default(),
+            anchor_start: true,
+            correct: false,
+        }
+    }
+}
+
+/// `error = offset + rate * seconds since start`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LinearFit {
+    pub offset: f64,
+    pub rate: f64,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum DriftModel {
+    /// Inches (or degrees for theta) and per second.
+    Linear {
+        x: Option<LinearFit>,
+        y: Option<LinearFit>,
+        theta: Option<LinearFit>,
+    },
+    /// Odometry position relative to `origin` maps to the true position as
+    /// `origin + shift + scale * rotate(odom - origin, rotation)`. `rotation`
+    /// is in compass degrees, so positive turns clockwise.
+    ScaleRotation {
+        scale: f64,
+        rotation: f64,
+        origin_x: f64,
+        origin_y: f64,
+        shift_x: f64,
+        shift_y: f64,
+    },
+}
+
+/// Errors are odometry minus reference; residuals are what is left after
+/// applying the fitted model. Inches and degrees.
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftEvent {
+    pub reference: usize,
+    pub label: String,
+    pub value: String,
+    pub t: u64,
+    pub odom_x: f64,
+    pub odom_y: f64,
+    pub odom_theta: f64,
+    pub error_x: Option<f64>,
+    pub error_y: Option<f64>,
+    pub error_theta: Option<f64>,
+    pub residual_x: Option<f64>,
+    pub residual_y: Option<f64>,
+    pub residual_theta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub model: DriftModel,
+    pub events: Vec<DriftEvent>,
+    /// Position error over the events with both `x` and `y`, before and
+    /// after correction.
+    pub position_error: ErrorStats,
+    pub position_residual: ErrorStats,
+    pub heading_error: ErrorStats,
+    pub heading_residual: ErrorStats,
+    /// Watches that matched a reference but fell outside the recorded poses.
+    pub unmatched: Vec<String>,
+    pub corrected: Option<Run>,
+}
+
+fn fit_line(points: &[(f64, f64)], anchor_start: bool) -> Option<LinearFit> {
+    let n = points.len();
+    if n == 0 {
+        return None;
+    }
+    let (offset, rate) = if anchor_start {
+        let tt: f64 = points.iter().map(|(t, _)| t * t).sum();
+        let te: f64 = points.iter().map(|(t, e)| t * e).sum();
+        (0.0, if tt > 0.0 { te / tt } else { 0.0 })
+    } else {
+        let mt = points.iter().map(|(t, _)| t).sum::<f64>() / n as f64;
+        let me = points.iter().map(|(_, e)| e).sum::<f64>() / n as f64;
+        let tt: f64 = points.iter().map(|(t, _)| (t - mt).powi(2)).sum();
+        let te: f64 = points.iter().map(|(t, e)| (t - mt) * (e - me)).sum();
+        let rate = if tt > 0.0 { te / tt } else { 0.0 };
+        (me - rate * mt, rate)
+    };
+    Some(LinearFit {
+        offset,
+        rate,
+        samples: n,
+    })
+}
+
+impl DriftModel {
+    /// Removes the drift from one pose, `t` in seconds since the run start.
+    fn apply(&self, t: f64, x: f64, y: f64, theta: f64) -> (f64, f64, f64) {
+        match *self {
+            DriftModel::Linear {
+                x: fx,
+                y: fy,
+                theta: ft,
+            } => {
+                let at = |f: Option<LinearFit>| f.map_or(0.0, |f| f.offset + f.rate * t);
+                (x - at(fx), y - at(fy), (theta - at(ft)).rem_euclid(360.0))
+            }
+            DriftModel::ScaleRotation {
+                scale,
+                rotation,
+                origin_x,
+                origin_y,
+                shift_x,
+                shift_y,
+            } => {
+                // Compass rotation is clockwise, the opposite of the math angle.
+                let (s, c) = (-rotation).to_radians().sin_cos();
+                let (dx, dy) = (x - origin_x, y - origin_y);
+                (
+                    origin_x + shift_x + scale * (c * dx - s * dy),
+                    origin_y + shift_y + scale * (s * dx + c * dy),
+                    (theta + rotation).rem_euclid(360.0),
+                )
+            }
+        }
+    }
+}
+
+/// Fits a similarity transform taking `odom` onto `truth`, both relative to
+/// the origin. Returns `(scale, compass rotation, shift)`.
+fn fit_similarity(pairs: &[(Point, Point)], anchor_start: bool) -> (f64, f64, Point) {
+    let n = pairs.len() as f64;
+    let (mo, mt) = if anchor_start {
+        ((0.0, 0.0), (0.0, 0.0))
+    } else {
+        let sum = pairs.iter().fold((0.0, 0.0, 0.0, 0.0), |a, (o, t)| {
+            (a.0 + o.0, a.1 + o.1, a.2 + t.0, a.3 + t.1)
+        });
+        ((sum.0 / n, sum.1 / n), (sum.2 / n, sum.3 / n))
+    };
+    // With z = odom and w = truth as complex numbers, a = sum(conj(z) w) /
+    // sum(|z|^2) is the least-squares scale and rotation.
+    let (mut re, mut im, mut norm) = (0.0, 0.0, 0.0);
+    for (o, t) in pairs {
+        let (zx, zy) = (o.0 - mo.0, o.1 - mo.1);
+        let (wx, wy) = (t.0 - mt.0, t.1 - mt.1);
+        re += zx * wx + zy * wy;
+        im += zx * wy - zy * wx;
+        norm += zx * zx + zy * zy;
+    }
+    if norm <= 1e-12 {
+        return (1.0, 0.0, (mt.0 - mo.0, mt.1 - mo.1));
+    }
+    let (ar, ai) = (re / norm, im / norm);
+    // shift = mean(truth) - a * mean(odom)
+    let shift = (
+        mt.0 - (ar * mo.0 - ai * mo.1),
+        mt.1 - (ai * mo.0 + ar * mo.1),
+    );
+    (ar.hypot(ai), -ai.atan2(ar).to_degrees(), shift)
+}
+
+/// Compares the run with `references` at every matching watch and fits the
+/// chosen drift model. References are in inches; the run is converted using
+/// `meta.units`, and the corrected run is written back in the same units.
+pub fn estimate(
+    run: &Run,
+    references: &[ReferencePoint],
+    opts: &DriftOptions,
+) -> Result<DriftReport, String> {
+    let first = run.poses.first().ok_or("run has no poses")?;
+    if references.is_empty() {
+        return Err("no reference points given".into());
+    }
+    if let Some(r) = references
+        .iter()
+        .find(|r| r.x.is_none() && r.y.is_none() && r.theta.is_none())
+    {
+        return Err(format!("reference {:?} has no x, y or theta", r.label));
+    }
+    let scale = run.inches_scale()?;
+    let t0 = first.t;
+    let secs = |t: u64| t.saturating_sub(t0) as f64 / 1000.0;
+
+    let mut events = Vec::new();
+    let mut unmatched = Vec::new();
+    for w in &run.watches {
+        for (i, r) in references.iter().enumerate() {
+            if w.label != r.label || r.value.as_ref().is_some_and(|v| *v != w.value) {
+                continue;
+            }
+            let Some(odom) = pose_at(&run.poses, scale, w.t) else {
+                unmatched.push(format!("{} at {} ms", w.label, w.t));
+                continue;
+            };
+            events.push(DriftEvent {
+                reference: i,
+                label: w.label.clone(),
+                value: w.value.clone(),
+                t: w.t,
+                odom_x: odom.x,
+                odom_y: odom.y,
+                odom_theta: odom.theta,
+                error_x: r.x.map(|x| odom.x - x),
+                error_y: r.y.map(|y| odom.y - y),
+                error_theta: r.theta.map(|h| angle_delta(h, odom.theta)),
+                residual_x: None,
+                residual_y: None,
+                residual_theta: None,
+            });
+        }
+    }
+    if events.is_empty() {
+        return Err("no watches match the reference labels".into());
+    }
+
+    let model = match opts.model {
+        DriftModelKind::Linear => {
+            let axis = |f: fn(&DriftEvent) -> Option<f64>| {
+                let points: Vec<(f64, f64)> = events
+                    .iter()
+                    .filter_map(|e| f(e).map(|v| (secs(e.t), v)))
+                    .collect();
+                fit_line(&points, opts.anchor_start)
+            };
+            DriftModel::Linear {
+                x: axis(|e| e.error_x),
+                y: axis(|e| e.error_y),
+                theta: axis(|e| e.error_theta),
+            }
+        }
+        DriftModelKind::ScaleRotation => {
+            let origin = (first.x * scale, first.y * scale);
+            let pairs: Vec<_> = events
+                .iter()
+                .filter_map(|e| {
+                    let r = &references[e.reference];
+                    let truth = (r.x? - origin.0, r.y? - origin.1);
+                    Some(((e.odom_x - origin.0, e.odom_y - origin.1), truth))
+                })
+                .collect();
+            let (needed, what) = if opts.anchor_start {
+                (1, "an event")
+            } else {
+                (2, "two events")
+            };
+            if pairs.len() < needed {
+                return Err(format!("scale and rotation needs {what} with both x and y"));
+            }
+            let (s, rotation, shift) = fit_similarity(&pairs, opts.anchor_start);
+            DriftModel::ScaleRotation {
+                scale: s,
+                rotation,
+                origin_x: origin.0,
+                origin_y: origin.1,
+                shift_x: shift.0,
+                shift_y: shift.1,
+            }
+        }
+    };
+
+    for e in &mut events {
+        let r = &references[e.reference];
+        let (x, y, theta) = model.apply(secs(e.t), e.odom_x, e.odom_y, e.odom_theta);
+        e.residual_x = r.x.map(|rx| x - rx);
+        e.residual_y = r.y.map(|ry| y - ry);
+        e.residual_theta = r.theta.map(|h| angle_delta(h, theta));
+    }
+    let distances = |dx: fn(&DriftEvent) -> Option<f64>, dy: fn(&DriftEvent) -> Option<f64>| {
+        ErrorStats::of(
+            events
+                .iter()
+                .filter_map(|e| Some(dx(e)?.hypot(dy(e)?)))
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let corrected = opts.correct.then(|| {
+        let mut out = run.clone();
+        for p in &mut out.poses {
+            let (x, y, theta) = model.apply(secs(p.t), p.x * scale, p.y * scale, p.theta);
+            p.x = x / scale;
+            p.y = y / scale;
+            p.theta = theta;
+        }
+        out
+    });
+
+    Ok(DriftReport {
+        model,
+        position_error: distances(|e| e.error_x, |e| e.error_y),
+        position_residual: distances(|e| e.residual_x, |e| e.residual_y),
+        heading_error: ErrorStats::of(events.iter().filter_map(|e| e.error_theta)),
+        heading_residual: ErrorStats::of(events.iter().filter_map(|e| e.residual_theta)),
+        events,
+        unmatched,
+        corrected,
+    })
+}
+
+#[tauri::command]
+pub fn estimate_drift(
+    run: Run,
+    references: Vec<ReferencePoint>,
+    options: DriftOptions,
+) -> Result<DriftReport, String> {
+    estimate(&run, &references, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::RunMeta;
+    use crate::telemetry::{Pose, Watch};
+
+    const TRUTH: [Point; 4] = [(24.0, 0.0), (24.0, 24.0), (0.0, 48.0), (-24.0, 24.0)];
+
+    /// A run starting at the origin that passes `odom[i]` at `(i + 1)` s,
+    /// with a `cp` watch there.
+    fn run(odom: &[(f64, f64, f64)]) -> Run {
+        let mut poses = vec![Pose::new(0, 0.0, 0.0, 0.0, None, None)];
+        let mut watches = Vec::new();
+        for (i, &(x, y, theta)) in odom.iter().enumerate() {
+            let t = 1000 * (i as u64 + 1);
+            poses.push(Pose::new(t, x, y, theta, None, None));
+            watches.push(Watch {
+                t,
+                level: "INFO".into(),
+                label: "cp".into(),
+                value: i.to_string(),
+            });
+        }
+        Run::new(RunMeta::default(), poses, watches)
+    }
+
+    fn reference(i: usize, theta: Option<f64>) -> ReferencePoint {
+        ReferencePoint {
+            label: "cp".into(),
+            value: Some(i.to_string()),
+            x: Some(TRUTH[i].0),
+            y: Some(TRUTH[i].1),
+            theta,
+        }
+    }
+
+    #[test]
+    fn recovers_scale_and_rotation() {
+        // Odometry reads 5% short and turned 3 degrees counter-clockwise.
+        let (s, c) = 3f64.to_radians().sin_cos();
+        let odom: Vec<_> = TRUTH
+            .iter()
+            .map(|&(x, y)| ((c * x - s * y) / 1.05, (s * x + c * y) / 1.05, 0.0))
+            .collect();
+        let references: Vec<_> = (0..TRUTH.len()).map(|i| reference(i, None)).collect();
+        let opts = DriftOptions {
+            model: DriftModelKind::ScaleRotation,
+            correct: true,
+            ..DriftOptions::default()
+        };
+        let report = estimate(&run(&odom), &references, &opts).unwrap();
+        let DriftModel::ScaleRotation {
+            scale,
+            rotation,
+            shift_x,
+            shift_y,
+            ..
+        } = report.model
+        else {
+            panic!("expected a scale and rotation model");
+        };
+        assert!((scale - 1.05).abs() < 1e-9, "{scale}");
+        assert!((rotation - 3.0).abs() < 1e-9, "{rotation}");
+        assert!(shift_x.abs() < 1e-9 && shift_y.abs() < 1e-9);
+        assert!(report.position_error.max > 1.0);
+        assert!(report.position_residual.max < 1e-9);
+
+        let corrected = report.corrected.unwrap();
+        for (p, truth) in corrected.poses[1..].iter().zip(TRUTH) {
+            assert!((p.x - truth.0).abs() < 1e-9 && (p.y - truth.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn recovers_linear_drift() {
+        // 0.5 in/s in x, -0.25 in/s in y and 1 deg/s of heading, on top of
+        // a constant 2 in x offset.
+        let odom: Vec<_> = TRUTH
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| {
+                let t = (i + 1) as f64;
+                (x + 2.0 + 0.5 * t, y - 0.25 * t, 90.0 + t)
+            })
+            .collect();
+        let references: Vec<_> = (0..TRUTH.len()).map(|i| reference(i, Some(90.0))).collect();
+        let opts = DriftOptions {
+            anchor_start: false,
+            ..DriftOptions::default()
+        };
+        let report = estimate(&run(&odom), &references, &opts).unwrap();
+        let DriftModel::Linear { x, y, theta } = report.model else {
+            panic!("expected a linear model");
+        };
+        let (x, y, theta) = (x.unwrap(), y.unwrap(), theta.unwrap());
+        assert!((x.offset - 2.0).abs() < 1e-9 && (x.rate - 0.5).abs() < 1e-9);
+        assert!(y.offset.abs() < 1e-9 && (y.rate + 0.25).abs() < 1e-9);
+        assert!(theta.offset.abs() < 1e-9 && (theta.rate - 1.0).abs() < 1e-9);
+        assert_eq!(x.samples, 4);
+        assert!(report.position_residual.max < 1e-9);
+        assert!(report.heading_residual.max < 1e-9);
+    }
+}
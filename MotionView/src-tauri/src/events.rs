@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+
+use crate::plan::angle_delta;
+use crate::run::{self, Run};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EventOptions {
+    /// Speeds and accelerations are measured over this much time.
+    pub window_ms: u64,
+    /// Below this speed (in/s) and turn rate (deg/s) the robot is stopped.
+    pub stop_speed: f64,
+    pub stop_turn: f64,
+    pub min_stop_ms: u64,
+    /// Moving faster than this (in/s) in the other direction is a reversal.
+    pub reversal_speed: f64,
+    /// Turning faster than `spin_turn` (deg/s) while travelling slower than
+    /// `spin_speed` (in/s) is a spin in place.
+    pub spin_turn: f64,
+    pub spin_speed: f64,
+    pub min_spin_ms: u64,
+    /// Slowing faster than this (in/s²) is a sudden deceleration.
+    pub decel_limit: f64,
+    /// Only decelerations from at least this speed (in/s) count.
+    pub decel_speed: f64,
+    /// Mean reported wheel speed (0..127) above which low motion is a stall.
+    pub stall_command: f64,
+    pub min_stall_ms: u64,
+    /// A pause in telemetry longer than this is a gap. Defaults to five
+    /// times the usual interval, and at least 100 ms.
+    pub gap_ms: Option<u64>,
+}
+
+impl Default for EventOptions {
+    fn default() -> Self {
+        EventOptions {
+            window_ms: 50,
+            stop_speed: 1.0,
+            stop_turn: 5.0,
+            min_stop_ms: 250,
+            reversal_speed: 3.0,
+            spin_turn: 60.0,
+            spin_speed: 3.0,
+            min_spin_ms: 150,
+            decel_limit: 250.0,
+            decel_speed: 6.0,
+            stall_command: 60.0,
+            min_stall_ms: 200,
+            gap_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Stop,
+    Reversal,
+    Spin,
+    /// Slowed down much faster than the drive normally brakes, usually a hit.
+    HardDecel,
+    /// Wheels commanded fast while the robot barely moves.
+    Stall,
+    /// No telemetry for a while.
+    Gap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunEvent {
+    pub kind: EventKind,
+    pub severity: Severity,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub start_index: usize,
+    pub end_index: usize,
+    /// Kind-specific size: degrees for a spin, in/s² for a deceleration,
+    /// the new speed (in/s) for a reversal and milliseconds otherwise.
+    pub magnitude: f64,
+    pub detail: String,
+}
+
+/// Motion at each pose over the trailing window. Speed is signed (in/s,
+/// negative when driving backwards), turn in deg/s and decel in in/s² from
+/// the speed a window back, `before`.
+struct Motion {
+    speed: Vec<f64>,
+    turn: Vec<f64>,
+    decel: Vec<f64>,
+    before: Vec<f64>,
+}
+
+fn motion(run: &Run, scale: f64, window_ms: u64, gap_ms: u64) -> Motion {
+    let poses = &run.poses;
+    let n = poses.len();
+    let (mut speed, mut turn) = (vec![0.0; n], vec![0.0; n]);
+    let (mut decel, mut before) = (vec![0.0; n], vec![0.0; n]);
+    let mut j = 0;
+    for i in 1..n {
+        while j + 1 < i && poses[i].t.saturating_sub(poses[j + 1].t) >= window_ms {
+            j += 1;
+        }
+        if poses[i].t.saturating_sub(poses[i - 1].t) > gap_ms {
+            continue;
+        }
+        let Some(step) = run::step(&poses[j], &poses[i], scale) else {
+            continue;
+        };
+        speed[i] = step.speed;
+        turn[i] = step.turn / step.dt;
+        before[i] = speed[j].abs();
+        decel[i] = (before[i] - speed[i].abs()) / step.dt;
+    }
+    Motion {
+        speed,
+        turn,
+        decel,
+        before,
+    }
+}
+
+/// Runs of consecutive indices where `pred` holds, as (first, last).
+fn spans(n: usize, pred: impl Fn(usize) -> bool) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for i in 0..=n {
+        match (start, i < n && pred(i)) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                out.push((s, i - 1));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn median_interval(run: &Run) -> u64 {
+    let mut dts: Vec<u64> = run
+        .poses
+        .windows(2)
+        .map(|p| p[1].t.saturating_sub(p[0].t))
+        .filter(|&dt| dt > 0)
+        .collect();
+    dts.sort_unstable();
+    dts.get(dts.len() / 2).copied().unwrap_or(10)
+}
+
+/// Finds stops, reversals, spins, sudden decelerations, stalls and
+/// telemetry gaps, ordered by start time. Positions are converted using
+/// `meta.units`. Poses are expected in time order; out-of-order ones are
+/// skipped rather than measured backwards.
+pub fn detect(run: &Run, opts: &EventOptions) -> Result<Vec<RunEvent>, String> {
+    let scale = run.inches_scale()?;
+    let poses = &run.poses;
+    let n = poses.len();
+    let gap_ms = opts
+        .gap_ms
+        .unwrap_or_else(|| (median_interval(run) * 5).max(100));
+    let m = motion(run, scale, opts.window_ms, gap_ms);
+    let t = |i: usize| poses[i].t;
+    let event = |kind, severity, (s, e): (usize, usize), magnitude: f64, detail: String| RunEvent {
+        kind,
+        severity,
+        start_ms: t(s),
+        end_ms: t(e),
+        start_index: s,
+        end_index: e,
+        magnitude,
+        detail,
+    };
+    let mut events = Vec::new();
+
+    for i in (1..n).filter(|&i| t(i).saturating_sub(t(i - 1)) > gap_ms) {
+        let ms = t(i) - t(i - 1);
+        let severity = if ms >= 1000 {
+            Severity::Error
+        } else {
+            Severity::Warn
+        };
+        let detail = format!("no telemetry for {ms} ms");
+        events.push(event(
+            EventKind::Gap,
+            severity,
+            (i - 1, i),
+            ms as f64,
+            detail,
+        ));
+    }
+
+    let still = |i: usize| m.speed[i].abs() < opts.stop_speed && m.turn[i].abs() < opts.stop_turn;
+    // Standing still with the wheels driven hard is a stall, not a stop.
+    let stalled = |i: usize| still(i) && poses[i].speed >= opts.stall_command;
+    for span in spans(n, |i| still(i) && !stalled(i)) {
+        let ms = t(span.1).saturating_sub(t(span.0));
+        if ms >= opts.min_stop_ms {
+            let detail = format!("stopped for {:.2} s", ms as f64 / 1000.0);
+            events.push(event(
+                EventKind::Stop,
+                Severity::Info,
+                span,
+                ms as f64,
+                detail,
+            ));
+        }
+    }
+
+    let mut last_moving: Option<usize> = None;
+    for i in 0..n {
+        if m.speed[i].abs() < opts.reversal_speed {
+            continue;
+        }
+        if let Some(p) = last_moving.filter(|&p| m.speed[p].signum() != m.speed[i].signum()) {
+            let detail = if m.speed[i] < 0.0 {
+                "reversed from forward to backward"
+            } else {
+                "reversed from backward to forward"
+            };
+            events.push(event(
+                EventKind::Reversal,
+                Severity::Info,
+                (p, i),
+                m.speed[i],
+                detail.to_string(),
+            ));
+        }
+        last_moving = Some(i);
+    }
+
+    let spinning =
+        |i: usize| m.turn[i].abs() >= opts.spin_turn && m.speed[i].abs() < opts.spin_speed;
+    for (s, e) in spans(n, spinning) {
+        if t(e).saturating_sub(t(s)) >= opts.min_spin_ms {
+            // The turn rate lags by up to a window; count the turn from there.
+            let start = poses[..s].partition_point(|p| p.t.saturating_add(opts.window_ms) < t(s));
+            let degrees = (start..e)
+                .map(|i| angle_delta(poses[i].theta, poses[i + 1].theta))
+                .sum::<f64>();
+            let detail = format!("spun {:.0}° in place", degrees.abs());
+            events.push(event(
+                EventKind::Spin,
+                Severity::Info,
+                (start, e),
+                degrees,
+                detail,
+            ));
+        }
+    }
+
+    let braking = |i: usize| m.decel[i] > opts.decel_limit && m.before[i] >= opts.decel_speed;
+    for span in spans(n, braking) {
+        let peak = (span.0..=span.1).map(|i| m.decel[i]).fold(0.0, f64::max);
+        let severity = if peak > 2.0 * opts.decel_limit {
+            Severity::Error
+        } else {
+            Severity::Warn
+        };
+        let detail = format!("decelerated at {peak:.0} in/s²");
+        events.push(event(EventKind::HardDecel, severity, span, peak, detail));
+    }
+
+    for span in spans(n, stalled) {
+        let ms = t(span.1).saturating_sub(t(span.0));
+        if ms >= opts.min_stall_ms {
+            let command = (span.0..=span.1).map(|i| poses[i].speed).sum::<f64>()
+                / (span.1 - span.0 + 1) as f64;
+            let severity = if ms >= 1000 {
+                Severity::Error
+            } else {
+                Severity::Warn
+            };
+            let detail = format!(
+                "stalled for {:.2} s at {command:.0}/127",
+                ms as f64 / 1000.0
+            );
+            events.push(event(EventKind::Stall, severity, span, ms as f64, detail));
+        }
+    }
+
+    events.sort_by_key(|e| (e.start_ms, e.end_ms));
+    Ok(events)
+}
+
+/// Returns the run with `events` filled in, ready to save or draw.
+#[tauri::command]
+pub fn detect_run_events(mut run: Run, options: EventOptions) -> Result<Run, String> {
+    // A run straight from the webview hasn't been through `run::from_value`.
+    run.poses.sort_by_key(|p| p.t);
+    run.events = detect(&run, &options)?;
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::RunMeta;
+    use crate::telemetry::Pose;
+
+    /// Drives forward at 25 in/s for a second, then sits still for a second.
+    fn drive_then_stop() -> Run {
+        let poses = (0..=200)
+            .map(|i| {
+                let t = i * 10;
+                let y = 0.25 * i.min(100) as f64;
+                Pose::new(t, 0.0, y, 0.0, Some(40.0), Some(40.0))
+            })
+            .collect();
+        Run::new(RunMeta::default(), poses, Vec::new())
+    }
+
+    #[test]
+    fn finds_the_stop() {
+        let events = detect(&drive_then_stop(), &EventOptions::default()).unwrap();
+        let stop = events.iter().find(|e| e.kind == EventKind::Stop).unwrap();
+        assert!((1000..=1060).contains(&stop.start_ms), "{stop:?}");
+        assert_eq!(stop.end_ms, 2000);
+    }
+
+    #[test]
+    fn out_of_order_poses_do_not_panic() {
+        let mut run = drive_then_stop();
+        run.poses.swap(10, 150);
+        run.poses.swap(0, 200);
+        detect(&run, &EventOptions::default()).unwrap();
+
+        let sorted = detect_run_events(run, EventOptions::default()).unwrap();
+        let expected = detect(&drive_then_stop(), &EventOptions::default()).unwrap();
+        assert_eq!(sorted.events, expected);
+    }
+
+    #[test]
+    fn events_survive_json_and_compact() {
+        let run = detect_run_events(drive_then_stop(), EventOptions::default()).unwrap();
+        assert!(!run.events.is_empty());
+        let json = serde_json::to_string(&run).unwrap();
+        assert_eq!(run::from_str(&json).unwrap(), run);
+        let bytes = crate::compact::encode_checked(&run).unwrap();
+        assert_eq!(crate::compact::decode(&bytes).unwrap(), run);
+
+        let plain = serde_json::to_value(drive_then_stop()).unwrap();
+        assert!(plain.get("events").is_none());
+    }
+}
@@ -0,0 +1,1217 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::plan::{angle_delta, read_saved_plan, Waypoint};
+use crate::run::{inches_per_unit, RobotSize, Run};
+use crate::settings;
+use crate::trajectory::{self, ProfileOptions};
+
+/// Season packages shipped with the app; their images are bundled assets.
+/// An installed package with the same id replaces the built-in one.
+const BUILTIN_PACKAGES: [&str; 1] = [include_str!("../fields/pushback-2025-2026.json")];
+const FIELDS_DIR: &str = "Fields";
+const MANIFEST_FILE: &str = "field.json";
+pub const CURRENT_PACKAGE_FORMAT: u64 = 1;
+/// Sparse poses are filled in so the footprint never jumps further than this.
+const SWEEP_STEP_IN: f64 = 1.0;
+const SWEEP_STEP_DEG: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub max_x: f64,
+    pub min_y: f64,
+    pub max_y: f64,
+}
+
+/// `rotation` is in compass degrees, like theta.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Shape {
+    /// Centered on (x, y); `w` along x and `h` along y before rotating.
+    Rect {
+        x: f64,
+        y: f64,
+        w: f64,
+        h: f64,
+        #[serde(default)]
+        rotation: f64,
+    },
+    Circle {
+        x: f64,
+        y: f64,
+        r: f64,
+    },
+    Polygon {
+        points: Vec<[f64; 2]>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElementKind {
+    Wall,
+    Goal,
+    Barrier,
+    Loader,
+    /// Scoring or parking area; drawn, but never collided with.
+    Zone,
+}
+
+impl ElementKind {
+    fn name(self) -> &'static str {
+        match self {
+            ElementKind::Wall => "wall",
+            ElementKind::Goal => "goal",
+            ElementKind::Barrier => "barrier",
+            ElementKind::Loader => "loader",
+            ElementKind::Zone => "zone",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldElement {
+    pub id: String,
+    pub kind: ElementKind,
+    pub shape: Shape,
+}
+
+/// Where a package's (0, 0) is on the field image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    #[default]
+    Center,
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+/// Screen direction a package axis points in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    Right,
+    Left,
+    Up,
+    Down,
+}
+
+impl Axis {
+    fn unit(self) -> Point {
+        match self {
+            Axis::Right => (1.0, 0.0),
+            Axis::Left => (-1.0, 0.0),
+            Axis::Up => (0.0, 1.0),
+            Axis::Down => (0.0, -1.0),
+        }
+    }
+}
+
+fn default_x_axis() -> Axis {
+    Axis::Right
+}
+
+fn default_y_axis() -> Axis {
+    Axis::Up
+}
+
+fn default_units() -> String {
+    "in".to_string()
+}
+
+/// Physical size of the area the field image covers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dimensions {
+    pub width: f64,
+    pub height: f64,
+    #[serde(default = "default_units")]
+    pub units: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageImage {
+    pub label: String,
+    /// Image file next to the manifest, or a data URL in single-file packages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Bundled asset key; only built-in packages use this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+/// A package's `field.json`. Geometry is in the package's own units and
+/// axes, measured from `origin`, and is converted to MotionView's frame
+/// (inches, origin at the field center, +x right, +y up) when loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldPackage {
+    pub format: u64,
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub images: Vec<PackageImage>,
+    pub dimensions: Dimensions,
+    #[serde(default)]
+    pub origin: Origin,
+    #[serde(default = "default_x_axis")]
+    pub x_axis: Axis,
+    #[serde(default = "default_y_axis")]
+    pub y_axis: Axis,
+    /// Inside faces of the perimeter walls; defaults to the whole image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Bounds>,
+    #[serde(default)]
+    pub elements: Vec<FieldElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldImage {
+    /// What `selectedField` stores for this image.
+    pub key: String,
+    pub label: String,
+    /// The image file of an installed package; built-in images are bundled.
+    pub path: Option<String>,
+}
+
+/// A package converted to MotionView's frame.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldModel {
+    pub id: String,
+    pub name: String,
+    pub season: Option<String>,
+    pub version: Option<String>,
+    pub images: Vec<FieldImage>,
+    pub width: f64,
+    pub height: f64,
+    pub bounds: Bounds,
+    pub elements: Vec<FieldElement>,
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldInfo {
+    pub id: String,
+    pub name: String,
+    pub season: Option<String>,
+    pub version: Option<String>,
+    pub images: Vec<FieldImage>,
+    pub builtin: bool,
+}
+
+impl From<FieldModel> for FieldInfo {
+    fn from(f: FieldModel) -> Self {
+        FieldInfo {
+            id: f.id,
+            name: f.name,
+            season: f.season,
+            version: f.version,
+            images: f.images,
+            builtin: f.builtin,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CollisionOptions {
+    /// Field model id or image key; defaults to the selected field.
+    pub field: Option<String>,
+    /// Defaults to `meta.robot` for runs and the settings robot size for plans.
+    pub robot: Option<RobotSize>,
+    /// Extra margin around the robot, in inches.
+    pub clearance: f64,
+    /// Element ids or kinds to leave out, e.g. `"barrier"`.
+    pub ignore: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    Collision,
+    OutOfBounds,
+}
+
+/// A stretch of consecutive poses that hit the same element.
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    /// Element id, or `"perimeter"` for out-of-bounds.
+    pub element: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Pose indexes into the checked poses (the run, or the profiled plan).
+    pub start_index: usize,
+    pub end_index: usize,
+    /// Robot position when the violation starts.
+    pub x: f64,
+    pub y: f64,
+    /// For plans, the waypoint being driven to.
+    pub waypoint: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollisionReport {
+    pub field: String,
+    pub robot: RobotSize,
+    pub poses_checked: usize,
+    pub violations: Vec<Violation>,
+}
+
+type Point = (f64, f64);
+
+enum Outline {
+    Polygon(Vec<Point>),
+    Circle(Point, f64),
+}
+
+/// Corners of a `w` by `h` box centered on (x, y), turned by compass degrees.
+fn box_corners(x: f64, y: f64, w: f64, h: f64, rotation: f64) -> Vec<Point> {
+    let (s, c) = rotation.to_radians().sin_cos();
+    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .iter()
+        .map(|&(i, j)| {
+            let (lx, ly) = (i * w / 2.0, j * h / 2.0);
+            (x + lx * c + ly * s, y - lx * s + ly * c)
+        })
+        .collect()
+}
+
+fn outline(shape: &Shape) -> Outline {
+    match *shape {
+        Shape::Rect {
+            x,
+            y,
+            w,
+            h,
+            rotation,
+        } => Outline::Polygon(box_corners(x, y, w, h, rotation)),
+        Shape::Circle { x, y, r } => Outline::Circle((x, y), r),
+        Shape::Polygon { ref points } => {
+            Outline::Polygon(points.iter().map(|p| (p[0], p[1])).collect())
+        }
+    }
+}
+
+fn edges(poly: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    poly.iter()
+        .enumerate()
+        .map(|(i, &a)| (a, poly[(i + 1) % poly.len()]))
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Whether `p`, already known to be on the line through `s`, is within `s`.
+fn within(p: Point, s: (Point, Point)) -> bool {
+    p.0 >= s.0 .0.min(s.1 .0)
+        && p.0 <= s.0 .0.max(s.1 .0)
+        && p.1 >= s.0 .1.min(s.1 .1)
+        && p.1 <= s.0 .1.max(s.1 .1)
+}
+
+fn segments_cross(a: (Point, Point), b: (Point, Point)) -> bool {
+    let d1 = cross(b.0, b.1, a.0);
+    let d2 = cross(b.0, b.1, a.1);
+    let d3 = cross(a.0, a.1, b.0);
+    let d4 = cross(a.0, a.1, b.1);
+    if d1 == 0.0 && d2 == 0.0 {
+        // Collinear: they meet only where their extents overlap.
+        return within(a.0, b) || within(a.1, b) || within(b.0, a) || within(b.1, a);
+    }
+    d1 * d2 <= 0.0 && d3 * d4 <= 0.0
+}
+
+fn contains(poly: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(poly) {
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn segment_distance(p: Point, (a, b): (Point, Point)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+fn overlaps(robot: &[Point], other: &Outline) -> bool {
+    match other {
+        Outline::Polygon(poly) if poly.len() >= 3 => {
+            edges(robot).any(|e| edges(poly).any(|f| segments_cross(e, f)))
+                || contains(poly, robot[0])
+                || contains(robot, poly[0])
+        }
+        Outline::Polygon(_) => false,
+        Outline::Circle(c, r) => {
+            contains(robot, *c) || edges(robot).any(|e| segment_distance(*c, e) <= *r)
+        }
+    }
+}
+
+/// A pose to check: time, position, heading and the plan waypoint, if any.
+#[derive(Debug, Clone, Copy)]
+struct Placed {
+    t: u64,
+    x: f64,
+    y: f64,
+    theta: f64,
+    index: usize,
+    waypoint: Option<usize>,
+}
+
+/// Adds in-between poses wherever consecutive ones are far apart, so a
+/// thinned run still sweeps the whole footprint.
+fn sweep(poses: &[Placed]) -> Vec<Placed> {
+    let mut out = Vec::with_capacity(poses.len());
+    for pair in poses.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        out.push(a);
+        let turn = angle_delta(a.theta, b.theta);
+        let gap = ((b.x - a.x).hypot(b.y - a.y) / SWEEP_STEP_IN).max(turn.abs() / SWEEP_STEP_DEG);
+        let n = gap.ceil() as usize;
+        for k in 1..n {
+            let f = k as f64 / n as f64;
+            out.push(Placed {
+                t: a.t + ((b.t.saturating_sub(a.t)) as f64 * f).round() as u64,
+                x: a.x + (b.x - a.x) * f,
+                y: a.y + (b.y - a.y) * f,
+                theta: a.theta + turn * f,
+                ..a
+            });
+        }
+    }
+    out.extend(poses.last().copied());
+    out
+}
+
+fn ignored(element: &FieldElement, ignore: &[String]) -> bool {
+    ignore
+        .iter()
+        .any(|i| *i == element.id || i == element.kind.name())
+}
+
+/// Checks every pose's footprint against the field perimeter and the solid
+/// elements, merging consecutive hits on the same element into one violation.
+fn check(
+    field: &FieldModel,
+    robot: &RobotSize,
+    opts: &CollisionOptions,
+    poses: &[Placed],
+) -> Vec<Violation> {
+    let solids: Vec<(&FieldElement, Outline)> = field
+        .elements
+        .iter()
+        .filter(|e| e.kind != ElementKind::Zone && !ignored(e, &opts.ignore))
+        .map(|e| (e, outline(&e.shape)))
+        .collect();
+    let b = field.bounds;
+    let (w, h) = (
+        robot.width + 2.0 * opts.clearance,
+        robot.height + 2.0 * opts.clearance,
+    );
+
+    let mut violations: Vec<Violation> = Vec::new();
+    // Violations still growing, by (kind, element).
+    let mut open: Vec<usize> = Vec::new();
+    for p in sweep(poses) {
+        let corners = box_corners(p.x, p.y, w, h, p.theta);
+        let mut hits: Vec<(ViolationKind, &str)> = Vec::new();
+        if corners
+            .iter()
+            .any(|c| c.0 < b.min_x || c.0 > b.max_x || c.1 < b.min_y || c.1 > b.max_y)
+        {
+            hits.push((ViolationKind::OutOfBounds, "perimeter"));
+        }
+        for (element, shape) in &solids {
+            if overlaps(&corners, shape) {
+                hits.push((ViolationKind::Collision, &element.id));
+            }
+        }
+
+        let mut still_open = Vec::new();
+        for (kind, element) in hits {
+            let existing = open
+                .iter()
+                .copied()
+                .find(|&i| violations[i].kind == kind && violations[i].element == element);
+            match existing {
+                Some(i) => {
+                    violations[i].end_ms = p.t;
+                    violations[i].end_index = p.index;
+                    still_open.push(i);
+                }
+                None => {
+                    violations.push(Violation {
+                        kind,
+                        element: element.to_string(),
+                        start_ms: p.t,
+                        end_ms: p.t,
+                        start_index: p.index,
+                        end_index: p.index,
+                        x: p.x,
+                        y: p.y,
+                        waypoint: p.waypoint,
+                    });
+                    still_open.push(violations.len() - 1);
+                }
+            }
+        }
+        open = still_open;
+    }
+    violations
+}
+
+fn fields_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e: tauri::Error| e.to_string())?
+        .join(FIELDS_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Package ids double as folder names.
+fn check_id(id: &str) -> Result<(), String> {
+    let ok = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if ok {
+        Ok(())
+    } else {
+        Err(format!(
+            "field package id {id:?} may only use letters, digits, '-', '_' and '.'"
+        ))
+    }
+}
+
+fn plain_file_name(name: &str) -> Result<&str, String> {
+    let plain = Path::new(name).file_name().and_then(|n| n.to_str()) == Some(name);
+    if name.is_empty() || !plain {
+        return Err(format!("{name:?} must be a file next to {MANIFEST_FILE}"));
+    }
+    Ok(name)
+}
+
+/// Maps package coordinates into MotionView's frame.
+struct Frame {
+    origin: Point,
+    ex: Point,
+    ey: Point,
+    scale: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Frame {
+    fn new(package: &FieldPackage) -> Result<Frame, String> {
+        let dims = &package.dimensions;
+        // Packages may also be measured in field tiles, like the units setting.
+        let scale = match dims.units.as_str() {
+            "tiles" => Some(24.0),
+            units => inches_per_unit(units),
+        }
+        .ok_or_else(|| format!("dimensions: unknown units {:?}", dims.units))?;
+        let ok = |v: f64| v.is_finite() && v > 0.0;
+        if !ok(dims.width) || !ok(dims.height) {
+            return Err("dimensions must be positive".into());
+        }
+        let (ex, ey) = (package.x_axis.unit(), package.y_axis.unit());
+        if ex.0 * ey.0 + ex.1 * ey.1 != 0.0 {
+            return Err("x_axis and y_axis must be perpendicular".into());
+        }
+        let (w, h) = (dims.width * scale, dims.height * scale);
+        let origin = match package.origin {
+            Origin::Center => (0.0, 0.0),
+            Origin::BottomLeft => (-w / 2.0, -h / 2.0),
+            Origin::BottomRight => (w / 2.0, -h / 2.0),
+            Origin::TopLeft => (-w / 2.0, h / 2.0),
+            Origin::TopRight => (w / 2.0, h / 2.0),
+        };
+        Ok(Frame {
+            origin,
+            ex,
+            ey,
+            scale,
+            width: w,
+            height: h,
+        })
+    }
+
+    fn point(&self, (x, y): Point) -> Point {
+        (
+            self.origin.0 + self.scale * (x * self.ex.0 + y * self.ey.0),
+            self.origin.1 + self.scale * (x * self.ex.1 + y * self.ey.1),
+        )
+    }
+
+    fn points(&self, points: impl IntoIterator<Item = Point>) -> Vec<[f64; 2]> {
+        points
+            .into_iter()
+            .map(|p| {
+                let (x, y) = self.point(p);
+                [x, y]
+            })
+            .collect()
+    }
+
+    /// Rects stay rects unless the axes are turned or mirrored.
+    fn shape(&self, shape: &Shape) -> Shape {
+        let upright = self.ex == (1.0, 0.0) && self.ey == (0.0, 1.0);
+        match *shape {
+            Shape::Rect {
+                x,
+                y,
+                w,
+                h,
+                rotation,
+            } if upright => {
+                let (x, y) = self.point((x, y));
+                Shape::Rect {
+                    x,
+                    y,
+                    w: w * self.scale,
+                    h: h * self.scale,
+                    rotation,
+                }
+            }
+            Shape::Rect {
+                x,
+                y,
+                w,
+                h,
+                rotation,
+            } => Shape::Polygon {
+                points: self.points(box_corners(x, y, w, h, rotation)),
+            },
+            Shape::Circle { x, y, r } => {
+                let (x, y) = self.point((x, y));
+                Shape::Circle {
+                    x,
+                    y,
+                    r: r * self.scale,
+                }
+            }
+            Shape::Polygon { ref points } => Shape::Polygon {
+                points: self.points(points.iter().map(|p| (p[0], p[1]))),
+            },
+        }
+    }
+
+    fn bounds(&self, bounds: Option<Bounds>) -> Bounds {
+        let Some(b) = bounds else {
+            return Bounds {
+                min_x: -self.width / 2.0,
+                max_x: self.width / 2.0,
+                min_y: -self.height / 2.0,
+                max_y: self.height / 2.0,
+            };
+        };
+        let (a, c) = (
+            self.point((b.min_x, b.min_y)),
+            self.point((b.max_x, b.max_y)),
+        );
+        Bounds {
+            min_x: a.0.min(c.0),
+            max_x: a.0.max(c.0),
+            min_y: a.1.min(c.1),
+            max_y: a.1.max(c.1),
+        }
+    }
+}
+
+/// Validates a package and converts it to MotionView's frame. `dir` is the
+/// installed package folder; `None` means a built-in package.
+fn normalize(package: &FieldPackage, dir: Option<&Path>) -> Result<FieldModel, String> {
+    if package.format == 0 || package.format > CURRENT_PACKAGE_FORMAT {
+        return Err(format!(
+            "field package format {} is not supported (this MotionView reads up to {CURRENT_PACKAGE_FORMAT})",
+            package.format
+        ));
+    }
+    check_id(&package.id)?;
+    if package.name.trim().is_empty() {
+        return Err(format!("{}: name is empty", package.id));
+    }
+    let frame = Frame::new(package).map_err(|e| format!("{}: {e}", package.id))?;
+
+    let images = package
+        .images
+        .iter()
+        .map(|image| {
+            let missing = || format!("{}: image {:?} has no file", package.id, image.label);
+            let (key, path) = match dir {
+                None => (image.key.clone().ok_or_else(missing)?, None),
+                Some(dir) => {
+                    let file = plain_file_name(image.file.as_deref().ok_or_else(missing)?)?;
+                    let path = dir.join(file).to_string_lossy().to_string();
+                    (format!("{}/{file}", package.id), Some(path))
+                }
+            };
+            Ok(FieldImage {
+                key,
+                label: image.label.clone(),
+                path,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let bounds = frame.bounds(package.bounds);
+    if !(bounds.min_x < bounds.max_x && bounds.min_y < bounds.max_y) {
+        return Err(format!("{}: bounds are empty", package.id));
+    }
+    Ok(FieldModel {
+        id: package.id.clone(),
+        name: package.name.clone(),
+        season: package.season.clone(),
+        version: package.version.clone(),
+        images,
+        width: frame.width,
+        height: frame.height,
+        bounds,
+        elements: package
+            .elements
+            .iter()
+            .map(|e| FieldElement {
+                shape: frame.shape(&e.shape),
+                ..e.clone()
+            })
+            .collect(),
+        builtin: dir.is_none(),
+    })
+}
+
+fn read_package(dir: &Path) -> Result<FieldModel, String> {
+    let contents = std::fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(|e| e.to_string())?;
+    let package: FieldPackage = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    normalize(&package, Some(dir))
+}
+
+/// Built-in packages followed by installed ones, which replace built-ins
+/// with the same id. Broken installed packages are reported and skipped.
+fn load_fields(app: &AppHandle) -> Result<Vec<FieldModel>, String> {
+    let mut fields = BUILTIN_PACKAGES
+        .iter()
+        .map(|c| {
+            let package: FieldPackage = serde_json::from_str(c).map_err(|e| e.to_string())?;
+            normalize(&package, None)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(fields_dir(app)?)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| !n.starts_with('.'))
+        })
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        match read_package(&dir) {
+            Ok(field) => {
+                fields.retain(|f| f.id != field.id);
+                fields.push(field);
+            }
+            Err(e) => eprintln!("FIELD PACKAGE ERROR: {}: {e}", dir.display()),
+        }
+    }
+    Ok(fields)
+}
+
+fn find_field(app: &AppHandle, key: Option<&str>) -> Result<FieldModel, String> {
+    let key = match key {
+        Some(k) => k.to_string(),
+        None => {
+            settings::load_settings(app)?
+                .unwrap_or_default()
+                .selected_field
+        }
+    };
+    load_fields(app)?
+        .into_iter()
+        .find(|f| f.id == key || f.images.iter().any(|i| i.key == key))
+        .ok_or_else(|| format!("no field model for {key:?}"))
+}
+
+/// Copies the package's images into `staging` (decoding data URLs) and
+/// writes the manifest there, pointing at the copies.
+fn stage_package(package: &mut FieldPackage, source: &Path, staging: &Path) -> Result<(), String> {
+    for (i, image) in package.images.iter_mut().enumerate() {
+        image.key = None;
+        let file = image
+            .file
+            .take()
+            .ok_or_else(|| format!("image {:?} has no file", image.label))?;
+        let name = if file.starts_with("data:") {
+            let (mime, bytes) = settings::parse_data_url(&file)?;
+            let name = format!("image-{}.{}", i + 1, settings::ext_from_mime(&mime));
+            std::fs::write(staging.join(&name), bytes).map_err(|e| e.to_string())?;
+            name
+        } else {
+            let name = plain_file_name(&file)?.to_string();
+            std::fs::copy(source.join(&name), staging.join(&name))
+                .map_err(|e| format!("{name}: {e}"))?;
+            name
+        };
+        image.file = Some(name);
+    }
+    let manifest = serde_json::to_string_pretty(package).map_err(|e| e.to_string())?;
+    std::fs::write(staging.join(MANIFEST_FILE), manifest).map_err(|e| e.to_string())?;
+    read_package(staging).map(|_| ())
+}
+
+fn settings_robot(app: &AppHandle) -> Result<RobotSize, String> {
+    let settings = settings::load_settings(app)?.unwrap_or_default();
+    Ok(RobotSize {
+        width: settings.robot_w,
+        height: settings.robot_h,
+    })
+}
+
+fn check_robot(robot: &RobotSize, clearance: f64) -> Result<(), String> {
+    let ok = |v: f64| v.is_finite() && v > 0.0;
+    if !ok(robot.width) || !ok(robot.height) {
+        return Err("robot width and height must be positive".into());
+    }
+    if !(clearance.is_finite() && clearance >= 0.0) {
+        return Err("clearance must not be negative".into());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_fields(app: AppHandle) -> Result<Vec<FieldInfo>, String> {
+    Ok(load_fields(&app)?
+        .into_iter()
+        .map(FieldInfo::from)
+        .collect())
+}
+
+/// `key` is a model id or image key; defaults to the selected field.
+#[tauri::command]
+pub fn get_field(app: AppHandle, key: Option<String>) -> Result<FieldModel, String> {
+    find_field(&app, key.as_deref())
+}
+
+/// Sweeps the plan as `profile_plan` would drive it (default limits, robot
+/// width as track width).
+#[tauri::command]
+pub fn check_plan_collisions(
+    app: AppHandle,
+    options: CollisionOptions,
+    waypoints: Option<Vec<Waypoint>>,
+) -> Result<CollisionReport, String> {
+    let field = find_field(&app, options.field.as_deref())?;
+    let robot = match options.robot.clone() {
+        Some(r) => r,
+        None => settings_robot(&app)?,
+    };
+    check_robot(&robot, options.clearance)?;
+    let waypoints = match waypoints {
+        Some(w) => w,
+        None => read_saved_plan(&app)?,
+    };
+    let profiled = trajectory::profile(&waypoints, &ProfileOptions::default(), robot.width)?;
+
+    let mut moves = profiled.moves.iter().peekable();
+    let poses: Vec<Placed> = profiled
+        .poses
+        .iter()
+        .enumerate()
+        .map(|(index, p)| {
+            while moves.next_if(|m| m.end_ms < p.t).is_some() {}
+            Placed {
+                t: p.t,
+                x: p.x,
+                y: p.y,
+                theta: p.theta,
+                index,
+                waypoint: moves.peek().map(|m| m.waypoint),
+            }
+        })
+        .collect();
+
+    Ok(CollisionReport {
+        violations: check(&field, &robot, &options, &poses),
+        field: field.id,
+        robot,
+        poses_checked: poses.len(),
+    })
+}
+
+/// Positions in other units are converted to inches using `meta.units`.
+#[tauri::command]
+pub fn check_run_collisions(
+    app: AppHandle,
+    options: CollisionOptions,
+    run: Run,
+) -> Result<CollisionReport, String> {
+    let field = find_field(&app, options.field.as_deref())?;
+    let scale = run.inches_scale()?;
+    let robot = match (options.robot.clone(), run.meta.robot.as_ref()) {
+        (Some(r), _) => r,
+        (None, Some(r)) => RobotSize {
+            width: r.width * scale,
+            height: r.height * scale,
+        },
+        (None, None) => settings_robot(&app)?,
+    };
+    check_robot(&robot, options.clearance)?;
+
+    let poses: Vec<Placed> = run
+        .poses
+        .iter()
+        .enumerate()
+        .map(|(index, p)| Placed {
+            t: p.t,
+            x: p.x * scale,
+            y: p.y * scale,
+            theta: p.theta,
+            index,
+            waypoint: None,
+        })
+        .collect();
+
+    Ok(CollisionReport {
+        violations: check(&field, &robot, &options, &poses),
+        field: field.id,
+        robot,
+        poses_checked: poses.len(),
+    })
+}
+
+/// Installs a package from a folder holding `field.json` and its images, or
+/// from a single manifest file whose images are data URLs or sit next to it.
+/// An installed package with the same id is replaced.
+#[tauri::command]
+pub fn install_field_package(app: AppHandle, path: String) -> Result<FieldInfo, String> {
+    let source = PathBuf::from(path);
+    let (manifest, base) = if source.is_dir() {
+        (source.join(MANIFEST_FILE), source.clone())
+    } else {
+        let base = source.parent().map(Path::to_path_buf).unwrap_or_default();
+        (source.clone(), base)
+    };
+    let contents =
+        std::fs::read_to_string(&manifest).map_err(|e| format!("{}: {e}", manifest.display()))?;
+    let mut package: FieldPackage = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    check_id(&package.id)?;
+
+    let root = fields_dir(&app)?;
+    let staging = root.join(format!(".install-{}", package.id));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging).map_err(|e| e.to_string())?;
+    }
+    std::fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+    if let Err(e) = stage_package(&mut package, &base, &staging) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let target = root.join(&package.id);
+    if target.exists() {
+        std::fs::remove_dir_all(&target).map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(&staging, &target).map_err(|e| e.to_string())?;
+    read_package(&target).map(FieldInfo::from)
+}
+
+/// Removes an installed package. Built-in packages can't be removed, but a
+/// built-in that was replaced comes back.
+#[tauri::command]
+pub fn remove_field_package(app: AppHandle, id: String) -> Result<(), String> {
+    check_id(&id)?;
+    let dir = fields_dir(&app)?.join(&id);
+    if !dir.is_dir() {
+        return Err(format!("no installed field package {id:?}"));
+    }
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn package(extra: serde_json::Value) -> FieldPackage {
+        let mut value = json!({
+            "format": 1,
+            "id": "test-field",
+            "name": "Test Field",
+            "dimensions": { "width": 144, "height": 144 },
+        });
+        for (k, v) in extra.as_object().unwrap() {
+            value[k] = v.clone();
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn builtin() -> FieldModel {
+        normalize(&serde_json::from_str(BUILTIN_PACKAGES[0]).unwrap(), None).unwrap()
+    }
+
+    fn placed(index: usize, t: u64, x: f64, y: f64, theta: f64) -> Placed {
+        Placed {
+            t,
+            x,
+            y,
+            theta,
+            index,
+            waypoint: None,
+        }
+    }
+
+    fn close(a: Point, b: Point) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    #[test]
+    fn frame_moves_each_origin_to_its_corner() {
+        let cases = [
+            ("center", (0.0, 0.0)),
+            ("bottom-left", (-72.0, -72.0)),
+            ("bottom-right", (72.0, -72.0)),
+            ("top-left", (-72.0, 72.0)),
+            ("top-right", (72.0, 72.0)),
+        ];
+        for (origin, corner) in cases {
+            let frame = Frame::new(&package(json!({ "origin": origin }))).unwrap();
+            assert_eq!(frame.point((0.0, 0.0)), corner, "{origin}");
+            assert_eq!(
+                frame.point((10.0, 20.0)),
+                (corner.0 + 10.0, corner.1 + 20.0),
+                "{origin}"
+            );
+        }
+    }
+
+    #[test]
+    fn frame_handles_mirrored_and_turned_axes() {
+        let mirrored = Frame::new(&package(json!({
+            "origin": "top-right", "x_axis": "left", "y_axis": "down",
+        })))
+        .unwrap();
+        assert_eq!(mirrored.point((10.0, 20.0)), (62.0, 52.0));
+        let b = mirrored.bounds(Some(Bounds {
+            min_x: 0.0,
+            max_x: 144.0,
+            min_y: 0.0,
+            max_y: 140.0,
+        }));
+        assert_eq!(
+            (b.min_x, b.max_x, b.min_y, b.max_y),
+            (-72.0, 72.0, -68.0, 72.0)
+        );
+
+        let turned = Frame::new(&package(json!({ "x_axis": "up", "y_axis": "left" }))).unwrap();
+        assert_eq!(turned.point((1.0, 2.0)), (-2.0, 1.0));
+        let rect = Shape::Rect {
+            x: 10.0,
+            y: 0.0,
+            w: 4.0,
+            h: 2.0,
+            rotation: 0.0,
+        };
+        let Shape::Polygon { points } = turned.shape(&rect) else {
+            panic!("a turned rect should become a polygon");
+        };
+        let mut xs: Vec<f64> = points.iter().map(|p| p[0]).collect();
+        let mut ys: Vec<f64> = points.iter().map(|p| p[1]).collect();
+        xs.sort_by(f64::total_cmp);
+        ys.sort_by(f64::total_cmp);
+        assert_eq!((xs[0], xs[3], ys[0], ys[3]), (-1.0, 1.0, 8.0, 12.0));
+
+        let skewed = package(json!({ "x_axis": "right", "y_axis": "left" }));
+        assert!(Frame::new(&skewed).is_err());
+    }
+
+    #[test]
+    fn frame_scales_cm_and_tiles_to_inches() {
+        let cm = Frame::new(&package(json!({
+            "dimensions": { "width": 365.76, "height": 365.76, "units": "cm" },
+        })))
+        .unwrap();
+        assert!((cm.width - 144.0).abs() < 1e-9);
+        assert!(close(cm.point((254.0, -127.0)), (100.0, -50.0)));
+
+        let tiles = Frame::new(&package(json!({
+            "dimensions": { "width": 6, "height": 6, "units": "tiles" },
+            "origin": "bottom-left",
+        })))
+        .unwrap();
+        assert_eq!((tiles.width, tiles.height), (144.0, 144.0));
+        assert_eq!(tiles.point((1.0, 2.0)), (-48.0, -24.0));
+        let Shape::Circle { r, .. } = tiles.shape(&Shape::Circle {
+            x: 3.0,
+            y: 3.0,
+            r: 0.5,
+        }) else {
+            panic!("circles stay circles");
+        };
+        assert_eq!(r, 12.0);
+
+        let unknown = package(json!({
+            "dimensions": { "width": 1, "height": 1, "units": "furlongs" },
+        }));
+        assert!(Frame::new(&unknown).is_err());
+    }
+
+    #[test]
+    fn collinear_segments_only_cross_where_they_overlap() {
+        let a = ((0.0, 0.0), (2.0, 0.0));
+        assert!(!segments_cross(a, ((3.0, 0.0), (5.0, 0.0))));
+        assert!(segments_cross(a, ((1.0, 0.0), (5.0, 0.0))));
+        assert!(segments_cross(a, ((2.0, 0.0), (5.0, 0.0))));
+        assert!(!segments_cross(
+            ((0.0, 0.0), (0.0, 2.0)),
+            ((0.0, 3.0), (0.0, 5.0))
+        ));
+    }
+
+    #[test]
+    fn overlaps_counts_touching_edges_as_contact() {
+        let robot = box_corners(0.0, 0.0, 10.0, 10.0, 0.0);
+        let rect = |x: f64| Outline::Polygon(box_corners(x, 0.0, 10.0, 10.0, 0.0));
+        assert!(!overlaps(&robot, &rect(20.0)));
+        assert!(overlaps(&robot, &rect(10.0)));
+        assert!(overlaps(&robot, &rect(8.0)));
+        assert!(overlaps(
+            &robot,
+            &Outline::Polygon(box_corners(0.0, 0.0, 2.0, 2.0, 30.0))
+        ));
+        assert!(overlaps(
+            &robot,
+            &Outline::Polygon(box_corners(0.0, 0.0, 40.0, 40.0, 0.0))
+        ));
+
+        assert!(!overlaps(&robot, &Outline::Circle((9.0, 0.0), 3.0)));
+        assert!(overlaps(&robot, &Outline::Circle((8.0, 0.0), 3.0)));
+        assert!(overlaps(&robot, &Outline::Circle((0.0, 0.0), 1.0)));
+
+        let triangle = |pts: [Point; 3]| Outline::Polygon(pts.to_vec());
+        assert!(!overlaps(
+            &robot,
+            &triangle([(6.0, 6.0), (10.0, 6.0), (10.0, 10.0)])
+        ));
+        assert!(overlaps(
+            &robot,
+            &triangle([(5.0, 5.0), (10.0, 6.0), (10.0, 10.0)])
+        ));
+        assert!(overlaps(
+            &robot,
+            &triangle([(4.0, 4.0), (10.0, 6.0), (10.0, 10.0)])
+        ));
+    }
+
+    #[test]
+    fn robot_alongside_a_goal_edge_is_clear() {
+        // The robot's back edge sits on y = 51, the line of long-goal-top's
+        // top edge, but 30 inches away from it.
+        let violations = check(
+            &builtin(),
+            &RobotSize {
+                width: 12.0,
+                height: 12.0,
+            },
+            &CollisionOptions::default(),
+            &[placed(0, 0, 60.0, 57.0, 0.0)],
+        );
+        assert!(violations.is_empty(), "{violations:?}");
+    }
+
+    #[test]
+    fn sweep_fills_in_sparse_poses() {
+        let swept = sweep(&[placed(0, 0, 0.0, 0.0, 0.0), placed(1, 1000, 10.0, 0.0, 0.0)]);
+        assert_eq!(swept.len(), 11);
+        assert_eq!((swept[5].t, swept[5].x, swept[5].index), (500, 5.0, 0));
+        assert_eq!((swept[10].t, swept[10].index), (1000, 1));
+
+        let swept = sweep(&[
+            placed(0, 0, 0.0, 0.0, 350.0),
+            placed(1, 400, 0.0, 0.0, 10.0),
+        ]);
+        assert_eq!(swept.len(), 5);
+        assert_eq!((swept[1].t, swept[1].theta), (100, 355.0));
+
+        assert_eq!(sweep(&[placed(0, 0, 1.0, 2.0, 0.0)]).len(), 1);
+        assert!(sweep(&[]).is_empty());
+    }
+
+    #[test]
+    fn check_merges_consecutive_hits() {
+        // Driving straight up through long-goal-top (y 45..51) from y = 30.
+        let poses: Vec<Placed> = (0..4)
+            .map(|i| placed(i, 1000 * i as u64, 0.0, 30.0 + 12.0 * i as f64, 0.0))
+            .collect();
+        let violations = check(
+            &builtin(),
+            &RobotSize {
+                width: 12.0,
+                height: 12.0,
+            },
+            &CollisionOptions::default(),
+            &poses,
+        );
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        let v = &violations[0];
+        assert_eq!(v.kind, ViolationKind::Collision);
+        assert_eq!(v.element, "long-goal-top");
+        assert_eq!((v.start_ms, v.end_ms), (750, 2250));
+        assert_eq!((v.start_index, v.end_index), (0, 2));
+        assert_eq!((v.x, v.y), (0.0, 39.0));
+
+        let opts = CollisionOptions {
+            ignore: vec!["goal".into()],
+            ..Default::default()
+        };
+        let robot = RobotSize {
+            width: 12.0,
+            height: 12.0,
+        };
+        assert!(check(&builtin(), &robot, &opts, &poses).is_empty());
+    }
+
+    #[test]
+    fn stage_package_rejects_image_paths_outside_the_package() {
+        let root = std::env::temp_dir().join(format!("mv-field-{}", std::process::id()));
+        let (source, staging) = (root.join("source"), root.join("staging"));
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(root.join("x.png"), b"not yours").unwrap();
+        std::fs::write(source.join("field.png"), b"png").unwrap();
+
+        for file in ["../x.png", "/tmp/x.png", "sub/x.png", ""] {
+            let mut bad = package(json!({ "images": [{ "label": "Field", "file": file }] }));
+            let err = stage_package(&mut bad, &source, &staging).unwrap_err();
+            assert!(err.contains("must be a file next to"), "{file}: {err}");
+        }
+        assert!(!staging.join("x.png").exists());
+
+        let mut good = package(json!({ "images": [{ "label": "Field", "file": "field.png" }] }));
+        stage_package(&mut good, &source, &staging).unwrap();
+        assert_eq!(std::fs::read(staging.join("field.png")).unwrap(), b"png");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
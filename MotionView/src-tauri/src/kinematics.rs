@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::deviation::ErrorStats;
+use crate::run::{self, Run};
+use crate::settings;
+
+const WHEEL_SCALE: f64 = 127.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KinematicsOptions {
+    /// Defaults to the run's robot width, then the robot width from settings.
+    pub track_width: Option<f64>,
+    /// Wheel surface speed that MVLib logs as 127.
+    pub max_wheel_velocity: f64,
+    /// Largest disagreement, on the ±127 scale, before a wheel counts as
+    /// slipping.
+    pub tolerance: f64,
+    /// Below this (±127 scale) a wheel is treated as stopped for the sign
+    /// and scale checks.
+    pub min_speed: f64,
+    /// Slip shorter than this is ignored.
+    pub min_slip_ms: u64,
+    /// Reported speeds off from derived ones by more than this fraction are
+    /// flagged as the wrong scale.
+    pub scale_tolerance: f64,
+    /// Extra distance (in) and turn (deg) allowed per sample beyond what the
+    /// wheels could cover before flagging a jump.
+    pub jump_margin: f64,
+    pub turn_margin: f64,
+}
+
+impl Default for KinematicsOptions {
+    fn default() -> Self {
+        KinematicsOptions {
+            track_width: None,
+            max_wheel_velocity: 75.0,
+            tolerance: 20.0,
+            min_speed: 10.0,
+            min_slip_ms: 60,
+            scale_tolerance: 0.25,
+            jump_margin: 2.0,
+            turn_margin: 15.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Reported and derived wheel speeds disagree.
+    Slip,
+    /// A wheel moves one way while its reported speed says the other.
+    WrongSign,
+    /// Reported speeds are consistently scaled from the derived ones, e.g. a
+    /// wrong gearset or wheel size in `setRobot`.
+    WrongScale,
+    /// The position moved further than the wheels could in one sample.
+    Teleport,
+    /// The heading turned further than the wheels could in one sample.
+    HeadingJump,
+    /// Theta wrapped past 0/360 between samples. MotionView handles this, but
+    /// anything that interpolates theta directly will spin.
+    ThetaWrap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Wheel {
+    Left,
+    Right,
+}
+
+/// A stretch of consecutive samples with the same problem.
+#[derive(Debug, Clone, Serialize)]
+pub struct KinematicIssue {
+    pub kind: IssueKind,
+    pub wheel: Option<Wheel>,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub start_index: usize,
+    pub end_index: usize,
+    /// Largest disagreement (±127 scale), jump (in), turn (deg) or scale
+    /// ratio in the range.
+    pub worst: f64,
+}
+
+/// Wheel speeds on the ±127 scale between pose `index - 1` and `index`.
+/// Reported speeds are the mean of the two poses.
+#[derive(Debug, Clone, Serialize)]
+pub struct WheelSample {
+    pub t: u64,
+    pub index: usize,
+    pub derived_l: f64,
+    pub derived_r: f64,
+    pub reported_l: Option<f64>,
+    pub reported_r: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KinematicsReport {
+    pub track_width: f64,
+    /// Least-squares ratio of reported to derived wheel speed.
+    pub scale: Option<f64>,
+    /// Reported minus derived, over samples with both.
+    pub error_l: ErrorStats,
+    pub error_r: ErrorStats,
+    pub samples: Vec<WheelSample>,
+    pub issues: Vec<KinematicIssue>,
+}
+
+fn check_options(opts: &KinematicsOptions, track_width: f64) -> Result<(), String> {
+    let positive = [
+        ("track_width", track_width),
+        ("max_wheel_velocity", opts.max_wheel_velocity),
+        ("tolerance", opts.tolerance),
+    ];
+    for (name, v) in positive {
+        if !(v.is_finite() && v > 0.0) {
+            return Err(format!("{name} must be positive"));
+        }
+    }
+    let non_negative = [
+        ("min_speed", opts.min_speed),
+        ("scale_tolerance", opts.scale_tolerance),
+        ("jump_margin", opts.jump_margin),
+        ("turn_margin", opts.turn_margin),
+    ];
+    for (name, v) in non_negative {
+        if !(v.is_finite() && v >= 0.0) {
+            return Err(format!("{name} must not be negative"));
+        }
+    }
+    Ok(())
+}
+
+/// Adds a flagged sample, extending the open issue of the same kind and
+/// wheel when it ended on the previous pose.
+fn flag(
+    issues: &mut Vec<KinematicIssue>,
+    kind: IssueKind,
+    wheel: Option<Wheel>,
+    t: u64,
+    index: usize,
+    amount: f64,
+) {
+    let open = issues
+        .iter_mut()
+        .rev()
+        .find(|i| i.kind == kind && i.wheel == wheel && i.end_index + 1 == index);
+    match open {
+        Some(issue) => {
+            issue.end_ms = t;
+            issue.end_index = index;
+            if amount.abs() > issue.worst.abs() {
+                issue.worst = amount;
+            }
+        }
+        None => issues.push(KinematicIssue {
+            kind,
+            wheel,
+            start_ms: t,
+            end_ms: t,
+            start_index: index,
+            end_index: index,
+            worst: amount,
+        }),
+    }
+}
+
+/// Derives wheel speeds from successive poses and checks them, and the
+/// poses themselves, against what the robot could physically do.
+/// `track_width` is in inches; positions are converted using `meta.units`.
+pub fn check(
+    run: &Run,
+    opts: &KinematicsOptions,
+    track_width: f64,
+) -> Result<KinematicsReport, String> {
+    check_options(opts, track_width)?;
+    let scale = run.inches_scale()?;
+    let to_wheel = WHEEL_SCALE / opts.max_wheel_velocity;
+    // Fastest possible turn, in degrees per second.
+    let max_turn = (2.0 * opts.max_wheel_velocity / track_width).to_degrees();
+
+    let mut samples = Vec::new();
+    let mut issues = Vec::new();
+    for (index, pair) in run.poses.windows(2).enumerate() {
+        let (a, b) = (&pair[0], &pair[1]);
+        let index = index + 1;
+        let Some(step) = run::step(a, b, scale) else {
+            continue;
+        };
+        let (dt, turn) = (step.dt, step.turn);
+
+        let raw = b.theta - a.theta;
+        if raw.abs() > 180.0 && (raw - turn).abs() > 1e-6 {
+            flag(&mut issues, IssueKind::ThetaWrap, None, b.t, index, raw);
+        }
+        let jump = step.dx.hypot(step.dy);
+        if jump > opts.max_wheel_velocity * dt + opts.jump_margin {
+            flag(&mut issues, IssueKind::Teleport, None, b.t, index, jump);
+            continue;
+        }
+        if turn.abs() > max_turn * dt + opts.turn_margin {
+            flag(&mut issues, IssueKind::HeadingJump, None, b.t, index, turn);
+            continue;
+        }
+
+        // Compass turns are clockwise, so a positive turn speeds up the
+        // left wheel.
+        let v = step.speed;
+        let w = turn.to_radians() / dt * track_width / 2.0;
+        let mean = |p: Option<f64>, q: Option<f64>| Some((p? + q?) / 2.0);
+        samples.push(WheelSample {
+            t: b.t,
+            index,
+            derived_l: (v + w) * to_wheel,
+            derived_r: (v - w) * to_wheel,
+            reported_l: mean(a.l_vel, b.l_vel),
+            reported_r: mean(a.r_vel, b.r_vel),
+        });
+    }
+
+    let wheels = |s: &WheelSample| {
+        [
+            (Wheel::Left, s.derived_l, s.reported_l),
+            (Wheel::Right, s.derived_r, s.reported_r),
+        ]
+    };
+    let moving: Vec<(f64, f64)> = samples
+        .iter()
+        .flat_map(wheels)
+        .filter_map(|(_, d, r)| Some((d, r?)))
+        .filter(|(d, r)| d.abs() >= opts.min_speed && r.abs() >= opts.min_speed && d * r > 0.0)
+        .collect();
+    let dd: f64 = moving.iter().map(|(d, _)| d * d).sum();
+    let ratio = (dd > 0.0).then(|| moving.iter().map(|(d, r)| d * r).sum::<f64>() / dd);
+    // With the wrong scale every sample would look like slip, so compare
+    // against the scaled speeds instead and report the scale once.
+    let expect = match ratio {
+        Some(k) if (k - 1.0).abs() > opts.scale_tolerance => {
+            if let (Some(first), Some(last)) = (samples.first(), samples.last()) {
+                issues.push(KinematicIssue {
+                    kind: IssueKind::WrongScale,
+                    wheel: None,
+                    start_ms: first.t,
+                    end_ms: last.t,
+                    start_index: first.index,
+                    end_index: last.index,
+                    worst: k,
+                });
+            }
+            k
+        }
+        _ => 1.0,
+    };
+
+    let mut slips = Vec::new();
+    for s in &samples {
+        for (wheel, derived, reported) in wheels(s) {
+            let Some(reported) = reported else {
+                continue;
+            };
+            let derived = derived * expect;
+            if derived.abs() >= opts.min_speed
+                && reported.abs() >= opts.min_speed
+                && derived * reported < 0.0
+            {
+                flag(
+                    &mut issues,
+                    IssueKind::WrongSign,
+                    Some(wheel),
+                    s.t,
+                    s.index,
+                    reported,
+                );
+            } else if (reported - derived).abs() > opts.tolerance {
+                flag(
+                    &mut slips,
+                    IssueKind::Slip,
+                    Some(wheel),
+                    s.t,
+                    s.index,
+                    reported - derived,
+                );
+            }
+        }
+    }
+    // The first sample's time is the end of its interval, so a range covers
+    // one sample less than it looks.
+    let interval_start = |i: &KinematicIssue| run.poses[i.start_index - 1].t;
+    issues.extend(
+        slips
+            .into_iter()
+            .filter(|i| i.end_ms.saturating_sub(interval_start(i)) >= opts.min_slip_ms),
+    );
+    issues.sort_by_key(|i| (i.start_index, i.end_index));
+
+    let errors = |f: fn(&WheelSample) -> (f64, Option<f64>)| {
+        ErrorStats::of(
+            samples
+                .iter()
+                .filter_map(|s| {
+                    let (d, r) = f(s);
+                    Some(r? - d)
+                })
+                .collect::<Vec<_>>(),
+        )
+    };
+    Ok(KinematicsReport {
+        track_width,
+        scale: ratio,
+        error_l: errors(|s| (s.derived_l, s.reported_l)),
+        error_r: errors(|s| (s.derived_r, s.reported_r)),
+        samples,
+        issues,
+    })
+}
+
+#[tauri::command]
+pub fn check_run_kinematics(
+    app: AppHandle,
+    run: Run,
+    options: KinematicsOptions,
+) -> Result<KinematicsReport, String> {
+    let track_width = match (options.track_width, run.meta.robot.as_ref()) {
+        (Some(w), _) => w,
+        (None, Some(r)) => r.width * run.inches_scale()?,
+        (None, None) => settings::load_settings(&app)?.unwrap_or_default().robot_w,
+    };
+    check(&run, &options, track_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::RunMeta;
+    use crate::telemetry::Pose;
+
+    fn run(poses: Vec<Pose>) -> Run {
+        Run::new(RunMeta::default(), poses, Vec::new())
+    }
+
+    #[test]
+    fn spin_in_place_splits_evenly_between_wheels() {
+        // 9 degrees clockwise every 20 ms on a 12 in track: 47.1 in/s per wheel.
+        let wheel = (9f64.to_radians() / 0.02 * 6.0) * WHEEL_SCALE / 75.0;
+        let poses = (0..=40)
+            .map(|k| {
+                Pose::new(
+                    20 * k,
+                    0.0,
+                    0.0,
+                    (9 * k) as f64 % 360.0,
+                    Some(wheel),
+                    Some(-wheel),
+                )
+            })
+            .collect();
+        let report = check(&run(poses), &KinematicsOptions::default(), 12.0).unwrap();
+        assert_eq!(report.samples.len(), 40);
+        for s in &report.samples {
+            assert_eq!(s.derived_l, -s.derived_r);
+            assert!((s.derived_l - wheel).abs() < 1e-9, "{s:?}");
+        }
+        // The log wraps from 351 to 0 on the last step; nothing else is off.
+        let kinds: Vec<IssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, [IssueKind::ThetaWrap]);
+        assert!((report.scale.unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scaled_wheel_speeds_are_wrong_scale_not_slip() {
+        // 30 in/s straight ahead is 50.8 on the wheel scale; the log says 1.5x.
+        let reported = 1.5 * 30.0 * WHEEL_SCALE / 75.0;
+        let poses = (0..50)
+            .map(|k| {
+                Pose::new(
+                    20 * k,
+                    0.0,
+                    0.6 * k as f64,
+                    0.0,
+                    Some(reported),
+                    Some(reported),
+                )
+            })
+            .collect();
+        let report = check(&run(poses), &KinematicsOptions::default(), 12.0).unwrap();
+        assert!((report.scale.unwrap() - 1.5).abs() < 1e-9);
+        let kinds: Vec<IssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, [IssueKind::WrongScale]);
+        let issue = &report.issues[0];
+        assert_eq!((issue.start_index, issue.end_index), (1, 49));
+        assert!((issue.worst - 1.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::settings::{self, parses_as, read_with_backup, write_atomic};
+
+const LIBRARY_DIR: &str = "Library";
+const INDEX_FILE: &str = "index.json";
+
+/// Serializes index updates; the entry documents themselves are only ever
+/// written once per id.
+#[derive(Default)]
+pub struct Library {
+    lock: Mutex<()>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntrySource {
+    Live,
+    File,
+    Plan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub id: String,
+    pub name: String,
+    pub created_ms: u64,
+    pub modified_ms: u64,
+    pub source: EntrySource,
+    pub robot: Option<String>,
+    pub field: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub pose_count: usize,
+    pub waypoint_count: usize,
+    pub watch_count: usize,
+    /// First to last pose timestamp, when the poses have them.
+    pub duration_ms: Option<u64>,
+}
+
+/// What the webview knows about a new entry; the rest is derived.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewEntry {
+    pub name: String,
+    pub source: EntrySource,
+    pub robot: Option<String>,
+    pub field: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LibraryQuery {
+    /// Case-insensitive match against name, tags, robot and field.
+    pub text: Option<String>,
+    /// Entries must carry all of these.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub source: Option<EntrySource>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn library_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e: tauri::Error| e.to_string())?
+        .join(LIBRARY_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub fn entry_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    Ok(entry_file(&library_dir(app)?, id))
+}
+
+fn entry_file(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.json"))
+}
+
+fn read_index(dir: &Path) -> Result<Vec<LibraryEntry>, String> {
+    let parse = |s: &str| serde_json::from_str::<Vec<LibraryEntry>>(s).map_err(|e| e.to_string());
+    Ok(read_with_backup(&dir.join(INDEX_FILE), parse)?.unwrap_or_default())
+}
+
+fn write_index(dir: &Path, entries: &[LibraryEntry]) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    write_atomic(
+        &dir.join(INDEX_FILE),
+        contents,
+        parses_as::<Vec<LibraryEntry>>,
+    )
+}
+
+fn find_entry<'a>(
+    entries: &'a mut [LibraryEntry],
+    id: &str,
+) -> Result<&'a mut LibraryEntry, String> {
+    entries
+        .iter_mut()
+        .find(|e| e.id == id)
+        .ok_or_else(|| format!("no library entry with id {id:?}"))
+}
+
+fn checked_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("name is empty".into());
+    }
+    Ok(name.to_string())
+}
+
+/// Trims, drops empties and removes case-insensitive duplicates.
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !out.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            out.push(tag.to_string());
+        }
+    }
+    out
+}
+
+/// Pose/waypoint/watch counts for either a saved-paths document or a run file.
+fn summarize(doc: &Value) -> (usize, usize, usize, Option<u64>) {
+    let array = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| doc.get(*k).and_then(Value::as_array))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    };
+    let poses = array(&["poses", "robot-path"]);
+    let times: Vec<u64> = poses
+        .iter()
+        .filter_map(|p| p.get("t").and_then(Value::as_u64))
+        .collect();
+    let duration = match (times.iter().min(), times.iter().max()) {
+        (Some(lo), Some(hi)) => Some(hi - lo),
+        _ => None,
+    };
+    (
+        poses.len(),
+        array(&["planned-path"]).len(),
+        array(&["watches", "watch"]).len(),
+        duration,
+    )
+}
+
+fn new_id(entries: &[LibraryEntry], created_ms: u64) -> String {
+    let mut id = created_ms.to_string();
+    let mut n = 2;
+    while entries.iter().any(|e| e.id == id) {
+        id = format!("{created_ms}-{n}");
+        n += 1;
+    }
+    id
+}
+
+fn matches(entry: &LibraryEntry, query: &LibraryQuery) -> bool {
+    if query.source.is_some_and(|s| s != entry.source) {
+        return false;
+    }
+    let has_tag = |want: &String| entry.tags.iter().any(|t| t.eq_ignore_ascii_case(want));
+    if !query.tags.iter().all(has_tag) {
+        return false;
+    }
+    let text = match query.text.as_deref().map(str::trim) {
+        Some(t) if !t.is_empty() => t.to_lowercase(),
+        _ => return true,
+    };
+    std::iter::once(&entry.name)
+        .chain(&entry.tags)
+        .chain(&entry.robot)
+        .chain(&entry.field)
+        .any(|s| s.to_lowercase().contains(&text))
+}
+
+/// Newest first.
+fn search(dir: &Path, query: &LibraryQuery) -> Result<Vec<LibraryEntry>, String> {
+    let mut entries: Vec<LibraryEntry> = read_index(dir)?
+        .into_iter()
+        .filter(|e| matches(e, query))
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.created_ms));
+    Ok(entries)
+}
+
+/// Writes the document and adds it to the index. `robot` and `field` are
+/// taken as given.
+fn store(dir: &Path, entry: NewEntry, contents: String) -> Result<LibraryEntry, String> {
+    let NewEntry {
+        name,
+        source,
+        robot,
+        field,
+        tags,
+    } = entry;
+    let name = checked_name(&name)?;
+    let doc: Value = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    if !doc.is_object() {
+        return Err("library entry must be a JSON object".into());
+    }
+    let (pose_count, waypoint_count, watch_count, duration_ms) = summarize(&doc);
+
+    let mut entries = read_index(dir)?;
+    let created_ms = now_ms();
+    let entry = LibraryEntry {
+        id: new_id(&entries, created_ms),
+        name,
+        created_ms,
+        modified_ms: created_ms,
+        source,
+        robot: robot.filter(|r| !r.trim().is_empty()),
+        field: field.filter(|f| !f.trim().is_empty()),
+        tags: clean_tags(tags),
+        pose_count,
+        waypoint_count,
+        watch_count,
+        duration_ms,
+    };
+    write_atomic(&entry_file(dir, &entry.id), contents, parses_as::<Value>)?;
+    entries.push(entry.clone());
+    write_index(dir, &entries)?;
+    Ok(entry)
+}
+
+fn update(
+    dir: &Path,
+    id: &str,
+    change: impl FnOnce(&mut LibraryEntry),
+) -> Result<LibraryEntry, String> {
+    let mut entries = read_index(dir)?;
+    let entry = find_entry(&mut entries, id)?;
+    change(entry);
+    entry.modified_ms = now_ms();
+    let entry = entry.clone();
+    write_index(dir, &entries)?;
+    Ok(entry)
+}
+
+fn remove(dir: &Path, id: &str) -> Result<(), String> {
+    let mut entries = read_index(dir)?;
+    find_entry(&mut entries, id)?;
+    entries.retain(|e| e.id != id);
+    write_index(dir, &entries)?;
+    let path = entry_file(dir, id);
+    for p in [settings::backup_path(&path), path] {
+        if p.exists() {
+            std::fs::remove_file(p).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_library(app: AppHandle) -> Result<Vec<LibraryEntry>, String> {
+    search_library(app, LibraryQuery::default())
+}
+
+/// Newest first.
+#[tauri::command]
+pub fn search_library(app: AppHandle, query: LibraryQuery) -> Result<Vec<LibraryEntry>, String> {
+    search(&library_dir(&app)?, &query)
+}
+
+/// Stores `contents` (a saved-paths or run document) as a new library entry.
+/// `robot` and `field` default to the active profile's name and field.
+#[tauri::command]
+pub fn save_library_entry(
+    app: AppHandle,
+    library: State<'_, Library>,
+    entry: NewEntry,
+    contents: String,
+) -> Result<LibraryEntry, String> {
+    let robot = match entry.robot {
+        Some(r) => Some(r),
+        None => {
+            let index = settings::read_profile_index(&app)?;
+            index
+                .profiles
+                .into_iter()
+                .find(|p| p.id == index.active)
+                .map(|p| p.name)
+        }
+    };
+    let field = match entry.field {
+        Some(f) => Some(f),
+        None => settings::load_settings(&app)?.map(|s| s.selected_field),
+    };
+    let entry = NewEntry {
+        robot,
+        field,
+        ..entry
+    };
+    let _guard = library.lock.lock().unwrap();
+    store(&library_dir(&app)?, entry, contents)
+}
+
+/// Returns the stored document, in the same shape it was saved in.
+#[tauri::command]
+pub fn open_library_entry(app: AppHandle, id: String) -> Result<String, String> {
+    let dir = library_dir(&app)?;
+    find_entry(&mut read_index(&dir)?, &id)?;
+    std::fs::read_to_string(entry_file(&dir, &id)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn rename_library_entry(
+    app: AppHandle,
+    library: State<'_, Library>,
+    id: String,
+    name: String,
+) -> Result<LibraryEntry, String> {
+    let name = checked_name(&name)?;
+    let _guard = library.lock.lock().unwrap();
+    update(&library_dir(&app)?, &id, |e| e.name = name)
+}
+
+/// Replaces the entry's tags.
+#[tauri::command]
+pub fn tag_library_entry(
+    app: AppHandle,
+    library: State<'_, Library>,
+    id: String,
+    tags: Vec<String>,
+) -> Result<LibraryEntry, String> {
+    let _guard = library.lock.lock().unwrap();
+    update(&library_dir(&app)?, &id, |e| e.tags = clean_tags(tags))
+}
+
+#[tauri::command]
+pub fn delete_library_entry(
+    app: AppHandle,
+    library: State<'_, Library>,
+    id: String,
+) -> Result<(), String> {
+    let _guard = library.lock.lock().unwrap();
+    remove(&library_dir(&app)?, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_library(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mv-library-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn new_entry(name: &str, source: EntrySource, tags: &[&str]) -> NewEntry {
+        NewEntry {
+            name: name.into(),
+            source,
+            robot: Some("Main bot".into()),
+            field: Some("pushback-2025-2026".into()),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    const RUN: &str =
+        r#"{"poses":[{"t":100,"x":0,"y":0},{"t":1600,"x":1,"y":2}],"watches":[{"time":50}]}"#;
+    const SAVED: &str = r#"{"robot-path":[],"planned-path":[{"x":0,"y":0},{"x":24,"y":0}]}"#;
+
+    #[test]
+    fn index_round_trips() {
+        let dir = temp_library("round-trip");
+        let run = store(
+            &dir,
+            new_entry(" Skills run ", EntrySource::Live, &[]),
+            RUN.into(),
+        )
+        .unwrap();
+        let plan = store(
+            &dir,
+            new_entry("Plan", EntrySource::Plan, &[]),
+            SAVED.into(),
+        )
+        .unwrap();
+        assert_ne!(run.id, plan.id);
+        assert_eq!(run.name, "Skills run");
+        assert_eq!(
+            (
+                run.pose_count,
+                run.waypoint_count,
+                run.watch_count,
+                run.duration_ms
+            ),
+            (2, 0, 1, Some(1500))
+        );
+        assert_eq!((plan.pose_count, plan.waypoint_count), (0, 2));
+
+        let index = read_index(&dir).unwrap();
+        let ids: Vec<&str> = index.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, [run.id.as_str(), plan.id.as_str()]);
+        assert_eq!(index[0].robot.as_deref(), Some("Main bot"));
+        assert_eq!(
+            std::fs::read_to_string(entry_file(&dir, &run.id)).unwrap(),
+            RUN
+        );
+
+        assert!(store(&dir, new_entry(" ", EntrySource::File, &[]), RUN.into()).is_err());
+        assert!(store(&dir, new_entry("List", EntrySource::File, &[]), "[]".into()).is_err());
+        assert_eq!(read_index(&dir).unwrap().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rename_tag_and_delete() {
+        let dir = temp_library("edit");
+        let entry = store(
+            &dir,
+            new_entry("Old", EntrySource::File, &["a"]),
+            RUN.into(),
+        )
+        .unwrap();
+
+        let renamed = update(&dir, &entry.id, |e| e.name = "New".into()).unwrap();
+        assert_eq!(renamed.name, "New");
+        assert!(renamed.modified_ms >= entry.modified_ms);
+        let tags = vec![" Skills ".into(), "skills".into(), "".into(), "left".into()];
+        let tagged = update(&dir, &entry.id, |e| e.tags = clean_tags(tags)).unwrap();
+        assert_eq!(tagged.tags, ["Skills", "left"]);
+        let stored = &read_index(&dir).unwrap()[0];
+        assert_eq!((stored.name.as_str(), stored.tags.len()), ("New", 2));
+
+        assert!(update(&dir, "missing", |_| {}).is_err());
+        remove(&dir, &entry.id).unwrap();
+        assert!(read_index(&dir).unwrap().is_empty());
+        assert!(!entry_file(&dir, &entry.id).exists());
+        assert!(remove(&dir, &entry.id).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn search_filters_and_orders_newest_first() {
+        let dir = temp_library("search");
+        let entry = |id: &str, created_ms: u64, source: EntrySource, tags: &[&str]| LibraryEntry {
+            id: id.into(),
+            name: format!("Run {id}"),
+            created_ms,
+            modified_ms: created_ms,
+            source,
+            robot: Some(if id == "b" { "Clamp bot" } else { "Main bot" }.into()),
+            field: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            pose_count: 0,
+            waypoint_count: 0,
+            watch_count: 0,
+            duration_ms: None,
+        };
+        let entries = [
+            entry("a", 100, EntrySource::Live, &["skills"]),
+            entry("b", 300, EntrySource::File, &["Skills", "left"]),
+            entry("c", 200, EntrySource::Live, &[]),
+        ];
+        write_index(&dir, &entries).unwrap();
+
+        let ids = |query: LibraryQuery| -> Vec<String> {
+            search(&dir, &query)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.id)
+                .collect()
+        };
+        assert_eq!(ids(LibraryQuery::default()), ["b", "c", "a"]);
+        let tagged = LibraryQuery {
+            tags: vec!["SKILLS".into()],
+            ..Default::default()
+        };
+        assert_eq!(ids(tagged), ["b", "a"]);
+        let live = LibraryQuery {
+            source: Some(EntrySource::Live),
+            ..Default::default()
+        };
+        assert_eq!(ids(live), ["c", "a"]);
+        let text = LibraryQuery {
+            text: Some(" clamp ".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(text), ["b"]);
+        let both = LibraryQuery {
+            text: Some("left".into()),
+            tags: vec!["skills".into()],
+            source: Some(EntrySource::Live),
+        };
+        assert!(ids(both).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_index_falls_back_to_backup() {
+        let dir = temp_library("corrupt");
+        let first = store(&dir, new_entry("First", EntrySource::File, &[]), RUN.into()).unwrap();
+        store(
+            &dir,
+            new_entry("Second", EntrySource::File, &[]),
+            RUN.into(),
+        )
+        .unwrap();
+        std::fs::write(dir.join(INDEX_FILE), "[{\"id\": \"tor").unwrap();
+
+        let index = read_index(&dir).unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(index[0].id, first.id);
+
+        // Writing again builds on the backup and leaves it alone.
+        let third = store(&dir, new_entry("Third", EntrySource::File, &[]), RUN.into()).unwrap();
+        let names: Vec<String> = read_index(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["First", "Third"]);
+        let backup = std::fs::read(settings::backup_path(&dir.join(INDEX_FILE))).unwrap();
+        assert!(!String::from_utf8(backup).unwrap().contains(&third.id));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
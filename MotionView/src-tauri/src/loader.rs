@@ -0,0 +1,341 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{ipc::Channel, AppHandle, Manager, State};
+
+use crate::compact;
+use crate::events::RunEvent;
+use crate::run::{self, Run, RunMeta, Thinning};
+use crate::telemetry::{Pose, Watch};
+
+const DEFAULT_PAGE_SIZE: usize = 2000;
+const CANCELLED: &str = "load cancelled";
+
+/// Cancellation flags for loads that are still streaming, keyed by load id.
+#[derive(Default)]
+pub struct RunLoads {
+    next_id: AtomicU32,
+    active: Mutex<HashMap<u32, Arc<AtomicBool>>>,
+}
+
+impl RunLoads {
+    fn register(&self) -> (u32, Arc<AtomicBool>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let flag = Arc::new(AtomicBool::new(false));
+        self.active.lock().unwrap().insert(id, flag.clone());
+        (id, flag)
+    }
+
+    fn finish(&self, id: u32) {
+        self.active.lock().unwrap().remove(&id);
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum LoadEvent {
+    Started {
+        load_id: u32,
+        total_bytes: u64,
+    },
+    Poses {
+        offset: usize,
+        poses: Vec<Pose>,
+    },
+    Progress {
+        bytes_read: u64,
+        total_bytes: u64,
+        poses: usize,
+    },
+    /// Sent once the whole file has been read, so it can carry migrated and
+    /// validated values no matter where they appear in the file.
+    Meta {
+        version: u64,
+        meta: RunMeta,
+        thinning: Option<Thinning>,
+        watches: Vec<Watch>,
+        events: Vec<RunEvent>,
+    },
+    Finished {
+        poses: usize,
+        watches: usize,
+    },
+    Cancelled,
+    Error {
+        message: String,
+    },
+}
+
+struct CountingReader<R> {
+    inner: R,
+    read: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+}
+
+struct StreamCtx<'a> {
+    channel: &'a Channel<LoadEvent>,
+    cancel: &'a AtomicBool,
+    bytes_read: Rc<Cell<u64>>,
+    total_bytes: u64,
+    page_size: usize,
+    page: Vec<Pose>,
+    sent: usize,
+}
+
+impl StreamCtx<'_> {
+    fn flush(&mut self) {
+        if self.page.is_empty() {
+            return;
+        }
+        let poses = std::mem::take(&mut self.page);
+        let count = poses.len();
+        let offset = self.sent;
+        self.sent += count;
+        let sent = self
+            .channel
+            .send(LoadEvent::Poses { offset, poses })
+            .is_ok()
+            && self
+                .channel
+                .send(LoadEvent::Progress {
+                    bytes_read: self.bytes_read.get(),
+                    total_bytes: self.total_bytes,
+                    poses: self.sent,
+                })
+                .is_ok();
+        // Nobody is listening anymore (window reloaded); stop reading.
+        if !sent {
+            self.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+struct PosesSeed<'a, 'b>(&'a mut StreamCtx<'b>);
+
+impl<'de> DeserializeSeed<'de> for PosesSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PosesSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of poses")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let ctx = self.0;
+        let mut i = ctx.sent + ctx.page.len();
+        while let Some(v) = seq.next_element::<Value>()? {
+            if ctx.cancel.load(Ordering::Relaxed) {
+                return Err(de::Error::custom(CANCELLED));
+            }
+            let pose = run::pose_from_value(&format!("poses[{i}]"), v)
+                .map_err(|e| de::Error::custom(e.to_string()))?;
+            ctx.page.push(pose);
+            i += 1;
+            if ctx.page.len() >= ctx.page_size {
+                ctx.flush();
+            }
+        }
+        ctx.flush();
+        Ok(())
+    }
+}
+
+/// Walks the top-level object, streaming `poses` and keeping everything else
+/// (small) as plain JSON for migration afterwards.
+struct RootVisitor<'a, 'b>(&'a mut StreamCtx<'b>);
+
+impl<'de> Visitor<'de> for RootVisitor<'_, '_> {
+    type Value = Map<String, Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a run object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut rest = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            // `robot-path` is the same array under its saved-paths.json name.
+            if key == "poses" || key == "robot-path" {
+                map.next_value_seed(PosesSeed(&mut *self.0))?;
+            } else {
+                rest.insert(key, map.next_value()?);
+            }
+        }
+        Ok(rest)
+    }
+}
+
+/// Streams `poses` straight off the reader and returns the rest of the run
+/// (meta, watches) with an empty pose list.
+fn stream_json(path: &Path, ctx: &mut StreamCtx) -> Result<Run, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let reader = CountingReader {
+        inner: BufReader::new(file),
+        read: ctx.bytes_read.clone(),
+    };
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let mut rest = de::Deserializer::deserialize_map(&mut de, RootVisitor(&mut *ctx))
+        .map_err(|e| e.to_string())?;
+    de.end().map_err(|e| e.to_string())?;
+
+    // Run the non-pose parts through the normal migrate/validate path.
+    rest.remove("planned-path");
+    rest.insert("poses".to_string(), Value::Array(Vec::new()));
+    run::from_value(Value::Object(rest)).map_err(|e| e.to_string())
+}
+
+/// Compact files are small enough to decode in one go; only the hand-off to
+/// the webview is paged.
+fn stream_compact(path: &Path, ctx: &mut StreamCtx) -> Result<Run, String> {
+    let mut run = run::read_run_file(path)?;
+    ctx.bytes_read.set(ctx.total_bytes);
+    for pose in std::mem::take(&mut run.poses) {
+        if ctx.cancel.load(Ordering::Relaxed) {
+            return Err(CANCELLED.into());
+        }
+        ctx.page.push(pose);
+        if ctx.page.len() >= ctx.page_size {
+            ctx.flush();
+        }
+    }
+    ctx.flush();
+    Ok(run)
+}
+
+fn is_compact_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| compact::is_compact(&magic))
+        .unwrap_or(false)
+}
+
+fn stream_file(path: &Path, ctx: &mut StreamCtx) -> Result<(), String> {
+    let skeleton = if is_compact_file(path) {
+        stream_compact(path, ctx)?
+    } else {
+        stream_json(path, ctx)?
+    };
+    let watches = skeleton.watches.len();
+    let _ = ctx.channel.send(LoadEvent::Meta {
+        version: skeleton.version,
+        meta: skeleton.meta,
+        thinning: skeleton.thinning,
+        watches: skeleton.watches,
+        events: skeleton.events,
+    });
+    let _ = ctx.channel.send(LoadEvent::Finished {
+        poses: ctx.sent,
+        watches,
+    });
+    Ok(())
+}
+
+fn start_load(
+    app: AppHandle,
+    path: PathBuf,
+    page_size: Option<usize>,
+    channel: Channel<LoadEvent>,
+) -> Result<u32, String> {
+    let total_bytes = std::fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    let (load_id, cancel) = app.state::<RunLoads>().register();
+    let _ = channel.send(LoadEvent::Started {
+        load_id,
+        total_bytes,
+    });
+    let page_size = page_size.filter(|n| *n > 0).unwrap_or(DEFAULT_PAGE_SIZE);
+
+    std::thread::spawn(move || {
+        let mut ctx = StreamCtx {
+            channel: &channel,
+            cancel: &cancel,
+            bytes_read: Rc::new(Cell::new(0)),
+            total_bytes,
+            page_size,
+            page: Vec::new(),
+            sent: 0,
+        };
+        let result = stream_file(&path, &mut ctx);
+        match result {
+            Ok(()) => {}
+            Err(_) if cancel.load(Ordering::Relaxed) => {
+                let _ = channel.send(LoadEvent::Cancelled);
+            }
+            Err(message) => {
+                let _ = channel.send(LoadEvent::Error { message });
+            }
+        }
+        app.state::<RunLoads>().finish(load_id);
+    });
+    Ok(load_id)
+}
+
+/// Streams a run file to the webview in pages of poses. Returns the load id
+/// to pass to `cancel_run_load`.
+#[tauri::command]
+pub fn stream_run(
+    app: AppHandle,
+    path: String,
+    page_size: Option<usize>,
+    on_event: Channel<LoadEvent>,
+) -> Result<u32, String> {
+    start_load(app, PathBuf::from(path), page_size, on_event)
+}
+
+/// Same as `stream_run`, for the robot path persisted in `saved-paths.json`.
+#[tauri::command]
+pub fn stream_saved_paths(
+    app: AppHandle,
+    page_size: Option<usize>,
+    on_event: Channel<LoadEvent>,
+) -> Result<Option<u32>, String> {
+    let path = crate::settings::saved_paths_path(&app)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    start_load(app, path, page_size, on_event).map(Some)
+}
+
+#[tauri::command]
+pub fn cancel_run_load(loads: State<'_, RunLoads>, load_id: u32) -> bool {
+    match loads.active.lock().unwrap().get(&load_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
@@ -12,7 +12,29 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use tauri::{Manager, RunEvent, State, Window};
 use tauri_plugin_posthog::{init as posthog_init, PostHogConfig};
+mod batch;
+mod codegen;
+mod compact;
+mod compare;
+mod cpp_import;
+mod csv;
+mod curve;
+mod deviation;
+mod drift;
+mod events;
+mod field;
+mod kinematics;
+mod library;
+mod loader;
+mod plan;
+mod recorder;
+mod run;
+mod sdlog;
 mod settings;
+mod telemetry;
+mod thinning;
+mod trajectory;
+mod ws;
 
 struct BridgeState(Mutex<Option<Child>>);
 struct BridgeOrigin(Mutex<Option<String>>);
@@ -378,6 +400,10 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .manage(BridgeState(Mutex::new(None)))
         .manage(BridgeOrigin(Mutex::new(None)))
+        .manage(loader::RunLoads::default())
+        .manage(recorder::Recorder::default())
+        .manage(library::Library::default())
+        .manage(plan::PlanHistory::default())
         .invoke_handler(tauri::generate_handler![
             settings::read_settings,
             settings::write_settings,
@@ -385,6 +411,65 @@ fn main() {
             settings::save_robot_image,
             settings::read_saved_paths,
             settings::write_saved_paths,
+            settings::list_profiles,
+            settings::get_active_profile,
+            settings::create_profile,
+            settings::rename_profile,
+            settings::duplicate_profile,
+            settings::delete_profile,
+            settings::switch_profile,
+            telemetry::parse_telemetry_line,
+            telemetry::parse_telemetry,
+            run::load_run,
+            run::parse_run,
+            run::save_run,
+            sdlog::import_sd_log,
+            sdlog::convert_sd_logs,
+            thinning::thin_run,
+            thinning::thin_run_file,
+            csv::export_run_csv,
+            csv::export_saved_paths_csv,
+            csv::import_run_csv,
+            loader::stream_run,
+            loader::stream_saved_paths,
+            loader::cancel_run_load,
+            compact::convert_run_to_compact,
+            compact::convert_compact_to_json,
+            recorder::list_unfinished_sessions,
+            recorder::recover_session,
+            recorder::discard_session,
+            library::list_library,
+            library::search_library,
+            library::save_library_entry,
+            library::open_library_entry,
+            library::rename_library_entry,
+            library::tag_library_entry,
+            library::delete_library_entry,
+            plan::list_plan_revisions,
+            plan::get_plan_revision,
+            plan::commit_plan_revision,
+            plan::diff_plan_revisions,
+            plan::restore_plan_revision,
+            codegen::generate_plan_code,
+            codegen::write_plan_to_pros_project,
+            cpp_import::import_pros_autons,
+            trajectory::profile_plan,
+            curve::smooth_plan,
+            field::list_fields,
+            field::get_field,
+            field::check_plan_collisions,
+            field::check_run_collisions,
+            field::install_field_package,
+            field::remove_field_package,
+            deviation::analyze_run_deviation,
+            compare::compare_runs,
+            compare::compare_run_files,
+            batch::batch_run_stats,
+            batch::batch_library_stats,
+            batch::export_batch_stats,
+            drift::estimate_drift,
+            kinematics::check_run_kinematics,
+            events::detect_run_events,
             set_windows_fullscreen,
             get_window_fullscreen_state,
             get_system_info,
@@ -395,6 +480,7 @@ fn main() {
             let port = pick_free_port();
             let child = spawn_bridge(app.handle(), port)?;
             write_bridge_pid(app.handle(), child.id());
+            recorder::start(app.handle(), port, format_log_ts());
             *app.state::<BridgeState>().0.lock().unwrap() = Some(child);
             *app.state::<BridgeOrigin>().0.lock().unwrap() =
                 Some(format!("http://127.0.0.1:{port}"));
@@ -447,12 +533,14 @@ fn main() {
                 // Fires when the app is exiting normally
                 RunEvent::Exit => {
                     persist_window_state(&app_handle);
+                    recorder::finish(&app_handle);
                     stop_bridge(&app_handle.state::<BridgeState>(), app_handle);
                 }
 
                 // Fires on quit requests (Cmd+Q / Dock Quit / menu Quit)
                 RunEvent::ExitRequested { .. } => {
                     persist_window_state(&app_handle);
+                    recorder::finish(&app_handle);
                     stop_bridge(&app_handle.state::<BridgeState>(), app_handle);
                 }
 
@@ -0,0 +1,460 @@
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, State};
+
+use crate::settings::{parses_as, read_with_backup, saved_paths_path, write_atomic};
+
+const HISTORY_DIR: &str = "PlanHistory";
+const REVISIONS_FILE: &str = "revisions.jsonl";
+const SAME_EPS: f64 = 1e-6;
+
+/// A planned-path point, as stored under `planned-path` in saved-paths.json.
+/// Positions are in inches, theta in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub theta: f64,
+}
+
+impl Waypoint {
+    fn same_as(&self, other: &Waypoint) -> bool {
+        (self.x - other.x).abs() < SAME_EPS
+            && (self.y - other.y).abs() < SAME_EPS
+            && angle_delta(self.theta, other.theta).abs() < SAME_EPS
+    }
+}
+
+/// Signed smallest difference `to - from`, in (-180, 180].
+pub fn angle_delta(from: f64, to: f64) -> f64 {
+    let d = (to - from).rem_euclid(360.0);
+    if d > 180.0 {
+        d - 360.0
+    } else {
+        d
+    }
+}
+
+/// Reads `planned-path` out of a saved-paths document. Waypoints with a
+/// missing or non-numeric x/y are dropped, like the webview does.
+pub fn waypoints_from_value(doc: &Value) -> Option<Vec<Waypoint>> {
+    let points = doc.get("planned-path")?.as_array()?;
+    let num = |p: &Value, k: &str| match p.get(k) {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    };
+    Some(
+        points
+            .iter()
+            .filter_map(|p| {
+                Some(Waypoint {
+                    x: num(p, "x")?,
+                    y: num(p, "y")?,
+                    theta: num(p, "theta").unwrap_or(0.0),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// saved-paths.json (or its backup); `None` if neither exists, an error if
+/// neither parses.
+fn read_saved_doc(app: &AppHandle) -> Result<Option<Map<String, Value>>, String> {
+    let path = saved_paths_path(app)?;
+    read_with_backup(&path, |s| {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    })
+}
+
+/// The planned path currently in saved-paths.json; empty if nothing is saved.
+pub fn read_saved_plan(app: &AppHandle) -> Result<Vec<Waypoint>, String> {
+    Ok(read_saved_doc(app)?
+        .and_then(|doc| waypoints_from_value(&Value::Object(doc)))
+        .unwrap_or_default())
+}
+
+/// Serializes appends to the revision log.
+#[derive(Default)]
+pub struct PlanHistory {
+    lock: Mutex<()>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: u64,
+    pub time_ms: u64,
+    pub message: Option<String>,
+    pub waypoints: Vec<Waypoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionInfo {
+    pub id: u64,
+    pub time_ms: u64,
+    pub message: Option<String>,
+    pub waypoint_count: usize,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn revisions_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e: tauri::Error| e.to_string())?
+        .join(HISTORY_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(REVISIONS_FILE))
+}
+
+/// One revision per line. A line torn by a crash mid-append is skipped.
+fn parse_revisions(contents: &str) -> Vec<Revision> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Revision>(line).ok())
+        .collect()
+}
+
+fn read_revisions(app: &AppHandle) -> Result<Vec<Revision>, String> {
+    let path = revisions_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    Ok(parse_revisions(&contents))
+}
+
+/// Appends `line` (ending in a newline) to the log. A torn last line is
+/// closed off first, so the new revision doesn't join it and get skipped too.
+fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    let mut out = String::with_capacity(line.len() + 1);
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            out.push('\n');
+        }
+    }
+    out.push_str(line);
+    file.write_all(out.as_bytes())?;
+    file.sync_all()
+}
+
+fn append_revision(
+    app: &AppHandle,
+    waypoints: Vec<Waypoint>,
+    message: Option<String>,
+    only_if_changed: bool,
+) -> Result<Option<Revision>, String> {
+    let history = app.state::<PlanHistory>();
+    let _guard = history.lock.lock().unwrap();
+    let revisions = read_revisions(app)?;
+    let last = revisions.last();
+    if only_if_changed {
+        let unchanged = match last {
+            Some(r) => {
+                r.waypoints.len() == waypoints.len()
+                    && r.waypoints
+                        .iter()
+                        .zip(&waypoints)
+                        .all(|(a, b)| a.same_as(b))
+            }
+            None => waypoints.is_empty(),
+        };
+        if unchanged {
+            return Ok(None);
+        }
+    }
+    let revision = Revision {
+        id: last.map_or(1, |r| r.id + 1),
+        time_ms: now_ms(),
+        message: message
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty()),
+        waypoints,
+    };
+    let mut line = serde_json::to_string(&revision).map_err(|e| e.to_string())?;
+    line.push('\n');
+    append_line(&revisions_path(app)?, &line).map_err(|e| e.to_string())?;
+    Ok(Some(revision))
+}
+
+/// Called after every saved-paths write; records the plan if it changed.
+pub fn record_saved_plan(app: &AppHandle, doc: &Value) {
+    if let Some(waypoints) = waypoints_from_value(doc) {
+        if let Err(e) = append_revision(app, waypoints, None, true) {
+            eprintln!("PLAN HISTORY ERROR: {e}");
+        }
+    }
+}
+
+fn find_revision(revisions: &[Revision], id: u64) -> Result<&Revision, String> {
+    revisions
+        .iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("no plan revision {id}"))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexedWaypoint {
+    pub index: usize,
+    pub waypoint: Waypoint,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MovedWaypoint {
+    pub from_index: usize,
+    pub to_index: usize,
+    pub from: Waypoint,
+    pub to: Waypoint,
+    pub dx: f64,
+    pub dy: f64,
+    pub dtheta: f64,
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaypointDiff {
+    pub unchanged: usize,
+    pub added: Vec<IndexedWaypoint>,
+    pub removed: Vec<IndexedWaypoint>,
+    pub moved: Vec<MovedWaypoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanDiff {
+    pub from: u64,
+    pub to: u64,
+    #[serde(flatten)]
+    pub diff: WaypointDiff,
+}
+
+/// Waypoints that are identical in both plans are matched first (longest
+/// common subsequence). Between two matched anchors, leftover old and new
+/// points are paired in order as moves; whatever is left over on one side
+/// was removed or added.
+pub fn diff_waypoints(old: &[Waypoint], new: &[Waypoint]) -> WaypointDiff {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i].same_as(&new[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut anchors = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i].same_as(&new[j]) {
+            anchors.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    anchors.push((n, m));
+
+    let (mut added, mut removed, mut moved) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut oi, mut nj) = (0, 0);
+    for &(ai, aj) in &anchors {
+        let olds: Vec<usize> = (oi..ai).collect();
+        let news: Vec<usize> = (nj..aj).collect();
+        let paired = olds.len().min(news.len());
+        for k in 0..paired {
+            let (from, to) = (old[olds[k]], new[news[k]]);
+            let (dx, dy) = (to.x - from.x, to.y - from.y);
+            moved.push(MovedWaypoint {
+                from_index: olds[k],
+                to_index: news[k],
+                from,
+                to,
+                dx,
+                dy,
+                dtheta: angle_delta(from.theta, to.theta),
+                distance: dx.hypot(dy),
+            });
+        }
+        removed.extend(olds[paired..].iter().map(|&index| IndexedWaypoint {
+            index,
+            waypoint: old[index],
+        }));
+        added.extend(news[paired..].iter().map(|&index| IndexedWaypoint {
+            index,
+            waypoint: new[index],
+        }));
+        oi = ai + 1;
+        nj = aj + 1;
+    }
+    WaypointDiff {
+        unchanged: anchors.len() - 1,
+        added,
+        removed,
+        moved,
+    }
+}
+
+#[tauri::command]
+pub fn list_plan_revisions(app: AppHandle) -> Result<Vec<RevisionInfo>, String> {
+    Ok(read_revisions(&app)?
+        .into_iter()
+        .map(|r| RevisionInfo {
+            id: r.id,
+            time_ms: r.time_ms,
+            message: r.message,
+            waypoint_count: r.waypoints.len(),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_plan_revision(app: AppHandle, id: u64) -> Result<Revision, String> {
+    let revisions = read_revisions(&app)?;
+    find_revision(&revisions, id).cloned()
+}
+
+/// Snapshots the currently saved plan with a message, even if it hasn't
+/// changed since the last revision.
+#[tauri::command]
+pub fn commit_plan_revision(app: AppHandle, message: Option<String>) -> Result<Revision, String> {
+    let waypoints = read_saved_plan(&app)?;
+    append_revision(&app, waypoints, message, false)?
+        .ok_or_else(|| "plan revision was not recorded".to_string())
+}
+
+#[tauri::command]
+pub fn diff_plan_revisions(app: AppHandle, from: u64, to: u64) -> Result<PlanDiff, String> {
+    let revisions = read_revisions(&app)?;
+    let old = find_revision(&revisions, from)?;
+    let new = find_revision(&revisions, to)?;
+    Ok(PlanDiff {
+        from,
+        to,
+        diff: diff_waypoints(&old.waypoints, &new.waypoints),
+    })
+}
+
+/// Puts an old revision back into saved-paths.json (leaving the robot path
+/// and watches alone) and records that as a new revision. The webview should
+/// reload the plan from the returned revision.
+#[tauri::command]
+pub fn restore_plan_revision(
+    app: AppHandle,
+    history: State<'_, PlanHistory>,
+    id: u64,
+) -> Result<Revision, String> {
+    let waypoints = {
+        let _guard = history.lock.lock().unwrap();
+        let revisions = read_revisions(&app)?;
+        find_revision(&revisions, id)?.waypoints.clone()
+    };
+    // Only start a fresh document when nothing was ever saved; a file that
+    // can't be read must not be replaced by one holding just the plan.
+    let mut root = read_saved_doc(&app)?.unwrap_or_default();
+    root.insert(
+        "planned-path".to_string(),
+        serde_json::to_value(&waypoints).map_err(|e| e.to_string())?,
+    );
+    let contents = serde_json::to_string(&root).map_err(|e| e.to_string())?;
+    write_atomic(
+        &saved_paths_path(&app)?,
+        contents,
+        parses_as::<Map<String, Value>>,
+    )?;
+    append_revision(
+        &app,
+        waypoints,
+        Some(format!("Restored revision {id}")),
+        false,
+    )?
+    .ok_or_else(|| "plan revision was not recorded".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(id: u64) -> String {
+        let r = Revision {
+            id,
+            time_ms: 1000 * id,
+            message: None,
+            waypoints: vec![Waypoint {
+                x: id as f64,
+                y: 0.0,
+                theta: 90.0,
+            }],
+        };
+        let mut line = serde_json::to_string(&r).unwrap();
+        line.push('\n');
+        line
+    }
+
+    #[test]
+    fn append_after_torn_line_starts_a_new_line() {
+        let dir = std::env::temp_dir().join(format!("mv-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(REVISIONS_FILE);
+        let second = revision(2);
+        std::fs::write(&path, revision(1) + &second[..second.len() / 2]).unwrap();
+
+        append_line(&path, &revision(3)).unwrap();
+        append_line(&path, &revision(4)).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let ids: Vec<u64> = parse_revisions(&contents).iter().map(|r| r.id).collect();
+        assert_eq!(ids, [1, 3, 4]);
+        assert!(contents.ends_with('\n'));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn append_creates_the_log() {
+        let dir = std::env::temp_dir().join(format!("mv-plan-new-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(REVISIONS_FILE);
+        append_line(&path, &revision(1)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), revision(1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn diff_pairs_moves_between_unchanged_points() {
+        let w = |x: f64, y: f64| Waypoint { x, y, theta: 0.0 };
+        let old = [w(0.0, 0.0), w(10.0, 0.0), w(20.0, 0.0)];
+        let new = [w(0.0, 0.0), w(10.0, 5.0), w(20.0, 0.0), w(30.0, 0.0)];
+        let d = diff_waypoints(&old, &new);
+        assert_eq!(d.unchanged, 2);
+        assert_eq!(d.moved.len(), 1);
+        assert_eq!((d.moved[0].dx, d.moved[0].dy), (0.0, 5.0));
+        assert_eq!(d.added.len(), 1);
+        assert_eq!(d.added[0].index, 3);
+        assert!(d.removed.is_empty());
+    }
+}
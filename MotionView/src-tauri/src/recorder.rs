@@ -0,0 +1,227 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::sdlog::{self, SdLogImport};
+use crate::ws::{Message, WsClient};
+
+const SESSIONS_DIR: &str = "Sessions";
+/// Still being written, or the app died before it could be closed.
+const PARTIAL_EXT: &str = "partial";
+const FINISHED_EXT: &str = "session";
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Records every line the bridge broadcasts into `Sessions/<ts>.partial`,
+/// one `<host epoch ms>\t<line>` per line. The file is renamed to
+/// `<ts>.session` on a clean exit; anything left `.partial` at startup is an
+/// unfinished session that can be recovered.
+#[derive(Default)]
+pub struct Recorder {
+    stop: AtomicBool,
+    current: Mutex<Option<PathBuf>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub path: String,
+    pub name: String,
+    pub bytes: u64,
+    pub lines: usize,
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+pub fn sessions_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e: tauri::Error| e.to_string())?
+        .join(SESSIONS_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+struct SessionFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl SessionFile {
+    /// Appends one line, creating the file on first use so launches that
+    /// never stream leave nothing behind.
+    fn append(&mut self, app: &AppHandle, line: &str) -> std::io::Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            *app.state::<Recorder>().current.lock().unwrap() = Some(self.path.clone());
+            self.file = Some(file);
+        }
+        let record = format!("{}\t{}\n", now_ms(), line.trim_end_matches(['\r', '\n']));
+        match self.file.as_mut() {
+            // Unbuffered on purpose: a crash loses at most the line in flight.
+            Some(f) => f.write_all(record.as_bytes()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Starts the background recorder for a bridge listening on `port`.
+pub fn start(app: &AppHandle, port: u16, session_name: String) {
+    let dir = match sessions_dir(app) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("RECORDER ERROR: {e}");
+            return;
+        }
+    };
+    let mut session = SessionFile {
+        path: dir.join(format!("{session_name}.{PARTIAL_EXT}")),
+        file: None,
+    };
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let stopped = || app.state::<Recorder>().stop.load(Ordering::Relaxed);
+        while !stopped() {
+            // The bridge takes a moment to come up, and may restart.
+            let mut ws = match WsClient::connect("127.0.0.1", port, "/ws", READ_TIMEOUT) {
+                Ok(ws) => ws,
+                Err(_) => {
+                    std::thread::sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            };
+            while !stopped() {
+                match ws.read() {
+                    Ok(Message::Text(text)) => {
+                        for line in text.lines() {
+                            if let Err(e) = session.append(&app, line) {
+                                eprintln!("RECORDER ERROR: {:?}: {e}", session.path);
+                            }
+                        }
+                    }
+                    Ok(Message::Idle) => {}
+                    Ok(Message::Closed) | Err(_) => break,
+                }
+            }
+            ws.close();
+        }
+    });
+}
+
+fn finished_path(partial: &Path) -> PathBuf {
+    partial.with_extension(FINISHED_EXT)
+}
+
+/// Stops recording and marks the current session as cleanly finished.
+pub fn finish(app: &AppHandle) {
+    let recorder = app.state::<Recorder>();
+    recorder.stop.store(true, Ordering::Relaxed);
+    let current = recorder.current.lock().unwrap().take();
+    if let Some(path) = current {
+        if let Err(e) = std::fs::rename(&path, finished_path(&path)) {
+            eprintln!("RECORDER ERROR: failed to close session {:?}: {e}", path);
+        }
+    }
+}
+
+/// Only accepts `.partial` files inside the sessions directory, so the
+/// webview can't point these commands at arbitrary files.
+fn checked_partial(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let dir = sessions_dir(app)?
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    let p = Path::new(path).canonicalize().map_err(|e| e.to_string())?;
+    if p.parent() != Some(dir.as_path())
+        || p.extension().and_then(|e| e.to_str()) != Some(PARTIAL_EXT)
+    {
+        return Err("not an unfinished session file".into());
+    }
+    Ok(p)
+}
+
+/// Strips the host timestamp column, leaving the lines as the bridge sent them.
+pub fn session_text(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    for record in contents.lines() {
+        let line = match record.split_once('\t') {
+            Some((ts, line)) if ts.chars().all(|c| c.is_ascii_digit()) => line,
+            _ => record,
+        };
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[tauri::command]
+pub fn list_unfinished_sessions(
+    app: AppHandle,
+    recorder: State<'_, Recorder>,
+) -> Result<Vec<SessionInfo>, String> {
+    let dir = sessions_dir(&app)?;
+    let current = recorder.current.lock().unwrap().clone();
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let path = match entry {
+            Ok(e) => e.path(),
+            Err(_) => continue,
+        };
+        if path.extension().and_then(|e| e.to_str()) != Some(PARTIAL_EXT)
+            || current.as_deref() == Some(path.as_path())
+        {
+            continue;
+        }
+        let contents = std::fs::read(&path).unwrap_or_default();
+        out.push(SessionInfo {
+            path: path.to_string_lossy().to_string(),
+            name: path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string(),
+            bytes: contents.len() as u64,
+            lines: contents.iter().filter(|b| **b == b'\n').count(),
+        });
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(out)
+}
+
+/// Rebuilds a run from an unfinished session, writes it next to the session
+/// as `<ts>.json` and closes the session so it is not offered again.
+#[tauri::command]
+pub fn recover_session(app: AppHandle, path: String) -> Result<SdLogImport, String> {
+    let partial = checked_partial(&app, &path)?;
+    let bytes = std::fs::read(&partial).map_err(|e| e.to_string())?;
+    let text = session_text(&String::from_utf8_lossy(&bytes));
+    let dir = partial.parent().map(Path::to_path_buf).unwrap_or_default();
+    let report = sdlog::import_text(&partial, &text, Some(&dir));
+    if report.error.is_none() {
+        std::fs::rename(&partial, finished_path(&partial)).map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn discard_session(app: AppHandle, path: String) -> Result<(), String> {
+    let partial = checked_partial(&app, &path)?;
+    std::fs::remove_file(partial).map_err(|e| e.to_string())
+}
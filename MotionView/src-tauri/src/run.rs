@@ -0,0 +1,582 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::events::RunEvent;
+use crate::telemetry::{wheel_speed, Pose, Watch};
+
+pub const CURRENT_RUN_VERSION: u64 = 1;
+const KNOWN_UNITS: [&str; 5] = ["in", "ft", "mm", "cm", "m"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RobotSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMeta {
+    pub run_name: String,
+    pub units: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robot: Option<RobotSize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_hz: Option<f64>,
+}
+
+impl Default for RunMeta {
+    fn default() -> Self {
+        RunMeta {
+            run_name: String::new(),
+            units: "in".to_string(),
+            robot: None,
+            log_hz: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Thinning {
+    pub raw: usize,
+    pub kept: usize,
+    pub removed: usize,
+    pub viewer_thin_ms: u64,
+    pub xy_tol: f64,
+    pub theta_tol: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    pub version: u64,
+    pub meta: RunMeta,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinning: Option<Thinning>,
+    pub poses: Vec<Pose>,
+    #[serde(default)]
+    pub watches: Vec<Watch>,
+    /// Filled in by `detect_run_events`; empty until then.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<RunEvent>,
+}
+
+impl Run {
+    pub fn new(meta: RunMeta, poses: Vec<Pose>, watches: Vec<Watch>) -> Self {
+        Run {
+            version: CURRENT_RUN_VERSION,
+            meta,
+            thinning: None,
+            poses,
+            watches,
+            events: Vec::new(),
+        }
+    }
+
+    /// Inches per position unit, from `meta.units`.
+    pub fn inches_scale(&self) -> Result<f64, String> {
+        inches_per_unit(&self.meta.units)
+            .ok_or_else(|| format!("unknown units {:?}", self.meta.units))
+    }
+}
+
+/// Motion from one pose to a later one, with positions multiplied by the
+/// scale given to `step`.
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    /// Seconds.
+    pub dt: f64,
+    pub dx: f64,
+    pub dy: f64,
+    /// Shortest heading change in degrees.
+    pub turn: f64,
+    /// Signed speed along the mean heading, negative when driving backwards.
+    pub speed: f64,
+}
+
+/// `None` unless `b` comes after `a`.
+pub fn step(a: &Pose, b: &Pose, scale: f64) -> Option<Step> {
+    if b.t <= a.t {
+        return None;
+    }
+    let dt = (b.t - a.t) as f64 / 1000.0;
+    let (dx, dy) = ((b.x - a.x) * scale, (b.y - a.y) * scale);
+    let turn = crate::plan::angle_delta(a.theta, b.theta);
+    // Compass headings: 0° is +y, so the heading vector is (sin h, cos h).
+    let h = (a.theta + turn / 2.0).to_radians();
+    Some(Step {
+        dt,
+        dx,
+        dy,
+        turn,
+        speed: (dx * h.sin() + dy * h.cos()) / dt,
+    })
+}
+
+/// How many inches one of `meta.units` is, for the units `validate` accepts.
+pub fn inches_per_unit(units: &str) -> Option<f64> {
+    match units {
+        "in" => Some(1.0),
+        "ft" => Some(12.0),
+        "mm" => Some(1.0 / 25.4),
+        "cm" => Some(1.0 / 2.54),
+        "m" => Some(1.0 / 0.0254),
+        _ => None,
+    }
+}
+
+/// A validation failure pointing at the offending value, e.g.
+/// `poses[42].theta: expected number`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunError {
+    pub path: String,
+    pub message: String,
+}
+
+impl RunError {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        RunError {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn expected(path: &str, what: &str, found: &Value) -> RunError {
+    RunError::new(path, format!("expected {what}, found {}", type_name(found)))
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn as_object<'a>(path: &str, v: &'a Value) -> Result<&'a Map<String, Value>, RunError> {
+    v.as_object().ok_or_else(|| expected(path, "object", v))
+}
+
+fn as_array<'a>(path: &str, v: &'a Value) -> Result<&'a Vec<Value>, RunError> {
+    v.as_array().ok_or_else(|| expected(path, "array", v))
+}
+
+fn require<'a>(obj: &'a Map<String, Value>, path: &str, key: &str) -> Result<&'a Value, RunError> {
+    obj.get(key)
+        .ok_or_else(|| RunError::new(&child(path, key), "missing field"))
+}
+
+fn check_number(path: &str, v: &Value) -> Result<f64, RunError> {
+    match v.as_f64() {
+        Some(n) if n.is_finite() => Ok(n),
+        _ => Err(expected(path, "number", v)),
+    }
+}
+
+fn check_optional_number(path: &str, v: Option<&Value>) -> Result<(), RunError> {
+    match v {
+        None | Some(Value::Null) => Ok(()),
+        Some(v) => check_number(path, v).map(|_| ()),
+    }
+}
+
+fn check_millis(path: &str, v: &Value) -> Result<(), RunError> {
+    check_number(path, v)?;
+    // `12723.0` is a float to serde and would fail later without a path.
+    if v.as_u64().is_none() {
+        return Err(RunError::new(
+            path,
+            "expected non-negative integer milliseconds",
+        ));
+    }
+    Ok(())
+}
+
+fn check_count(path: &str, v: &Value) -> Result<(), RunError> {
+    if v.as_u64().is_none() {
+        return Err(expected(path, "non-negative integer", v));
+    }
+    Ok(())
+}
+
+fn check_string<'a>(path: &str, v: &'a Value) -> Result<&'a str, RunError> {
+    v.as_str().ok_or_else(|| expected(path, "string", v))
+}
+
+fn validate_meta(path: &str, v: &Value) -> Result<(), RunError> {
+    let meta = as_object(path, v)?;
+    check_string(&child(path, "run_name"), require(meta, path, "run_name")?)?;
+    let units_path = child(path, "units");
+    let units = check_string(&units_path, require(meta, path, "units")?)?;
+    if !KNOWN_UNITS.contains(&units) {
+        return Err(RunError::new(
+            &units_path,
+            format!(
+                "unknown unit {units:?} (expected one of {})",
+                KNOWN_UNITS.join(", ")
+            ),
+        ));
+    }
+    if let Some(robot) = meta.get("robot").filter(|r| !r.is_null()) {
+        let robot_path = child(path, "robot");
+        let obj = as_object(&robot_path, robot)?;
+        for key in ["width", "height"] {
+            let p = child(&robot_path, key);
+            if check_number(&p, require(obj, &robot_path, key)?)? <= 0.0 {
+                return Err(RunError::new(&p, "expected positive number"));
+            }
+        }
+    }
+    if let Some(hz) = meta.get("log_hz").filter(|h| !h.is_null()) {
+        let p = child(path, "log_hz");
+        if check_number(&p, hz)? <= 0.0 {
+            return Err(RunError::new(&p, "expected positive number"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_thinning(path: &str, v: &Value) -> Result<(), RunError> {
+    let obj = as_object(path, v)?;
+    for key in ["raw", "kept", "removed", "viewer_thin_ms"] {
+        check_count(&child(path, key), require(obj, path, key)?)?;
+    }
+    for key in ["xy_tol", "theta_tol"] {
+        check_number(&child(path, key), require(obj, path, key)?)?;
+    }
+    Ok(())
+}
+
+fn validate_pose(path: &str, v: &Value) -> Result<(), RunError> {
+    let obj = as_object(path, v)?;
+    check_millis(&child(path, "t"), require(obj, path, "t")?)?;
+    for key in ["x", "y", "theta"] {
+        check_number(&child(path, key), require(obj, path, key)?)?;
+    }
+    for key in ["l_vel", "r_vel", "speed"] {
+        check_optional_number(&child(path, key), obj.get(key))?;
+    }
+    Ok(())
+}
+
+fn validate_watch(path: &str, v: &Value) -> Result<(), RunError> {
+    let obj = as_object(path, v)?;
+    check_millis(&child(path, "time"), require(obj, path, "time")?)?;
+    check_string(&child(path, "level"), require(obj, path, "level")?)?;
+    check_string(&child(path, "label"), require(obj, path, "label")?)?;
+    check_string(&child(path, "value"), require(obj, path, "value")?)?;
+    Ok(())
+}
+
+/// Checks a current-version run document and reports the first bad value.
+pub fn validate(v: &Value) -> Result<(), RunError> {
+    let root = as_object("", v)?;
+    validate_meta("meta", require(root, "", "meta")?)?;
+    if let Some(thinning) = root.get("thinning").filter(|t| !t.is_null()) {
+        validate_thinning("thinning", thinning)?;
+    }
+    for (i, pose) in as_array("poses", require(root, "", "poses")?)?
+        .iter()
+        .enumerate()
+    {
+        validate_pose(&format!("poses[{i}]"), pose)?;
+    }
+    if let Some(watches) = root.get("watches") {
+        for (i, watch) in as_array("watches", watches)?.iter().enumerate() {
+            validate_watch(&format!("watches[{i}]"), watch)?;
+        }
+    }
+    Ok(())
+}
+
+fn rename_key(obj: &mut Map<String, Value>, aliases: &[&str], key: &str) {
+    if obj.contains_key(key) {
+        return;
+    }
+    for alias in aliases {
+        if let Some(v) = obj.remove(*alias) {
+            obj.insert(key.to_string(), v);
+            return;
+        }
+    }
+}
+
+/// Version 0 is the unversioned shape the viewer has always accepted: a bare
+/// `{poses, watches}` object (or `robot-path` from `saved-paths.json`), watches
+/// keyed by `t`, and poses without a derived `speed`.
+fn migrate_v0_to_v1(mut v: Value) -> Result<Value, RunError> {
+    if !v.is_object() {
+        return Err(expected("", "object", &v));
+    }
+    let root = match v.as_object_mut() {
+        Some(root) => root,
+        None => return Ok(v),
+    };
+    rename_key(root, &["robot-path"], "poses");
+    rename_key(root, &["watch"], "watches");
+    root.entry("meta")
+        .or_insert_with(|| serde_json::to_value(RunMeta::default()).unwrap_or(Value::Null));
+    if let Some(meta) = root.get_mut("meta").and_then(Value::as_object_mut) {
+        meta.entry("run_name")
+            .or_insert(Value::String(String::new()));
+        meta.entry("units")
+            .or_insert(Value::String("in".to_string()));
+    }
+    if let Some(watches) = root.get_mut("watches").and_then(Value::as_array_mut) {
+        for w in watches.iter_mut().filter_map(Value::as_object_mut) {
+            rename_key(w, &["t", "timestamp", "ms"], "time");
+            rename_key(w, &["lvl", "severity"], "level");
+            rename_key(w, &["name"], "label");
+            rename_key(w, &["val", "message"], "value");
+            w.entry("level")
+                .or_insert(Value::String("INFO".to_string()));
+            w.entry("label").or_insert(Value::String(String::new()));
+            w.entry("value").or_insert(Value::String(String::new()));
+        }
+    }
+    if let Some(poses) = root.get_mut("poses").and_then(Value::as_array_mut) {
+        for p in poses.iter_mut().filter_map(Value::as_object_mut) {
+            rename_key(p, &["speed_raw"], "speed");
+        }
+    }
+    root.insert("version".to_string(), Value::from(1u64));
+    Ok(v)
+}
+
+type Migration = fn(Value) -> Result<Value, RunError>;
+
+/// Ordered upgrades; entry `n` lifts a version `n` document to `n + 1`.
+const MIGRATIONS: [Migration; CURRENT_RUN_VERSION as usize] = [migrate_v0_to_v1];
+
+fn document_version(v: &Value) -> Result<u64, RunError> {
+    match v.get("version") {
+        None | Some(Value::Null) => Ok(0),
+        Some(ver) => ver
+            .as_u64()
+            .ok_or_else(|| expected("version", "non-negative integer", ver)),
+    }
+}
+
+pub fn migrate(mut v: Value) -> Result<Value, RunError> {
+    let mut version = document_version(&v)?;
+    if version > CURRENT_RUN_VERSION {
+        return Err(RunError::new(
+            "version",
+            format!(
+                "run file version {version} is newer than this app supports ({CURRENT_RUN_VERSION})"
+            ),
+        ));
+    }
+    while version < CURRENT_RUN_VERSION {
+        v = MIGRATIONS[version as usize](v)?;
+        version += 1;
+    }
+    Ok(v)
+}
+
+/// Fills in `speed` for poses that don't carry one, using the same rule as
+/// the live parser.
+fn fill_speed(p: &mut Map<String, Value>) {
+    if p.get("speed").is_none_or(Value::is_null) {
+        let l = p.get("l_vel").and_then(Value::as_f64);
+        let r = p.get("r_vel").and_then(Value::as_f64);
+        p.insert("speed".to_string(), Value::from(wheel_speed(l, r)));
+    }
+}
+
+fn fill_derived(v: &mut Value) {
+    let poses = match v.get_mut("poses").and_then(Value::as_array_mut) {
+        Some(p) => p,
+        None => return,
+    };
+    for p in poses.iter_mut().filter_map(Value::as_object_mut) {
+        fill_speed(p);
+    }
+}
+
+/// Validates and converts a single pose on its own, for loaders that never
+/// hold the whole `poses` array in memory. Accepts both current and v0 keys.
+pub fn pose_from_value(path: &str, mut v: Value) -> Result<Pose, RunError> {
+    if let Some(p) = v.as_object_mut() {
+        rename_key(p, &["speed_raw"], "speed");
+    }
+    validate_pose(path, &v)?;
+    if let Some(p) = v.as_object_mut() {
+        fill_speed(p);
+    }
+    serde_json::from_value(v).map_err(|e| RunError::new(path, e.to_string()))
+}
+
+pub fn from_value(v: Value) -> Result<Run, RunError> {
+    let mut v = migrate(v)?;
+    validate(&v)?;
+    fill_derived(&mut v);
+    let mut run: Run = serde_json::from_value(v).map_err(|e| RunError::new("", e.to_string()))?;
+    run.poses.sort_by_key(|p| p.t);
+    run.watches.sort_by_key(|w| w.t);
+    Ok(run)
+}
+
+pub fn from_str(contents: &str) -> Result<Run, RunError> {
+    let v: Value = serde_json::from_str(contents).map_err(|e| RunError::new("", e.to_string()))?;
+    from_value(v)
+}
+
+/// Reads a JSON run, or a compact `.mvrun` container by its magic bytes.
+pub fn read_run_file(path: &std::path::Path) -> Result<Run, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    if crate::compact::is_compact(&bytes) {
+        return crate::compact::decode(&bytes);
+    }
+    let contents = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+    from_str(&contents).map_err(|e| e.to_string())
+}
+
+pub fn write_run_file(path: &std::path::Path, run: &Run) -> Result<(), String> {
+    let contents = serde_json::to_string(run).map_err(|e| e.to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, contents).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn load_run(path: String) -> Result<Run, String> {
+    read_run_file(std::path::Path::new(&path))
+}
+
+#[tauri::command]
+pub fn parse_run(contents: String) -> Result<Run, String> {
+    from_str(&contents).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_run(path: String, mut run: Run) -> Result<(), String> {
+    run.version = CURRENT_RUN_VERSION;
+    // Round-trip through validation so a bad payload from the webview can't
+    // produce a file that fails to load later.
+    let v = serde_json::to_value(&run).map_err(|e| e.to_string())?;
+    validate(&v).map_err(|e| e.to_string())?;
+    write_run_file(std::path::Path::new(&path), &run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn doc(poses: Value) -> Value {
+        json!({
+            "version": 1,
+            "meta": { "run_name": "skills", "units": "in" },
+            "poses": poses,
+            "watches": [{ "time": 5, "level": "INFO", "label": "Intake", "value": "on" }],
+        })
+    }
+
+    fn pose(t: Value) -> Value {
+        json!({ "t": t, "x": 1.0, "y": 2.0, "theta": 90.0, "l_vel": 10.0, "r_vel": -20.0 })
+    }
+
+    #[test]
+    fn validate_reports_the_offending_path() {
+        let mut v = doc(json!([pose(json!(0)), pose(json!(10))]));
+        v["poses"][1]["theta"] = json!("north");
+        let err = validate(&v).unwrap_err();
+        assert_eq!(err.path, "poses[1].theta");
+        assert_eq!(
+            err.to_string(),
+            "poses[1].theta: expected number, found string"
+        );
+
+        let mut v = doc(json!([]));
+        v["meta"]["units"] = json!("furlong");
+        assert_eq!(validate(&v).unwrap_err().path, "meta.units");
+    }
+
+    #[test]
+    fn millis_must_be_non_negative_integers() {
+        for bad in [json!(12723.0), json!(12723.5), json!(-1)] {
+            let v = doc(json!([pose(json!(0)), pose(bad.clone())]));
+            let err = from_value(v).unwrap_err();
+            assert_eq!(err.path, "poses[1].t", "{bad}");
+        }
+        let mut v = doc(json!([]));
+        v["watches"][0]["time"] = json!(1.5);
+        assert_eq!(validate(&v).unwrap_err().path, "watches[0].time");
+    }
+
+    #[test]
+    fn migrates_unversioned_saved_paths() {
+        let v = json!({
+            "robot-path": [
+                { "t": 20, "x": 0.0, "y": 0.0, "theta": 0.0, "speed_raw": 3.0 },
+                { "t": 10, "x": 0.0, "y": 0.0, "theta": 0.0, "l_vel": 4.0, "r_vel": -8.0 },
+            ],
+            "watch": [{ "t": 15, "name": "Arm", "val": "up" }],
+        });
+        let run = from_value(v).unwrap();
+        assert_eq!(run.version, CURRENT_RUN_VERSION);
+        assert_eq!(run.meta, RunMeta::default());
+        let times: Vec<u64> = run.poses.iter().map(|p| p.t).collect();
+        assert_eq!(times, [10, 20]);
+        assert_eq!(run.poses[0].speed, 6.0);
+        assert_eq!(run.poses[1].speed, 3.0);
+        let w = &run.watches[0];
+        assert_eq!(
+            (w.t, w.level.as_str(), w.label.as_str()),
+            (15, "INFO", "Arm")
+        );
+        assert_eq!(w.value, "up");
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut v = doc(json!([]));
+        v["version"] = json!(CURRENT_RUN_VERSION + 1);
+        assert_eq!(migrate(v).unwrap_err().path, "version");
+        let mut v = doc(json!([]));
+        v["version"] = json!("1");
+        assert_eq!(migrate(v).unwrap_err().path, "version");
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let run = from_value(doc(json!([pose(json!(0)), pose(json!(10))]))).unwrap();
+        let again = from_str(&serde_json::to_string(&run).unwrap()).unwrap();
+        assert_eq!(run, again);
+    }
+
+    #[test]
+    fn single_pose_loader_matches_full_loader() {
+        let p = pose_from_value("poses[0]", pose(json!(7))).unwrap();
+        assert_eq!(p, Pose::new(7, 1.0, 2.0, 90.0, Some(10.0), Some(-20.0)));
+        assert_eq!(
+            pose_from_value("poses[3]", pose(json!(7.5)))
+                .unwrap_err()
+                .path,
+            "poses[3].t"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::run::{self, Run, RunMeta};
+use crate::telemetry::{LineError, Pose, TelemetryParser};
+
+const SD_LOG_PREFIX: &str = "MVLIB_";
+const HEADER_PREFIX: &str = "=== Logger initialized at ";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SdLogImport {
+    pub source: String,
+    /// Where the converted run was written, if it was written at all.
+    pub output: Option<String>,
+    pub run: Option<Run>,
+    /// Seconds since program start reported by the `=== Logger initialized ===` header.
+    pub initialized_at: Option<f64>,
+    pub skipped: Vec<LineError>,
+    /// Plain log lines with no telemetry in them (setup messages, etc.).
+    pub ignored: usize,
+    pub error: Option<String>,
+}
+
+/// `/usd/MVLIB_2026-02-05_13-21.log` -> `2026-02-05_13-21`.
+pub fn run_name_from_path(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string();
+    match stem.strip_prefix(SD_LOG_PREFIX) {
+        Some(rest) if !rest.is_empty() => rest.to_string(),
+        _ => stem,
+    }
+}
+
+fn parse_header(line: &str) -> Option<f64> {
+    line.trim()
+        .strip_prefix(HEADER_PREFIX)?
+        .strip_suffix("s ===")?
+        .trim()
+        .parse::<f64>()
+        .ok()
+}
+
+/// Sample rate from the median spacing between poses, which stays stable when
+/// the log has a few stalls or dropped lines.
+pub fn estimate_log_hz(poses: &[Pose]) -> Option<f64> {
+    let mut deltas: Vec<u64> = poses
+        .windows(2)
+        .map(|w| w[1].t.saturating_sub(w[0].t))
+        .filter(|dt| *dt > 0)
+        .collect();
+    if deltas.is_empty() {
+        return None;
+    }
+    deltas.sort_unstable();
+    let median = deltas[deltas.len() / 2] as f64;
+    let hz = 1000.0 / median;
+    Some((hz * 100.0).round() / 100.0)
+}
+
+/// Parses one log's text into a run, recording skipped lines on `report`.
+pub fn convert_text(text: &str, run_name: String, report: &mut SdLogImport) -> Run {
+    let mut parser = TelemetryParser::new();
+    let mut initialized_at = None;
+    let mut headers = 0;
+    for line in text.lines() {
+        if let Some(secs) = parse_header(line) {
+            initialized_at.get_or_insert(secs);
+            headers += 1;
+        }
+        parser.push_line(line);
+    }
+    let parsed = parser.finish();
+    let meta = RunMeta {
+        run_name,
+        log_hz: estimate_log_hz(&parsed.poses),
+        ..RunMeta::default()
+    };
+    report.initialized_at = initialized_at;
+    report.skipped = parsed.errors;
+    report.ignored = parsed.ignored - headers;
+    Run::new(meta, parsed.poses, parsed.watches)
+}
+
+impl SdLogImport {
+    fn new(source: &Path) -> Self {
+        SdLogImport {
+            source: source.to_string_lossy().to_string(),
+            output: None,
+            run: None,
+            initialized_at: None,
+            skipped: Vec::new(),
+            ignored: 0,
+            error: None,
+        }
+    }
+}
+
+fn import_one(source: &Path, dest: Option<PathBuf>) -> SdLogImport {
+    let bytes = match std::fs::read(source) {
+        Ok(b) => b,
+        Err(e) => {
+            let mut report = SdLogImport::new(source);
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+    // A card pulled mid-write can leave a torn UTF-8 sequence at the end.
+    let text = String::from_utf8_lossy(&bytes);
+    convert_to(source, &text, dest)
+}
+
+/// Converts already-read log text. `source` names the run and, with
+/// `out_dir`, decides where `<name>.json` is written.
+pub fn import_text(source: &Path, text: &str, out_dir: Option<&Path>) -> SdLogImport {
+    convert_to(
+        source,
+        text,
+        out_dir.map(|dir| output_path(source, dir, &[])),
+    )
+}
+
+fn convert_to(source: &Path, text: &str, dest: Option<PathBuf>) -> SdLogImport {
+    let mut report = SdLogImport::new(source);
+    let run = convert_text(text, run_name_from_path(source), &mut report);
+
+    if run.poses.is_empty() {
+        report.error = Some("no [DATA] entries found in log".into());
+        return report;
+    }
+
+    if let Some(dest) = dest {
+        match run::write_run_file(&dest, &run) {
+            Ok(()) => report.output = Some(dest.to_string_lossy().to_string()),
+            Err(e) => report.error = Some(e),
+        }
+    }
+    report.run = Some(run);
+    report
+}
+
+/// `<out_dir>/<stem>.json`, numbered `<stem>-2.json` and up when an earlier
+/// log in the same batch already took the name.
+fn output_path(source: &Path, out_dir: &Path, taken: &[PathBuf]) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "run".into());
+    let mut dest = out_dir.join(format!("{stem}.json"));
+    let mut n = 2;
+    while taken.contains(&dest) {
+        dest = out_dir.join(format!("{stem}-{n}.json"));
+        n += 1;
+    }
+    dest
+}
+
+/// Converts a single SD log and hands the run straight back to the viewer.
+#[tauri::command]
+pub fn import_sd_log(path: String) -> SdLogImport {
+    import_one(Path::new(&path), None)
+}
+
+/// Bulk conversion: every log becomes `<name>.json` in `out_dir` (or next to
+/// the log when no directory is given). Logs sharing a name get numbered
+/// outputs instead of overwriting each other. One bad file does not stop the
+/// rest.
+#[tauri::command]
+pub fn convert_sd_logs(paths: Vec<String>, out_dir: Option<String>) -> Vec<SdLogImport> {
+    let mut written = Vec::new();
+    paths
+        .iter()
+        .map(|p| {
+            let source = Path::new(p);
+            let dir = match &out_dir {
+                Some(d) => PathBuf::from(d),
+                None => source.parent().map(Path::to_path_buf).unwrap_or_default(),
+            };
+            let dest = output_path(source, &dir, &written);
+            let mut report = import_one(source, Some(dest));
+            if let Some(output) = &report.output {
+                written.push(PathBuf::from(output));
+            }
+            // The caller only needs the summary for bulk jobs; runs can be huge.
+            report.run = None;
+            report
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_named_logs_get_numbered_outputs() {
+        let root = std::env::temp_dir().join(format!("mv-sdlog-{}", std::process::id()));
+        let out = root.join("runs");
+        let mut paths = Vec::new();
+        for card in ["a", "b", "c"] {
+            let dir = root.join(card);
+            std::fs::create_dir_all(&dir).unwrap();
+            let log = dir.join("MVLIB_skills.log");
+            std::fs::write(&log, "[DATA],0,1.00,2.00,90.00,0.0,0.0\n").unwrap();
+            paths.push(log.to_string_lossy().to_string());
+        }
+        let reports = convert_sd_logs(paths, Some(out.to_string_lossy().to_string()));
+        let outputs: Vec<PathBuf> = reports
+            .iter()
+            .map(|r| PathBuf::from(r.output.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                out.join("MVLIB_skills.json"),
+                out.join("MVLIB_skills-2.json"),
+                out.join("MVLIB_skills-3.json"),
+            ]
+        );
+        assert!(outputs.iter().all(|p| p.exists()));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
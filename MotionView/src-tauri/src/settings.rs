@@ -1,22 +1,42 @@
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use base64::Engine as _;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use tauri::{AppHandle, Manager};
 
 const SETTINGS_FILE: &str = "user-preferences.json";
 const ROBOT_IMAGE_FILE_BASE: &str = "robot-image";
 const SAVED_PATHS_FILE: &str = "saved-paths.json";
+const PROFILES_DIR: &str = "Profiles";
+const PROFILES_FILE: &str = "profiles.json";
+const DEFAULT_PROFILE_ID: &str = "default";
+pub const CURRENT_SETTINGS_VERSION: u64 = 1;
+const UNITS: [&str; 4] = ["in", "cm", "ft", "tiles"];
+const FIELD_ROTATIONS: [f64; 4] = [0.0, 90.0, 180.0, 270.0];
+const DEFAULT_FIELD_KEY: &str = "./assets/match_field_2025-2026_pushback.png";
+const MAX_OFFSET_THETA: f64 = 359.0;
+const MAX_MOTOR_SPEED: f64 = 127.0;
 #[cfg(not(mobile))]
 #[allow(dead_code)]
 const WINDOW_STATE_FILE: &str = "window-state.json";
 
-fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
     let dir = app
         .path()
         .app_data_dir()
         .map_err(|e: tauri::Error| e.to_string())?;
     std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-    Ok(dir.join(SETTINGS_FILE))
+    Ok(dir)
+}
+
+/// Settings of the active profile.
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let index = read_profile_index(app)?;
+    Ok(profile_dir(app, &index.active)?.join(SETTINGS_FILE))
 }
 
 fn legacy_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
@@ -27,7 +47,7 @@ fn legacy_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(dir.join(SETTINGS_FILE))
 }
 
-fn saved_paths_path(app: &AppHandle) -> Result<PathBuf, String> {
+pub(crate) fn saved_paths_path(app: &AppHandle) -> Result<PathBuf, String> {
     let dir = app
         .path()
         .app_data_dir()
@@ -47,55 +67,803 @@ fn window_state_path(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(dir.join(WINDOW_STATE_FILE))
 }
 
-#[tauri::command]
-pub fn read_settings(app: AppHandle) -> Result<Option<String>, String> {
-    let path = settings_path(&app)?;
-    if path.exists() {
-        return std::fs::read_to_string(path)
-            .map(Some)
-            .map_err(|e| e.to_string());
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+pub(crate) fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".bak")
+}
+
+/// Writes `contents` to a temp file next to `path`, fsyncs it and renames it
+/// into place, so readers only ever see the old or the new file. The previous
+/// version is kept as `<name>.bak` if `intact` accepts it; a primary that no
+/// longer parses is dropped instead, since `.bak` may be the only good copy.
+pub(crate) fn write_atomic(
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+    intact: impl Fn(&[u8]) -> bool,
+) -> Result<(), String> {
+    let keep_backup = std::fs::read(path).is_ok_and(|current| intact(&current));
+    replace_file(path, contents.as_ref(), keep_backup)
+}
+
+/// `intact` check for JSON documents that deserialize as `T`.
+pub(crate) fn parses_as<T: DeserializeOwned>(bytes: &[u8]) -> bool {
+    serde_json::from_slice::<T>(bytes).is_ok()
+}
+
+/// Same as `write_atomic` without the backup, for files outside the app data
+/// dir (e.g. a PROS project) where a stray `.bak` would be clutter.
+pub(crate) fn write_atomic_no_backup(
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+) -> Result<(), String> {
+    replace_file(path, contents.as_ref(), false)
+}
+
+fn replace_file(path: &Path, contents: &[u8], keep_backup: bool) -> Result<(), String> {
+    let tmp = sibling_path(path, ".tmp");
+    let result = (|| -> std::io::Result<()> {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+        if keep_backup && path.exists() {
+            std::fs::rename(path, backup_path(path))?;
+        }
+        std::fs::rename(&tmp, path)?;
+        // Make the renames themselves durable.
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Reads and parses `path`, falling back to `<name>.bak` when the primary is
+/// missing or fails to parse. The primary's error wins if both fail.
+pub(crate) fn read_with_backup<T>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    let read = |p: &Path| {
+        std::fs::read_to_string(p)
+            .map_err(|e| e.to_string())
+            .and_then(|s| parse(&s))
+    };
+    let backup = backup_path(path);
+    if !path.exists() {
+        // Only possible mid-write (between the two renames) or after manual deletion.
+        return if backup.exists() {
+            read(&backup).map(Some)
+        } else {
+            Ok(None)
+        };
+    }
+    match read(path) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => match backup.exists().then(|| read(&backup)) {
+            Some(Ok(value)) => {
+                eprintln!("SETTINGS ERROR: {}: {err}; using backup", path.display());
+                Ok(Some(value))
+            }
+            _ => Err(format!("{}: {err}", path.display())),
+        },
+    }
+}
+
+fn parse_json_text(contents: &str) -> Result<String, String> {
+    serde_json::from_str::<serde_json::Value>(contents).map_err(|e| e.to_string())?;
+    Ok(contents.to_string())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RobotImage {
+    pub path: Option<String>,
+    pub data_url: Option<String>,
+}
+
+/// `user-preferences.json`. Keys the app doesn't know about are kept in
+/// `extra` and written back untouched.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub schema_version: u64,
+    pub pros_dir: String,
+    pub pros_exe: String,
+    pub robot_image_enabled: bool,
+    pub units: String,
+    pub robot_w: f64,
+    pub robot_h: f64,
+    pub off_x: f64,
+    pub off_y: f64,
+    pub off_theta: f64,
+    pub min_speed: f64,
+    pub max_speed: f64,
+    pub plan_move_step: f64,
+    pub plan_snap_step: f64,
+    pub plan_theta_snap_step: f64,
+    pub plan_speed: f64,
+    pub refresh_interval_ms: u64,
+    pub live_debug: bool,
+    pub playback_speed: f64,
+    pub selected_field: String,
+    pub robot_img_scale: f64,
+    pub robot_img_off_x: f64,
+    pub robot_img_off_y: f64,
+    pub robot_img_rot: f64,
+    pub robot_img_alpha: f64,
+    pub robot_image: RobotImage,
+    pub field_rotation: f64,
+    pub layout_left_sidebar_width: f64,
+    pub layout_right_sidebar_width: f64,
+    pub layout_timeline_height: f64,
+    pub layout_planning_waypoint_height: f64,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: CURRENT_SETTINGS_VERSION,
+            pros_dir: String::new(),
+            pros_exe: String::new(),
+            robot_image_enabled: true,
+            units: "in".to_string(),
+            robot_w: 12.0,
+            robot_h: 12.0,
+            off_x: 0.0,
+            off_y: 0.0,
+            off_theta: 0.0,
+            min_speed: 0.0,
+            max_speed: MAX_MOTOR_SPEED,
+            plan_move_step: 0.5,
+            plan_snap_step: 0.0,
+            plan_theta_snap_step: 0.0,
+            plan_speed: 50.0,
+            refresh_interval_ms: 500,
+            live_debug: false,
+            playback_speed: 1.0,
+            selected_field: DEFAULT_FIELD_KEY.to_string(),
+            robot_img_scale: 1.0,
+            robot_img_off_x: 0.0,
+            robot_img_off_y: 0.0,
+            robot_img_rot: 0.0,
+            robot_img_alpha: 100.0,
+            robot_image: RobotImage::default(),
+            field_rotation: 0.0,
+            layout_left_sidebar_width: 360.0,
+            layout_right_sidebar_width: 370.0,
+            layout_timeline_height: 180.0,
+            layout_planning_waypoint_height: 240.0,
+            extra: Map::new(),
+        }
+    }
+}
+
+const NUMERIC_KEYS: [&str; 24] = [
+    "robotW",
+    "robotH",
+    "offX",
+    "offY",
+    "offTheta",
+    "minSpeed",
+    "maxSpeed",
+    "planMoveStep",
+    "planSnapStep",
+    "planThetaSnapStep",
+    "planSpeed",
+    "refreshIntervalMs",
+    "playbackSpeed",
+    "robotImgScale",
+    "robotImgOffX",
+    "robotImgOffY",
+    "robotImgRot",
+    "robotImgAlpha",
+    "fieldRotation",
+    "layoutLeftSidebarWidth",
+    "layoutRightSidebarWidth",
+    "layoutTimelineHeight",
+    "layoutPlanningWaypointHeight",
+    "schemaVersion",
+];
+
+/// Unversioned files were written straight from the form inputs, so numbers
+/// and flags may be strings.
+fn migrate_settings_v0_to_v1(root: &mut Map<String, Value>) {
+    for key in NUMERIC_KEYS {
+        let parsed = match root.get(key) {
+            Some(Value::String(s)) => s.trim().parse::<f64>().ok(),
+            _ => continue,
+        };
+        if let Some(n) = parsed.and_then(serde_json::Number::from_f64) {
+            let n = match n.as_f64() {
+                Some(f) if f.fract() == 0.0 && (0.0..=u64::MAX as f64).contains(&f) => {
+                    Value::from(f as u64)
+                }
+                _ => Value::Number(n),
+            };
+            root.insert(key.to_string(), n);
+        }
+    }
+    for key in ["robotImageEnabled", "liveDebug"] {
+        if let Some(Value::String(s)) = root.get(key) {
+            let b = s.trim().eq_ignore_ascii_case("true");
+            root.insert(key.to_string(), Value::Bool(b));
+        }
+    }
+}
+
+type SettingsMigration = fn(&mut Map<String, Value>);
+
+const SETTINGS_MIGRATIONS: [SettingsMigration; CURRENT_SETTINGS_VERSION as usize] =
+    [migrate_settings_v0_to_v1];
+
+/// Pulls known keys out of the settings object one at a time, falling back to
+/// the default (and noting why) when a value is missing, mistyped or invalid.
+struct Fields {
+    obj: Map<String, Value>,
+    issues: Vec<String>,
+}
+
+impl Fields {
+    fn take<T: DeserializeOwned>(&mut self, key: &str, default: T) -> T {
+        match self.obj.remove(key) {
+            None | Some(Value::Null) => default,
+            Some(v) => match serde_json::from_value(v) {
+                Ok(t) => t,
+                Err(e) => {
+                    self.issues.push(format!("{key}: {e}; using default"));
+                    default
+                }
+            },
+        }
+    }
+
+    fn number(&mut self, key: &str, default: f64, expected: &str, ok: impl Fn(f64) -> bool) -> f64 {
+        let n = self.take(key, default);
+        if n.is_finite() && ok(n) {
+            n
+        } else {
+            self.issues.push(format!(
+                "{key}: expected {expected}, found {n}; using {default}"
+            ));
+            default
+        }
+    }
+}
+
+impl Settings {
+    /// Migrates and validates a parsed preferences document. Bad values are
+    /// replaced with defaults; the returned list says which and why.
+    pub fn from_value(v: Value) -> Result<(Settings, Vec<String>), String> {
+        let mut obj = match v {
+            Value::Object(obj) => obj,
+            other => return Err(format!("settings: expected object, found {other}")),
+        };
+        let mut issues = Vec::new();
+        let mut version = match obj.get("schemaVersion") {
+            None | Some(Value::Null) => 0,
+            Some(Value::String(s)) => s.trim().parse::<u64>().unwrap_or(0),
+            Some(v) => v.as_u64().unwrap_or(0),
+        };
+        if version > CURRENT_SETTINGS_VERSION {
+            issues.push(format!(
+                "schemaVersion: {version} is newer than this app supports ({CURRENT_SETTINGS_VERSION}); reading what it can"
+            ));
+        }
+        while version < CURRENT_SETTINGS_VERSION {
+            SETTINGS_MIGRATIONS[version as usize](&mut obj);
+            version += 1;
+        }
+        obj.remove("schemaVersion");
+
+        let d = Settings::default();
+        let mut f = Fields { obj, issues };
+        let mut s = Settings {
+            schema_version: CURRENT_SETTINGS_VERSION,
+            pros_dir: f.take("prosDir", d.pros_dir).trim().to_string(),
+            pros_exe: f.take("prosExe", d.pros_exe).trim().to_string(),
+            robot_image_enabled: f.take("robotImageEnabled", d.robot_image_enabled),
+            units: f.take("units", d.units.clone()),
+            robot_w: f.number("robotW", d.robot_w, "a positive size", |n| n > 0.0),
+            robot_h: f.number("robotH", d.robot_h, "a positive size", |n| n > 0.0),
+            off_x: f.number("offX", d.off_x, "a number", |_| true),
+            off_y: f.number("offY", d.off_y, "a number", |_| true),
+            off_theta: f.number("offTheta", d.off_theta, "an angle within ±359", |n| {
+                n.abs() <= MAX_OFFSET_THETA
+            }),
+            min_speed: f.number("minSpeed", d.min_speed, "a non-negative speed", |n| {
+                n >= 0.0
+            }),
+            max_speed: f.number("maxSpeed", d.max_speed, "a positive speed", |n| n > 0.0),
+            plan_move_step: f.number(
+                "planMoveStep",
+                d.plan_move_step,
+                "a non-negative step",
+                |n| n >= 0.0,
+            ),
+            plan_snap_step: f.number(
+                "planSnapStep",
+                d.plan_snap_step,
+                "a non-negative step",
+                |n| n >= 0.0,
+            ),
+            plan_theta_snap_step: f.number(
+                "planThetaSnapStep",
+                d.plan_theta_snap_step,
+                "a non-negative step",
+                |n| n >= 0.0,
+            ),
+            plan_speed: f.number("planSpeed", d.plan_speed, "a speed from 0 to 127", |n| {
+                (0.0..=MAX_MOTOR_SPEED).contains(&n)
+            }),
+            refresh_interval_ms: f.take("refreshIntervalMs", d.refresh_interval_ms),
+            live_debug: f.take("liveDebug", d.live_debug),
+            playback_speed: f.number("playbackSpeed", d.playback_speed, "a positive rate", |n| {
+                n > 0.0
+            }),
+            selected_field: f.take("selectedField", d.selected_field),
+            robot_img_scale: f.number(
+                "robotImgScale",
+                d.robot_img_scale,
+                "a positive scale",
+                |n| n > 0.0,
+            ),
+            robot_img_off_x: f.number("robotImgOffX", d.robot_img_off_x, "a number", |_| true),
+            robot_img_off_y: f.number("robotImgOffY", d.robot_img_off_y, "a number", |_| true),
+            robot_img_rot: f.number("robotImgRot", d.robot_img_rot, "a number", |_| true),
+            robot_img_alpha: f.number("robotImgAlpha", d.robot_img_alpha, "a percentage", |n| {
+                (0.0..=100.0).contains(&n)
+            }),
+            robot_image: f.take("robotImage", d.robot_image),
+            field_rotation: f.number(
+                "fieldRotation",
+                d.field_rotation,
+                "0, 90, 180 or 270",
+                |n| FIELD_ROTATIONS.contains(&n),
+            ),
+            layout_left_sidebar_width: f.number(
+                "layoutLeftSidebarWidth",
+                d.layout_left_sidebar_width,
+                "a non-negative size",
+                |n| n >= 0.0,
+            ),
+            layout_right_sidebar_width: f.number(
+                "layoutRightSidebarWidth",
+                d.layout_right_sidebar_width,
+                "a non-negative size",
+                |n| n >= 0.0,
+            ),
+            layout_timeline_height: f.number(
+                "layoutTimelineHeight",
+                d.layout_timeline_height,
+                "a non-negative size",
+                |n| n >= 0.0,
+            ),
+            layout_planning_waypoint_height: f.number(
+                "layoutPlanningWaypointHeight",
+                d.layout_planning_waypoint_height,
+                "a non-negative size",
+                |n| n >= 0.0,
+            ),
+            extra: Map::new(),
+        };
+        let Fields { obj, mut issues } = f;
+        s.extra = obj;
+
+        if !UNITS.contains(&s.units.as_str()) {
+            issues.push(format!(
+                "units: unknown unit {:?}; using {:?}",
+                s.units, d.units
+            ));
+            s.units = d.units;
+        }
+        if s.max_speed <= s.min_speed {
+            issues.push(format!(
+                "maxSpeed: {} is not above minSpeed {}; using {}..{}",
+                s.max_speed, s.min_speed, d.min_speed, d.max_speed
+            ));
+            s.min_speed = d.min_speed;
+            s.max_speed = d.max_speed;
+        }
+        // Kept as is: a USB drive or network share may just not be mounted
+        // right now. Commands that need the project report it when used.
+        if !s.pros_dir.is_empty() && !Path::new(&s.pros_dir).is_dir() {
+            issues.push(format!(
+                "prosDir: {:?} is not a directory right now",
+                s.pros_dir
+            ));
+        }
+        Ok((s, issues))
     }
+}
 
-    let legacy_path = legacy_settings_path(&app)?;
+fn parse_settings(contents: &str) -> Result<(Settings, Vec<String>), String> {
+    let v: Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    Settings::from_value(v)
+}
+
+fn report_settings_issues(issues: &[String]) {
+    for issue in issues {
+        eprintln!("SETTINGS WARNING: {issue}");
+    }
+}
+
+fn settings_intact(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(|s| parse_settings(s).is_ok())
+}
+
+fn write_settings_file(path: &Path, settings: &Settings) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    write_atomic(path, contents, settings_intact)
+}
+
+/// Reads `user-preferences.json` (or its backup) as validated settings.
+pub fn load_settings(app: &AppHandle) -> Result<Option<Settings>, String> {
+    let path = settings_path(app)?;
+    if let Some((settings, issues)) = read_with_backup(&path, parse_settings)? {
+        report_settings_issues(&issues);
+        return Ok(Some(settings));
+    }
+
+    let legacy_path = legacy_settings_path(app)?;
     if !legacy_path.exists() {
         return Ok(None);
     }
 
     let contents = std::fs::read_to_string(&legacy_path).map_err(|e| e.to_string())?;
+    let (settings, issues) = parse_settings(&contents)?;
+    report_settings_issues(&issues);
     // Best-effort migration to the new location.
-    if let Some(parent) = path.parent() {
-        if std::fs::create_dir_all(parent).is_ok() {
-            if std::fs::write(&path, &contents).is_ok() {
-                let _ = std::fs::remove_file(&legacy_path);
+    if write_settings_file(&path, &settings).is_ok() {
+        let _ = std::fs::remove_file(&legacy_path);
+    }
+    Ok(Some(settings))
+}
+
+#[tauri::command]
+pub fn read_settings(app: AppHandle) -> Result<Option<Settings>, String> {
+    load_settings(&app)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSettings {
+    pub settings: Settings,
+    /// Values that were replaced by defaults before saving.
+    pub issues: Vec<String>,
+}
+
+/// Validates and stores the settings, returning them as saved.
+#[tauri::command]
+pub fn write_settings(app: AppHandle, contents: String) -> Result<SavedSettings, String> {
+    let (settings, issues) = parse_settings(&contents)?;
+    report_settings_issues(&issues);
+    let path = settings_path(&app)?;
+    write_settings_file(&path, &settings)?;
+    Ok(SavedSettings { settings, issues })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// `profiles.json`. Each profile keeps its own preferences and robot image
+/// in `Profiles/<id>/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileIndex {
+    pub active: String,
+    pub profiles: Vec<ProfileInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveProfile {
+    pub id: String,
+    pub name: String,
+    pub settings: Settings,
+}
+
+/// Ids come back from `profiles.json`, which can be edited by hand, and are
+/// used as directory names.
+fn check_profile_id(id: &str) -> Result<(), String> {
+    let ok = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if ok {
+        Ok(())
+    } else {
+        Err(format!(
+            "profile id {id:?} may only use letters, digits, '-' and '_'"
+        ))
+    }
+}
+
+fn profile_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    check_profile_id(id)?;
+    Ok(data_dir(app)?.join(PROFILES_DIR).join(id))
+}
+
+fn profile_dir(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    let dir = profile_path(app, id)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn profile_index_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(data_dir(app)?.join(PROFILES_FILE))
+}
+
+fn write_profile_index(app: &AppHandle, index: &ProfileIndex) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+    write_atomic(
+        &profile_index_path(app)?,
+        contents,
+        parses_as::<ProfileIndex>,
+    )
+}
+
+/// Points `robotImage.path` at `to` if it referred to an image in `from`.
+/// Works on the raw JSON so nothing else in the file is touched.
+fn retarget_robot_image(settings_file: &Path, from: &Path, to: &Path) -> Result<(), String> {
+    let contents = match std::fs::read_to_string(settings_file) {
+        Ok(c) => c,
+        Err(_) => return Ok(()),
+    };
+    let mut v: Value = match serde_json::from_str(&contents) {
+        Ok(v) => v,
+        Err(_) => return Ok(()),
+    };
+    let image = match v.pointer_mut("/robotImage/path") {
+        Some(image) => image,
+        None => return Ok(()),
+    };
+    let old = match image.as_str().map(Path::new) {
+        Some(old) if old.parent() == Some(from) => old.to_path_buf(),
+        _ => return Ok(()),
+    };
+    if let Some(name) = old.file_name() {
+        *image = Value::String(to.join(name).to_string_lossy().to_string());
+        let contents = serde_json::to_string_pretty(&v).map_err(|e| e.to_string())?;
+        write_atomic(settings_file, contents, settings_intact)?;
+    }
+    Ok(())
+}
+
+/// First run with profiles: the existing single configuration becomes the
+/// default profile.
+fn adopt_single_profile(app: &AppHandle) -> Result<ProfileIndex, String> {
+    let root = data_dir(app)?;
+    let dir = profile_dir(app, DEFAULT_PROFILE_ID)?;
+    for entry in std::fs::read_dir(&root).map_err(|e| e.to_string())? {
+        let path = match entry {
+            Ok(e) => e.path(),
+            Err(_) => continue,
+        };
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_file()
+            && (name.starts_with(SETTINGS_FILE) || name.starts_with(ROBOT_IMAGE_FILE_BASE))
+        {
+            std::fs::rename(&path, dir.join(name)).map_err(|e| e.to_string())?;
+        }
+    }
+    retarget_robot_image(&dir.join(SETTINGS_FILE), &root, &dir)?;
+    let index = ProfileIndex {
+        active: DEFAULT_PROFILE_ID.to_string(),
+        profiles: vec![ProfileInfo {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "Default".to_string(),
+        }],
+    };
+    write_profile_index(app, &index)?;
+    Ok(index)
+}
+
+pub fn read_profile_index(app: &AppHandle) -> Result<ProfileIndex, String> {
+    let path = profile_index_path(app)?;
+    let parse = |s: &str| serde_json::from_str::<ProfileIndex>(s).map_err(|e| e.to_string());
+    let mut index = match read_with_backup(&path, parse)? {
+        Some(index) => index,
+        None => return adopt_single_profile(app),
+    };
+    if index.profiles.is_empty() {
+        index.profiles.push(ProfileInfo {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "Default".to_string(),
+        });
+    }
+    if !index.profiles.iter().any(|p| p.id == index.active) {
+        index.active = index.profiles[0].id.clone();
+    }
+    Ok(index)
+}
+
+fn find_profile<'a>(index: &'a ProfileIndex, id: &str) -> Result<&'a ProfileInfo, String> {
+    index
+        .profiles
+        .iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("no profile with id {id:?}"))
+}
+
+fn checked_profile_name(
+    index: &ProfileIndex,
+    name: &str,
+    except: Option<&str>,
+) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("profile name is empty".into());
+    }
+    let taken = index
+        .profiles
+        .iter()
+        .any(|p| Some(p.id.as_str()) != except && p.name.eq_ignore_ascii_case(name));
+    if taken {
+        return Err(format!("a profile named {name:?} already exists"));
+    }
+    Ok(name.to_string())
+}
+
+/// Directory-safe id derived from the name, unique among existing profiles.
+fn new_profile_id(app: &AppHandle, index: &ProfileIndex, name: &str) -> Result<String, String> {
+    let mut base = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            base.push(c);
+        } else if !base.is_empty() && !base.ends_with('-') {
+            base.push('-');
+        }
+    }
+    let base = match base.trim_end_matches('-') {
+        "" => "profile".to_string(),
+        b => b.to_string(),
+    };
+    let root = data_dir(app)?.join(PROFILES_DIR);
+    let mut id = base.clone();
+    let mut n = 2;
+    while index.profiles.iter().any(|p| p.id == id) || root.join(&id).exists() {
+        id = format!("{base}-{n}");
+        n += 1;
+    }
+    Ok(id)
+}
+
+fn active_profile(app: &AppHandle, index: &ProfileIndex) -> Result<ActiveProfile, String> {
+    let info = find_profile(index, &index.active)?;
+    Ok(ActiveProfile {
+        id: info.id.clone(),
+        name: info.name.clone(),
+        settings: load_settings(app)?.unwrap_or_default(),
+    })
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> Result<ProfileIndex, String> {
+    read_profile_index(&app)
+}
+
+#[tauri::command]
+pub fn get_active_profile(app: AppHandle) -> Result<ActiveProfile, String> {
+    let index = read_profile_index(&app)?;
+    active_profile(&app, &index)
+}
+
+/// Creates a profile with default settings. Does not switch to it.
+#[tauri::command]
+pub fn create_profile(app: AppHandle, name: String) -> Result<ProfileInfo, String> {
+    let mut index = read_profile_index(&app)?;
+    let name = checked_profile_name(&index, &name, None)?;
+    let id = new_profile_id(&app, &index, &name)?;
+    let dir = profile_dir(&app, &id)?;
+    write_settings_file(&dir.join(SETTINGS_FILE), &Settings::default())?;
+    let info = ProfileInfo { id, name };
+    index.profiles.push(info.clone());
+    write_profile_index(&app, &index)?;
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn rename_profile(app: AppHandle, id: String, name: String) -> Result<ProfileInfo, String> {
+    let mut index = read_profile_index(&app)?;
+    find_profile(&index, &id)?;
+    let name = checked_profile_name(&index, &name, Some(&id))?;
+    let info = index
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("no profile with id {id:?}"))?;
+    info.name = name;
+    let info = info.clone();
+    write_profile_index(&app, &index)?;
+    Ok(info)
+}
+
+/// Copies a profile's settings and robot image under a new name.
+#[tauri::command]
+pub fn duplicate_profile(app: AppHandle, id: String, name: String) -> Result<ProfileInfo, String> {
+    let mut index = read_profile_index(&app)?;
+    find_profile(&index, &id)?;
+    let name = checked_profile_name(&index, &name, None)?;
+    let new_id = new_profile_id(&app, &index, &name)?;
+    let from = profile_dir(&app, &id)?;
+    let to = profile_dir(&app, &new_id)?;
+    for entry in std::fs::read_dir(&from).map_err(|e| e.to_string())? {
+        let path = match entry {
+            Ok(e) => e.path(),
+            Err(_) => continue,
+        };
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if path.is_file() && ext != "bak" && ext != "tmp" {
+            if let Some(file) = path.file_name() {
+                std::fs::copy(&path, to.join(file)).map_err(|e| e.to_string())?;
             }
         }
     }
-    Ok(Some(contents))
+    retarget_robot_image(&to.join(SETTINGS_FILE), &from, &to)?;
+    let info = ProfileInfo { id: new_id, name };
+    index.profiles.push(info.clone());
+    write_profile_index(&app, &index)?;
+    Ok(info)
 }
 
+/// Deletes a profile and its files. The last profile can't be deleted; if the
+/// active one goes, the first remaining profile becomes active.
 #[tauri::command]
-pub fn write_settings(app: AppHandle, contents: String) -> Result<(), String> {
-    serde_json::from_str::<serde_json::Value>(&contents).map_err(|e| e.to_string())?;
-    let path = settings_path(&app)?;
-    std::fs::write(path, contents).map_err(|e| e.to_string())
+pub fn delete_profile(app: AppHandle, id: String) -> Result<ProfileIndex, String> {
+    let mut index = read_profile_index(&app)?;
+    find_profile(&index, &id)?;
+    if index.profiles.len() == 1 {
+        return Err("can't delete the only profile".into());
+    }
+    let dir = profile_path(&app, &id)?;
+    index.profiles.retain(|p| p.id != id);
+    if index.active == id {
+        index.active = index.profiles[0].id.clone();
+    }
+    write_profile_index(&app, &index)?;
+    if dir.exists() {
+        std::fs::remove_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    Ok(index)
+}
+
+/// Makes `id` the active profile and returns it with its settings, which the
+/// webview should apply in place of the current ones.
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, id: String) -> Result<ActiveProfile, String> {
+    let mut index = read_profile_index(&app)?;
+    find_profile(&index, &id)?;
+    index.active = id;
+    write_profile_index(&app, &index)?;
+    active_profile(&app, &index)
 }
 
 #[tauri::command]
 pub fn read_saved_paths(app: AppHandle) -> Result<Option<String>, String> {
     let path = saved_paths_path(&app)?;
-    if !path.exists() {
-        return Ok(None);
-    }
-    std::fs::read_to_string(path)
-        .map(Some)
-        .map_err(|e| e.to_string())
+    read_with_backup(&path, parse_json_text)
 }
 
 #[tauri::command]
 pub fn write_saved_paths(app: AppHandle, contents: String) -> Result<(), String> {
-    serde_json::from_str::<serde_json::Value>(&contents).map_err(|e| e.to_string())?;
+    let doc = serde_json::from_str::<serde_json::Value>(&contents).map_err(|e| e.to_string())?;
     let path = saved_paths_path(&app)?;
-    std::fs::write(path, contents).map_err(|e| e.to_string())
+    write_atomic(&path, contents, parses_as::<Value>)?;
+    crate::plan::record_saved_plan(&app, &doc);
+    Ok(())
 }
 
 fn mime_from_ext(path: &std::path::Path) -> &'static str {
@@ -116,7 +884,7 @@ fn mime_from_ext(path: &std::path::Path) -> &'static str {
     }
 }
 
-fn ext_from_mime(mime: &str) -> &'static str {
+pub(crate) fn ext_from_mime(mime: &str) -> &'static str {
     match mime {
         "image/png" => "png",
         "image/jpeg" => "jpg",
@@ -128,7 +896,7 @@ fn ext_from_mime(mime: &str) -> &'static str {
     }
 }
 
-fn parse_data_url(data_url: &str) -> Result<(String, Vec<u8>), String> {
+pub(crate) fn parse_data_url(data_url: &str) -> Result<(String, Vec<u8>), String> {
     let (meta, b64) = data_url
         .split_once(',')
         .ok_or_else(|| "invalid data URL".to_string())?;
@@ -166,14 +934,12 @@ pub fn read_image_data(path: String) -> Result<String, String> {
 pub fn save_robot_image(app: AppHandle, dataUrl: String) -> Result<String, String> {
     let (mime, bytes) = parse_data_url(&dataUrl)?;
     let ext = ext_from_mime(&mime);
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e: tauri::Error| e.to_string())?;
-    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let index = read_profile_index(&app)?;
+    let dir = profile_dir(&app, &index.active)?;
     let filename = format!("{ROBOT_IMAGE_FILE_BASE}.{ext}");
     let path = dir.join(filename);
-    std::fs::write(&path, bytes).map_err(|e| e.to_string())?;
+    // Any previous image is worth keeping; there is nothing to parse.
+    write_atomic(&path, bytes, |_| true)?;
     Ok(path.to_string_lossy().to_string())
 }
 
@@ -192,7 +958,7 @@ pub fn save_window_state(app: &AppHandle, window: &tauri::WebviewWindow) -> Resu
     });
     let path = window_state_path(app)?;
     let contents = serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?;
-    std::fs::write(path, contents).map_err(|e| e.to_string())
+    write_atomic(&path, contents, parses_as::<WindowState>)
 }
 
 #[cfg(not(mobile))]
@@ -210,10 +976,73 @@ pub struct WindowState {
 #[allow(dead_code)]
 pub fn read_window_state(app: &AppHandle) -> Result<Option<WindowState>, String> {
     let path = window_state_path(app)?;
-    if !path.exists() {
-        return Ok(None);
+    read_with_backup(&path, |s| {
+        serde_json::from_str::<WindowState>(s).map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Value, String> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn corrupt_primary_does_not_replace_good_backup() {
+        let dir = std::env::temp_dir().join(format!("mv-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("prefs.json");
+        let write = |s: &str| write_atomic(&path, s, parses_as::<Value>).unwrap();
+        write("{\"v\":1}");
+        write("{\"v\":2}");
+        std::fs::write(&path, "{\"v\":").unwrap();
+
+        // No read in between: the writer checks the primary itself.
+        write("{\"v\":3}");
+        let backup = std::fs::read_to_string(backup_path(&path)).unwrap();
+        assert_eq!(backup, "{\"v\":1}");
+        assert_eq!(
+            read_with_backup(&path, parse).unwrap(),
+            Some(serde_json::json!({"v": 3}))
+        );
+
+        // Once the primary is good again, saves rotate as usual.
+        write("{\"v\":4}");
+        let backup = std::fs::read_to_string(backup_path(&path)).unwrap();
+        assert_eq!(backup, "{\"v\":3}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_pros_dir_is_reported_but_kept() {
+        let dir = "/nonexistent/usb/team-1234";
+        let (s, issues) = Settings::from_value(serde_json::json!({ "prosDir": dir })).unwrap();
+        assert_eq!(s.pros_dir, dir);
+        assert!(
+            issues.iter().any(|i| i.starts_with("prosDir:")),
+            "{issues:?}"
+        );
+    }
+
+    #[test]
+    fn profile_ids_stay_inside_the_profiles_dir() {
+        for id in ["default", "match-bot_2", "A1"] {
+            assert!(check_profile_id(id).is_ok(), "{id}");
+        }
+        for id in [
+            "",
+            ".",
+            "..",
+            "../../etc",
+            ".hidden",
+            "a/b",
+            "a\\b",
+            "C:",
+            "skills bot",
+        ] {
+            assert!(check_profile_id(id).is_err(), "{id}");
+        }
     }
-    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let state: WindowState = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
-    Ok(Some(state))
 }
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+
+const DATA_TAG: &str = "[DATA]";
+const WATCH_TAG: &str = "[WATCH]";
+// [DATA],millis,x,y,theta,l_vel,r_vel
+const DATA_FIELDS: usize = 7;
+// [WATCH],millis,level,label,value (value may contain commas)
+const WATCH_FIELDS: usize = 5;
+
+/// One `[DATA]` sample as written by `Logger::Update`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pose {
+    pub t: u64,
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+    pub l_vel: Option<f64>,
+    pub r_vel: Option<f64>,
+    pub speed: f64,
+}
+
+impl Pose {
+    pub fn new(t: u64, x: f64, y: f64, theta: f64, l_vel: Option<f64>, r_vel: Option<f64>) -> Self {
+        let speed = wheel_speed(l_vel, r_vel);
+        Pose {
+            t,
+            x,
+            y,
+            theta,
+            l_vel,
+            r_vel,
+            speed,
+        }
+    }
+}
+
+/// One `[WATCH]` sample as written by `Logger::printWatches`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watch {
+    #[serde(rename = "time", alias = "t")]
+    pub t: u64,
+    pub level: String,
+    pub label: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Sample {
+    Pose(Pose),
+    Watch(Watch),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelemetryError {
+    FieldCount {
+        tag: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    NotANumber {
+        field: &'static str,
+        value: String,
+    },
+    OutOfOrder {
+        previous: u64,
+        t: u64,
+    },
+}
+
+impl std::fmt::Display for TelemetryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TelemetryError::FieldCount {
+                tag,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{tag} expected at least {expected} fields, found {found}"
+            ),
+            TelemetryError::NotANumber { field, value } => {
+                write!(f, "{field}: expected number, found {value:?}")
+            }
+            TelemetryError::OutOfOrder { previous, t } => {
+                write!(
+                    f,
+                    "timestamp {t} is not after previous sample at {previous}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TelemetryError {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LineError {
+    pub line: usize,
+    pub text: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParsedTelemetry {
+    pub poses: Vec<Pose>,
+    pub watches: Vec<Watch>,
+    pub errors: Vec<LineError>,
+    /// Lines that carried no `[DATA]` or `[WATCH]` tag (plain log output).
+    pub ignored: usize,
+}
+
+pub fn wheel_speed(l_vel: Option<f64>, r_vel: Option<f64>) -> f64 {
+    let lv = l_vel.filter(|v| v.is_finite()).unwrap_or(0.0);
+    let rv = r_vel.filter(|v| v.is_finite()).unwrap_or(0.0);
+    (lv.abs() + rv.abs()) / 2.0
+}
+
+/// Drops everything before the first `[DATA]`/`[WATCH]` tag, so library
+/// prefixed lines like `[28.08] [INFO]: [DATA],...` parse the same as bare ones.
+pub fn strip_log_prefix(line: &str) -> Option<&str> {
+    let i_data = line.find(DATA_TAG);
+    let i_watch = line.find(WATCH_TAG);
+    let i = match (i_data, i_watch) {
+        (Some(a), Some(b)) => a.min(b),
+        (Some(a), None) => a,
+        (None, Some(b)) => b,
+        (None, None) => return None,
+    };
+    Some(line[i..].trim())
+}
+
+fn parse_num(field: &'static str, raw: &str) -> Result<f64, TelemetryError> {
+    raw.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| TelemetryError::NotANumber {
+            field,
+            value: raw.trim().to_string(),
+        })
+}
+
+/// MVLib prints millis with `%d`, and run files reject fractional `t`, so
+/// anything but a non-negative integer is an error rather than rounded.
+fn parse_millis(raw: &str) -> Result<u64, TelemetryError> {
+    raw.trim()
+        .parse::<u64>()
+        .map_err(|_| TelemetryError::NotANumber {
+            field: "millis",
+            value: raw.trim().to_string(),
+        })
+}
+
+fn parse_data(s: &str) -> Result<Pose, TelemetryError> {
+    let parts: Vec<&str> = s.split(',').collect();
+    if parts.len() < DATA_FIELDS {
+        return Err(TelemetryError::FieldCount {
+            tag: DATA_TAG,
+            expected: DATA_FIELDS,
+            found: parts.len(),
+        });
+    }
+    let t = parse_millis(parts[1])?;
+    let x = parse_num("x", parts[2])?;
+    let y = parse_num("y", parts[3])?;
+    let theta = parse_num("theta", parts[4])?;
+    let l_vel = parse_num("l_vel", parts[5])?;
+    let r_vel = parse_num("r_vel", parts[6])?;
+    Ok(Pose::new(t, x, y, theta, Some(l_vel), Some(r_vel)))
+}
+
+fn parse_watch(s: &str) -> Result<Watch, TelemetryError> {
+    let parts: Vec<&str> = s.split(',').collect();
+    if parts.len() < WATCH_FIELDS {
+        return Err(TelemetryError::FieldCount {
+            tag: WATCH_TAG,
+            expected: WATCH_FIELDS,
+            found: parts.len(),
+        });
+    }
+    let t = parse_millis(parts[1])?;
+    let level = match parts[2].trim() {
+        "" => "INFO".to_string(),
+        lvl => lvl.to_string(),
+    };
+    // Labels are registered as "Name:" on the robot side; the colon is noise here.
+    let label = parts[3].replace(':', "").trim().to_string();
+    let value = parts[4..].join(",");
+    Ok(Watch {
+        t,
+        level,
+        label,
+        value,
+    })
+}
+
+/// Parses a single line. Returns `Ok(None)` for lines without a telemetry tag.
+pub fn parse_line(line: &str) -> Result<Option<Sample>, TelemetryError> {
+    let s = match strip_log_prefix(line) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    if s.starts_with("[DATA],") || s == DATA_TAG {
+        return parse_data(s).map(|p| Some(Sample::Pose(p)));
+    }
+    if s.starts_with("[WATCH],") || s == WATCH_TAG {
+        return parse_watch(s).map(|w| Some(Sample::Watch(w)));
+    }
+    Ok(None)
+}
+
+/// Incremental parser that keeps the monotonic-timestamp guard between calls,
+/// so it can be fed a live stream one line at a time.
+#[derive(Debug, Default)]
+pub struct TelemetryParser {
+    last_pose_t: Option<u64>,
+    line_no: usize,
+    out: ParsedTelemetry,
+}
+
+impl TelemetryParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        self.line_no += 1;
+        let line_no = self.line_no;
+        match parse_line(line) {
+            Ok(Some(Sample::Pose(p))) => {
+                if let Some(prev) = self.last_pose_t {
+                    if p.t <= prev {
+                        self.out.errors.push(LineError {
+                            line: line_no,
+                            text: line.to_string(),
+                            message: TelemetryError::OutOfOrder {
+                                previous: prev,
+                                t: p.t,
+                            }
+                            .to_string(),
+                        });
+                        return;
+                    }
+                }
+                self.last_pose_t = Some(p.t);
+                self.out.poses.push(p);
+            }
+            Ok(Some(Sample::Watch(w))) => self.out.watches.push(w),
+            Ok(None) => self.out.ignored += 1,
+            Err(e) => self.out.errors.push(LineError {
+                line: line_no,
+                text: line.to_string(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    pub fn finish(mut self) -> ParsedTelemetry {
+        // Watches can be printed ahead of the pose that shares their tick.
+        self.out.watches.sort_by_key(|w| w.t);
+        self.out
+    }
+}
+
+pub fn parse_text(text: &str) -> ParsedTelemetry {
+    let mut parser = TelemetryParser::new();
+    for line in text.lines() {
+        parser.push_line(line);
+    }
+    parser.finish()
+}
+
+#[tauri::command]
+pub fn parse_telemetry_line(line: String) -> Result<Option<Sample>, String> {
+    parse_line(&line).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn parse_telemetry(text: String) -> ParsedTelemetry {
+    parse_text(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pose(line: &str) -> Pose {
+        match parse_line(line) {
+            Ok(Some(Sample::Pose(p))) => p,
+            other => panic!("expected a pose from {line:?}, got {other:?}"),
+        }
+    }
+
+    fn watch(line: &str) -> Watch {
+        match parse_line(line) {
+            Ok(Some(Sample::Watch(w))) => w,
+            other => panic!("expected a watch from {line:?}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_bare_data_line() {
+        let p = pose("[DATA],12723,-24.50,36.12,271.30,-63.5,63.5");
+        assert_eq!(p.t, 12723);
+        assert_eq!((p.x, p.y, p.theta), (-24.5, 36.12, 271.3));
+        assert_eq!((p.l_vel, p.r_vel), (Some(-63.5), Some(63.5)));
+        assert_eq!(p.speed, 63.5);
+    }
+
+    #[test]
+    fn parses_library_prefixed_data_line() {
+        let bare = pose("[DATA],28080,1.00,2.00,90.00,10.0,12.0");
+        let prefixed = pose("[28.08] [INFO]: [DATA],28080,1.00,2.00,90.00,10.0,12.0");
+        assert_eq!(bare, prefixed);
+    }
+
+    #[test]
+    fn watch_value_keeps_commas() {
+        let w = watch("[31.50] [WARN]: [WATCH],31500,WARN,Pose:,(12.00, -3.50, 90.00)");
+        assert_eq!(w.t, 31500);
+        assert_eq!(w.level, "WARN");
+        assert_eq!(w.label, "Pose");
+        assert_eq!(w.value, "(12.00, -3.50, 90.00)");
+    }
+
+    #[test]
+    fn short_lines_report_field_count() {
+        assert_eq!(
+            parse_line("[DATA],100,1.0,2.0"),
+            Err(TelemetryError::FieldCount {
+                tag: DATA_TAG,
+                expected: DATA_FIELDS,
+                found: 4,
+            })
+        );
+        assert!(matches!(
+            parse_line("[WATCH],100,INFO"),
+            Err(TelemetryError::FieldCount { found: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_nan_and_non_numeric_fields() {
+        assert!(matches!(
+            parse_line("[DATA],100,nan,2.0,3.0,0.0,0.0"),
+            Err(TelemetryError::NotANumber { field: "x", .. })
+        ));
+        assert!(matches!(
+            parse_line("[DATA],100,1.0,2.0,inf,0.0,0.0"),
+            Err(TelemetryError::NotANumber { field: "theta", .. })
+        ));
+        assert!(matches!(
+            parse_line("[DATA],abc,1.0,2.0,3.0,0.0,0.0"),
+            Err(TelemetryError::NotANumber {
+                field: "millis",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_line("[DATA],-5,1.0,2.0,3.0,0.0,0.0"),
+            Err(TelemetryError::NotANumber {
+                field: "millis",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_line("[WATCH],12723.0,INFO,Intake:,on"),
+            Err(TelemetryError::NotANumber {
+                field: "millis",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn ignores_untagged_lines() {
+        assert_eq!(parse_line("[1.00] [INFO]: Auton selected"), Ok(None));
+        assert_eq!(parse_line(""), Ok(None));
+    }
+
+    #[test]
+    fn push_line_drops_out_of_order_poses() {
+        let mut parser = TelemetryParser::new();
+        parser.push_line("[DATA],100,0.00,0.00,0.00,0.0,0.0");
+        parser.push_line("[DATA],90,1.00,0.00,0.00,0.0,0.0");
+        parser.push_line("[DATA],100,2.00,0.00,0.00,0.0,0.0");
+        parser.push_line("[WATCH],95,INFO,Intake:,on");
+        parser.push_line("[DATA],110,3.00,0.00,0.00,0.0,0.0");
+        let out = parser.finish();
+
+        let times: Vec<u64> = out.poses.iter().map(|p| p.t).collect();
+        assert_eq!(times, [100, 110]);
+        assert_eq!(out.watches.len(), 1);
+        let lines: Vec<usize> = out.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [2, 3]);
+        assert!(out.errors[0].message.contains("not after previous sample"));
+    }
+
+    #[test]
+    fn parse_text_sorts_watches_and_counts_ignored() {
+        let out = parse_text(
+            "PROS terminal\n\
+             [WATCH],20,INFO,B:,2\n\
+             [DATA],10,0.00,0.00,0.00,0.0,0.0\n\
+             [WATCH],10,,A:,1\n",
+        );
+        assert_eq!(out.ignored, 1);
+        assert_eq!(out.poses.len(), 1);
+        let labels: Vec<&str> = out.watches.iter().map(|w| w.label.as_str()).collect();
+        assert_eq!(labels, ["A", "B"]);
+        assert_eq!(out.watches[0].level, "INFO");
+    }
+}
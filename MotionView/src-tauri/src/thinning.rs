@@ -0,0 +1,227 @@
+use serde::Deserialize;
+
+use crate::run::{self, Run, Thinning};
+use crate::telemetry::{Pose, Watch};
+
+/// Must match `WATCH_TOL_MS` in the viewer, which decides which pose a watch
+/// marker snaps to.
+pub const WATCH_TOL_MS: u64 = 60;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThinningOptions {
+    /// Poses closer than this (in run units) to the last kept pose are redundant.
+    pub xy_tol: f64,
+    /// Heading change in degrees that always keeps a pose.
+    pub theta_tol: f64,
+    /// Longest stretch of time, in ms, allowed between kept poses so the
+    /// timeline keeps its resolution while the robot sits still.
+    pub viewer_thin_ms: u64,
+}
+
+impl Default for ThinningOptions {
+    fn default() -> Self {
+        ThinningOptions {
+            xy_tol: 1.5,
+            theta_tol: 35.0,
+            viewer_thin_ms: 250,
+        }
+    }
+}
+
+fn heading_delta(a: f64, b: f64) -> f64 {
+    let d = (b - a).rem_euclid(360.0);
+    d.min(360.0 - d)
+}
+
+/// Indices of poses a watch attaches to. A watch with no pose inside the
+/// tolerance is drawn by interpolation, so both neighbours are pinned instead.
+fn pinned_indices(poses: &[Pose], watches: &[Watch]) -> Vec<bool> {
+    let mut pinned = vec![false; poses.len()];
+    if poses.is_empty() {
+        return pinned;
+    }
+    for w in watches {
+        let i = poses.partition_point(|p| p.t < w.t);
+        let before = i.checked_sub(1);
+        let after = (i < poses.len()).then_some(i);
+        let nearest = match (before, after) {
+            (Some(b), Some(a)) => {
+                if w.t - poses[b].t <= poses[a].t - w.t {
+                    b
+                } else {
+                    a
+                }
+            }
+            (Some(b), None) => b,
+            (None, Some(a)) => a,
+            (None, None) => continue,
+        };
+        if poses[nearest].t.abs_diff(w.t) <= WATCH_TOL_MS {
+            pinned[nearest] = true;
+        } else {
+            for j in [before, after].into_iter().flatten() {
+                pinned[j] = true;
+            }
+        }
+    }
+    pinned
+}
+
+/// Returns the indices of the poses to keep. The first and last poses always
+/// survive so the run keeps its start, end and duration.
+pub fn select(poses: &[Pose], watches: &[Watch], opts: &ThinningOptions) -> Vec<usize> {
+    if poses.len() <= 2 {
+        return (0..poses.len()).collect();
+    }
+    let pinned = pinned_indices(poses, watches);
+    let last = poses.len() - 1;
+    let mut kept = vec![0];
+    for i in 1..poses.len() {
+        let anchor = &poses[kept[kept.len() - 1]];
+        let p = &poses[i];
+        let moved = (p.x - anchor.x).hypot(p.y - anchor.y) > opts.xy_tol;
+        let turned = heading_delta(anchor.theta, p.theta) > opts.theta_tol;
+        let stale = p.t.saturating_sub(anchor.t) >= opts.viewer_thin_ms;
+        if i == last || pinned[i] || moved || turned || stale {
+            kept.push(i);
+        }
+    }
+    kept
+}
+
+/// Thins `run` in place and records accurate stats. Re-thinning an already
+/// thinned run keeps the original `raw` count.
+pub fn thin(run: &mut Run, opts: &ThinningOptions) {
+    let raw = run
+        .thinning
+        .as_ref()
+        .map(|t| t.raw)
+        .unwrap_or(run.poses.len());
+    let mut keep = vec![false; run.poses.len()];
+    for i in select(&run.poses, &run.watches, opts) {
+        keep[i] = true;
+    }
+    let mut i = 0;
+    run.poses.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+    let kept = run.poses.len();
+    // Events point at poses by index; move them onto the poses that are left.
+    for e in &mut run.events {
+        e.start_index = run
+            .poses
+            .partition_point(|p| p.t < e.start_ms)
+            .min(kept.saturating_sub(1));
+        e.end_index = run
+            .poses
+            .partition_point(|p| p.t <= e.end_ms)
+            .saturating_sub(1)
+            .max(e.start_index);
+    }
+    run.thinning = Some(Thinning {
+        raw,
+        kept,
+        removed: raw.saturating_sub(kept),
+        viewer_thin_ms: opts.viewer_thin_ms,
+        xy_tol: opts.xy_tol,
+        theta_tol: opts.theta_tol,
+    });
+}
+
+#[tauri::command]
+pub fn thin_run(mut run: Run, options: Option<ThinningOptions>) -> Run {
+    thin(&mut run, &options.unwrap_or_default());
+    run
+}
+
+/// Thins a run file on disk, writing to `out_path` (or back over `path`) in
+/// the input's format. Overwritten files are kept as `<name>.bak`.
+#[tauri::command]
+pub fn thin_run_file(
+    path: String,
+    out_path: Option<String>,
+    options: Option<ThinningOptions>,
+) -> Result<Thinning, String> {
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let compact = crate::compact::is_compact(&bytes);
+    let mut run = if compact {
+        crate::compact::decode(&bytes)?
+    } else {
+        let contents = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+        run::from_str(&contents).map_err(|e| e.to_string())?
+    };
+    thin(&mut run, &options.unwrap_or_default());
+    let contents = if compact {
+        crate::compact::encode_checked(&run)?
+    } else {
+        serde_json::to_vec(&run).map_err(|e| e.to_string())?
+    };
+    let dest = std::path::PathBuf::from(out_path.unwrap_or(path));
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let intact = |b: &[u8]| {
+        if crate::compact::is_compact(b) {
+            crate::compact::decode(b).is_ok()
+        } else {
+            std::str::from_utf8(b).is_ok_and(|s| run::from_str(s).is_ok())
+        }
+    };
+    crate::settings::write_atomic(&dest, contents, intact)?;
+    run.thinning
+        .ok_or_else(|| "thinning stats missing".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::RunMeta;
+
+    fn still_run(n: u64) -> Run {
+        let poses = (0..n)
+            .map(|i| Pose::new(i * 10, 1.0, 2.0, 90.0, None, None))
+            .collect();
+        Run::new(RunMeta::default(), poses, Vec::new())
+    }
+
+    #[test]
+    fn thin_run_file_keeps_compact_format_and_backup() {
+        let dir = std::env::temp_dir().join(format!("mv-thin-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run.mvrun");
+        let original = crate::compact::encode(&still_run(100)).unwrap();
+        std::fs::write(&path, &original).unwrap();
+
+        let stats = thin_run_file(path.to_string_lossy().into(), None, None).unwrap();
+        assert_eq!((stats.raw, stats.kept), (100, 5));
+        let written = std::fs::read(&path).unwrap();
+        assert!(crate::compact::is_compact(&written));
+        assert_eq!(crate::compact::decode(&written).unwrap().poses.len(), 5);
+        assert_eq!(
+            std::fs::read(crate::settings::backup_path(&path)).unwrap(),
+            original
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn thin_run_file_keeps_json_format() {
+        let dir = std::env::temp_dir().join(format!("mv-thin-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, out) = (dir.join("run.json"), dir.join("out/thin.json"));
+        std::fs::write(&path, serde_json::to_string(&still_run(10)).unwrap()).unwrap();
+
+        thin_run_file(
+            path.to_string_lossy().into(),
+            Some(out.to_string_lossy().into()),
+            None,
+        )
+        .unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(run::from_str(&text).unwrap().poses.len(), 2);
+        assert_eq!(run::read_run_file(&path).unwrap().poses.len(), 10);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,513 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::plan::{angle_delta, read_saved_plan, Waypoint};
+use crate::settings;
+use crate::telemetry::Pose;
+
+/// Full scale of the `l_vel`/`r_vel` values MVLib logs.
+const WHEEL_SCALE: f64 = 127.0;
+const MIN_MOVE: f64 = 1e-6;
+const BISECT_STEPS: usize = 64;
+/// Most poses a profile may produce.
+const MAX_SAMPLES: f64 = 1_000_000.0;
+
+/// Lengths in inches, angles in degrees, time in seconds unless noted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProfileOptions {
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+    /// `None` gives plain trapezoidal profiles.
+    pub max_jerk: Option<f64>,
+    /// Defaults to the robot width from settings.
+    pub track_width: Option<f64>,
+    /// Wheel surface speed that MVLib logs as 127.
+    pub max_wheel_velocity: f64,
+    /// Drive backwards to a waypoint when that needs less turning.
+    pub allow_reverse: bool,
+    pub sample_ms: u64,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        ProfileOptions {
+            max_velocity: 60.0,
+            max_acceleration: 120.0,
+            max_jerk: Some(600.0),
+            track_width: None,
+            max_wheel_velocity: 75.0,
+            allow_reverse: true,
+            sample_ms: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveKind {
+    Turn,
+    Drive,
+}
+
+/// One leg of the trajectory. `amount` is inches for drives (negative when
+/// reversing) and degrees for turns (positive is clockwise).
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfiledMove {
+    pub kind: MoveKind,
+    /// Index of the waypoint this leg ends at.
+    pub waypoint: usize,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trajectory {
+    /// Same shape as recorded run poses, starting at t = 0.
+    pub poses: Vec<Pose>,
+    pub moves: Vec<ProfiledMove>,
+    pub duration_ms: u64,
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    velocity: f64,
+    acceleration: f64,
+    jerk: Option<f64>,
+}
+
+/// A rest-to-rest move as seven constant-jerk phases: jerk up, hold
+/// acceleration, jerk down, cruise, then the mirror image to stop.
+#[derive(Debug, Clone)]
+struct SCurve {
+    distance: f64,
+    /// (duration, acceleration at phase start, jerk)
+    phases: [(f64, f64, f64); 7],
+}
+
+impl SCurve {
+    fn new(distance: f64, limits: Limits) -> Self {
+        let Limits {
+            velocity,
+            acceleration,
+            jerk,
+        } = limits;
+        // (jerk time, constant-acceleration time, peak acceleration) to get
+        // from rest to `v`.
+        let ramp = |v: f64| match jerk {
+            Some(j) if v * j < acceleration * acceleration => ((v / j).sqrt(), 0.0, (v * j).sqrt()),
+            Some(j) => (
+                acceleration / j,
+                v / acceleration - acceleration / j,
+                acceleration,
+            ),
+            None => (0.0, v / acceleration, acceleration),
+        };
+        // The ramp is symmetric, so the average speed over it is v / 2.
+        let ramp_distance = |v: f64| {
+            let (tj, ta, _) = ramp(v);
+            v * (2.0 * tj + ta) / 2.0
+        };
+
+        let mut peak = velocity;
+        if 2.0 * ramp_distance(peak) > distance {
+            let (mut lo, mut hi) = (0.0, velocity);
+            for _ in 0..BISECT_STEPS {
+                let mid = (lo + hi) / 2.0;
+                if 2.0 * ramp_distance(mid) > distance {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            peak = lo;
+        }
+        let cruise = if peak > 0.0 {
+            ((distance - 2.0 * ramp_distance(peak)) / peak).max(0.0)
+        } else {
+            0.0
+        };
+        let (tj, ta, a) = ramp(peak);
+        let j = jerk.unwrap_or(0.0);
+        SCurve {
+            distance,
+            phases: [
+                (tj, 0.0, j),
+                (ta, a, 0.0),
+                (tj, a, -j),
+                (cruise, 0.0, 0.0),
+                (tj, 0.0, -j),
+                (ta, -a, 0.0),
+                (tj, -a, j),
+            ],
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.phases.iter().map(|p| p.0).sum()
+    }
+
+    /// Distance travelled and speed `t` seconds in.
+    fn at(&self, t: f64) -> (f64, f64) {
+        if t >= self.duration() {
+            return (self.distance, 0.0);
+        }
+        let (mut s, mut v, mut rest) = (0.0, 0.0, t.max(0.0));
+        for &(duration, a, j) in &self.phases {
+            let dt = rest.min(duration);
+            s += v * dt + a * dt * dt / 2.0 + j * dt * dt * dt / 6.0;
+            v += a * dt + j * dt * dt / 2.0;
+            rest -= dt;
+            if rest <= 0.0 {
+                break;
+            }
+        }
+        (s.clamp(0.0, self.distance), v.max(0.0))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Leg {
+    Turn {
+        x: f64,
+        y: f64,
+        from: f64,
+        sign: f64,
+    },
+    Drive {
+        from: Waypoint,
+        heading: f64,
+        dir: f64,
+        ux: f64,
+        uy: f64,
+    },
+}
+
+fn check_options(opts: &ProfileOptions, track_width: f64) -> Result<(), String> {
+    let positive = [
+        ("max_velocity", opts.max_velocity),
+        ("max_acceleration", opts.max_acceleration),
+        ("max_wheel_velocity", opts.max_wheel_velocity),
+        ("track_width", track_width),
+        ("max_jerk", opts.max_jerk.unwrap_or(1.0)),
+    ];
+    for (name, value) in positive {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(format!("{name} must be a positive number"));
+        }
+    }
+    if opts.sample_ms == 0 {
+        return Err("sample_ms must be at least 1".into());
+    }
+    Ok(())
+}
+
+fn compass_heading(dx: f64, dy: f64) -> f64 {
+    dx.atan2(dy).to_degrees().rem_euclid(360.0)
+}
+
+fn wheel_value(v: f64, max_wheel_velocity: f64) -> f64 {
+    (v / max_wheel_velocity * WHEEL_SCALE).clamp(-WHEEL_SCALE, WHEEL_SCALE)
+}
+
+/// A turn in place from `from` to `to`, or nothing if they already match.
+fn turn_leg(
+    x: f64,
+    y: f64,
+    from: f64,
+    to: f64,
+    waypoint: usize,
+    limits: Limits,
+) -> Option<(Leg, SCurve, ProfiledMove)> {
+    let delta = angle_delta(from, to);
+    if delta.abs() <= MIN_MOVE {
+        return None;
+    }
+    let leg = Leg::Turn {
+        x,
+        y,
+        from,
+        sign: delta.signum(),
+    };
+    let curve = SCurve::new(delta.abs(), limits);
+    Some((leg, curve, move_of(MoveKind::Turn, waypoint, delta)))
+}
+
+/// Time-parameterizes a plan for a differential drive. The robot starts at
+/// rest at the first waypoint facing its theta, turns in place towards each
+/// following waypoint, drives straight to it and stops, and finally turns to
+/// the last waypoint's theta. Headings of intermediate waypoints only matter
+/// for picking the drive direction.
+///
+/// Drives are limited by `max_velocity` and the wheel speed; turns by the
+/// wheel speed. Acceleration and jerk limits apply to the chassis when
+/// driving and to each wheel when turning.
+pub fn profile(
+    waypoints: &[Waypoint],
+    opts: &ProfileOptions,
+    track_width: f64,
+) -> Result<Trajectory, String> {
+    check_options(opts, track_width)?;
+    let first = *waypoints.first().ok_or("plan has no waypoints")?;
+
+    let half_track = track_width / 2.0;
+    let drive_limits = Limits {
+        velocity: opts.max_velocity.min(opts.max_wheel_velocity),
+        acceleration: opts.max_acceleration,
+        jerk: opts.max_jerk,
+    };
+    // Wheel limits turned into degrees of heading.
+    let per_wheel = |v: f64| (v / half_track).to_degrees();
+    let turn_limits = Limits {
+        velocity: per_wheel(opts.max_wheel_velocity),
+        acceleration: per_wheel(opts.max_acceleration),
+        jerk: opts.max_jerk.map(per_wheel),
+    };
+
+    let mut legs: Vec<(Leg, SCurve, ProfiledMove)> = Vec::new();
+    let mut heading = first.theta;
+    let mut distance = 0.0;
+    for (i, pair) in waypoints.windows(2).enumerate() {
+        let (a, b) = (pair[0], pair[1]);
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx.hypot(dy);
+        if length <= MIN_MOVE {
+            continue;
+        }
+        let forward = compass_heading(dx, dy);
+        let reverse = opts.allow_reverse && angle_delta(heading, forward).abs() > 90.0;
+        let (drive_heading, dir) = if reverse {
+            ((forward + 180.0).rem_euclid(360.0), -1.0)
+        } else {
+            (forward, 1.0)
+        };
+        legs.extend(turn_leg(
+            a.x,
+            a.y,
+            heading,
+            drive_heading,
+            i + 1,
+            turn_limits,
+        ));
+        let leg = Leg::Drive {
+            from: a,
+            heading: drive_heading,
+            dir,
+            ux: dx / length,
+            uy: dy / length,
+        };
+        let curve = SCurve::new(length, drive_limits);
+        legs.push((leg, curve, move_of(MoveKind::Drive, i + 1, dir * length)));
+        heading = drive_heading;
+        distance += length;
+    }
+    let last = waypoints[waypoints.len() - 1];
+    let end = waypoints.len() - 1;
+    legs.extend(turn_leg(
+        last.x,
+        last.y,
+        heading,
+        last.theta,
+        end,
+        turn_limits,
+    ));
+
+    let sample = |leg: &Leg, curve: &SCurve, t: f64, ms: u64| -> Pose {
+        let (s, v) = curve.at(t);
+        match *leg {
+            Leg::Turn { x, y, from, sign } => {
+                let wheel = sign * v.to_radians() * half_track;
+                let theta = (from + sign * s).rem_euclid(360.0);
+                let l = wheel_value(wheel, opts.max_wheel_velocity);
+                let r = wheel_value(-wheel, opts.max_wheel_velocity);
+                Pose::new(ms, x, y, theta, Some(l), Some(r))
+            }
+            Leg::Drive {
+                from,
+                heading,
+                dir,
+                ux,
+                uy,
+            } => {
+                let wheel = wheel_value(dir * v, opts.max_wheel_velocity);
+                let (x, y) = (from.x + ux * s, from.y + uy * s);
+                Pose::new(ms, x, y, heading, Some(wheel), Some(wheel))
+            }
+        }
+    };
+
+    let step = opts.sample_ms as f64 / 1000.0;
+    let total: f64 = legs.iter().map(|(_, curve, _)| curve.duration()).sum();
+    if total / step > MAX_SAMPLES {
+        return Err(format!(
+            "the profile would take {total:.0} s, too long to sample every {} ms; raise max_velocity or sample_ms",
+            opts.sample_ms
+        ));
+    }
+    let mut poses = vec![Pose::new(
+        0,
+        first.x,
+        first.y,
+        first.theta.rem_euclid(360.0),
+        Some(0.0),
+        Some(0.0),
+    )];
+    let mut moves = Vec::with_capacity(legs.len());
+    let mut start = 0.0;
+    let mut next = 1u64;
+    for (leg, curve, info) in &legs {
+        let end = start + curve.duration();
+        while (next as f64) * step < end {
+            let ms = next * opts.sample_ms;
+            poses.push(sample(leg, curve, ms as f64 / 1000.0 - start, ms));
+            next += 1;
+        }
+        moves.push(ProfiledMove {
+            start_ms: (start * 1000.0).round() as u64,
+            end_ms: (end * 1000.0).round() as u64,
+            ..info.clone()
+        });
+        start = end;
+    }
+    // Always finish with a sample at rest at the very end.
+    if let (Some((leg, curve, _)), Some(m)) = (legs.last(), moves.last()) {
+        if poses.last().is_some_and(|p| p.t < m.end_ms) {
+            poses.push(sample(leg, curve, curve.duration(), m.end_ms));
+        }
+    }
+
+    Ok(Trajectory {
+        duration_ms: poses.last().map_or(0, |p| p.t),
+        poses,
+        moves,
+        distance,
+    })
+}
+
+fn move_of(kind: MoveKind, waypoint: usize, amount: f64) -> ProfiledMove {
+    ProfiledMove {
+        kind,
+        waypoint,
+        start_ms: 0,
+        end_ms: 0,
+        amount,
+    }
+}
+
+/// Profiles `waypoints`, or the saved plan when none are given.
+#[tauri::command]
+pub fn profile_plan(
+    app: AppHandle,
+    options: ProfileOptions,
+    waypoints: Option<Vec<Waypoint>>,
+) -> Result<Trajectory, String> {
+    let waypoints = match waypoints {
+        Some(w) => w,
+        None => read_saved_plan(&app)?,
+    };
+    let track_width = match options.track_width {
+        Some(w) => w,
+        None => settings::load_settings(&app)?.unwrap_or_default().robot_w,
+    };
+    profile(&waypoints, &options, track_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wp(x: f64, y: f64, theta: f64) -> Waypoint {
+        Waypoint { x, y, theta }
+    }
+
+    #[test]
+    fn refuses_profiles_with_too_many_samples() {
+        let opts = ProfileOptions {
+            max_velocity: 1e-6,
+            sample_ms: 1,
+            ..ProfileOptions::default()
+        };
+        let err = profile(&[wp(0.0, 0.0, 0.0), wp(0.0, 48.0, 0.0)], &opts, 12.0).unwrap_err();
+        assert!(err.contains("raise max_velocity or sample_ms"), "{err}");
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn straight_48(max_jerk: Option<f64>) -> Trajectory {
+        let opts = ProfileOptions {
+            max_jerk,
+            ..ProfileOptions::default()
+        };
+        profile(&[wp(0.0, 0.0, 0.0), wp(0.0, 48.0, 0.0)], &opts, 12.0).unwrap()
+    }
+
+    #[test]
+    fn trapezoid_drive_of_48_inches() {
+        // 0.5 s ramps cover 15 in each; the other 18 in cruise at 60 in/s.
+        let t = straight_48(None);
+        assert_eq!((t.duration_ms, t.distance), (1300, 48.0));
+        assert_eq!(t.moves.len(), 1);
+        let m = &t.moves[0];
+        assert_eq!(
+            (m.kind, m.start_ms, m.end_ms, m.amount),
+            (MoveKind::Drive, 0, 1300, 48.0)
+        );
+
+        let at = |ms: u64| t.poses.iter().find(|p| p.t == ms).unwrap();
+        assert!(close(at(500).y, 15.0));
+        assert!(close(at(800).y, 33.0));
+        assert!(close(at(650).l_vel.unwrap(), 60.0 / 75.0 * 127.0));
+        let end = t.poses.last().unwrap();
+        assert_eq!(
+            (end.t, end.x, end.y, end.l_vel),
+            (1300, 0.0, 48.0, Some(0.0))
+        );
+    }
+
+    #[test]
+    fn s_curve_drive_of_48_inches() {
+        // 0.2 s of jerk, 0.3 s at 120 in/s^2 and 0.2 s of jerk reach 60 in/s
+        // over 21 in; the last 6 in cruise.
+        let t = straight_48(Some(600.0));
+        assert_eq!((t.duration_ms, t.distance), (1500, 48.0));
+        let at = |ms: u64| t.poses.iter().find(|p| p.t == ms).unwrap();
+        assert!(close(at(200).y, 600.0 * 0.2f64.powi(3) / 6.0));
+        assert!(close(at(700).y, 21.0));
+        assert!(close(at(750).y, 24.0));
+        assert!(close(at(750).speed, 60.0 / 75.0 * 127.0));
+        assert!(t.poses.windows(2).all(|w| w[1].y >= w[0].y));
+        assert_eq!(t.poses.last().unwrap().y, 48.0);
+    }
+
+    #[test]
+    fn backs_up_when_facing_away() {
+        let plan = [wp(0.0, 0.0, 180.0), wp(0.0, 48.0, 180.0)];
+        let t = profile(&plan, &ProfileOptions::default(), 12.0).unwrap();
+        assert_eq!(t.moves.len(), 1);
+        assert_eq!(
+            (t.moves[0].kind, t.moves[0].amount),
+            (MoveKind::Drive, -48.0)
+        );
+        assert!(t.poses.iter().all(|p| p.theta == 180.0));
+        assert!(t.poses.iter().all(|p| p.l_vel.unwrap() <= 0.0));
+
+        let forward_only = ProfileOptions {
+            allow_reverse: false,
+            ..ProfileOptions::default()
+        };
+        let t = profile(&plan, &forward_only, 12.0).unwrap();
+        let kinds: Vec<(MoveKind, f64)> = t.moves.iter().map(|m| (m.kind, m.amount)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (MoveKind::Turn, 180.0),
+                (MoveKind::Drive, 48.0),
+                (MoveKind::Turn, 180.0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! Minimal WebSocket client, just enough to subscribe to the bridge's `/ws`
+//! feed from the backend (text frames in, pongs and close out).
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine as _;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+pub struct WsClient {
+    stream: TcpStream,
+    partial: Vec<u8>,
+}
+
+pub enum Message {
+    Text(String),
+    /// Nothing arrived within the read timeout.
+    Idle,
+    Closed,
+}
+
+// Not security-sensitive: the key only has to differ between handshakes.
+fn nonce() -> [u8; 16] {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let seed = now ^ ((std::process::id() as u128) << 64);
+    seed.to_le_bytes()
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+impl WsClient {
+    pub fn connect(host: &str, port: u16, path: &str, read_timeout: Duration) -> io::Result<Self> {
+        let mut stream = TcpStream::connect((host, port))?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let key = base64::engine::general_purpose::STANDARD.encode(nonce());
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: {host}:{port}\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )?;
+
+        // Read the response head byte by byte so no frame data is swallowed.
+        let mut head = Vec::new();
+        let mut byte = [0u8; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            if head.len() > 8192 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "handshake too long",
+                ));
+            }
+            stream.read_exact(&mut byte)?;
+            head.push(byte[0]);
+        }
+        let status = String::from_utf8_lossy(&head);
+        if !status.starts_with("HTTP/1.1 101") {
+            let line = status.lines().next().unwrap_or("").to_string();
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("websocket upgrade refused: {line}"),
+            ));
+        }
+        stream.set_read_timeout(Some(read_timeout))?;
+        Ok(WsClient {
+            stream,
+            partial: Vec::new(),
+        })
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        // Client frames must be masked (RFC 6455 5.3).
+        let mask = nonce();
+        let mask = [mask[0], mask[3], mask[7], mask[11]];
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            n if n < 126 => frame.push(0x80 | n as u8),
+            n if n <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            n => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        self.stream.write_all(&frame)
+    }
+
+    pub fn close(&mut self) {
+        let _ = self.send_frame(OP_CLOSE, &[]);
+    }
+
+    /// Reads the next complete message, answering pings along the way.
+    pub fn read(&mut self) -> io::Result<Message> {
+        loop {
+            let mut hdr = [0u8; 2];
+            match self.stream.read(&mut hdr[..1]) {
+                Ok(0) => return Ok(Message::Closed),
+                Ok(_) => {}
+                Err(e) if is_timeout(&e) => return Ok(Message::Idle),
+                Err(e) => return Err(e),
+            }
+            self.read_full(&mut hdr[1..])?;
+            // A frame has started; wait for the rest of it even across timeouts.
+            let fin = hdr[0] & 0x80 != 0;
+            let opcode = hdr[0] & 0x0F;
+            let masked = hdr[1] & 0x80 != 0;
+            let len = match hdr[1] & 0x7F {
+                126 => {
+                    let mut b = [0u8; 2];
+                    self.read_full(&mut b)?;
+                    u16::from_be_bytes(b) as usize
+                }
+                127 => {
+                    let mut b = [0u8; 8];
+                    self.read_full(&mut b)?;
+                    u64::from_be_bytes(b) as usize
+                }
+                n => n as usize,
+            };
+            if len > MAX_MESSAGE || self.partial.len() + len > MAX_MESSAGE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "websocket message too large",
+                ));
+            }
+            let mut mask = [0u8; 4];
+            if masked {
+                self.read_full(&mut mask)?;
+            }
+            let mut payload = vec![0u8; len];
+            self.read_full(&mut payload)?;
+            if masked {
+                for (i, b) in payload.iter_mut().enumerate() {
+                    *b ^= mask[i % 4];
+                }
+            }
+
+            match opcode {
+                OP_PING => self.send_frame(OP_PONG, &payload)?,
+                OP_PONG => {}
+                OP_CLOSE => {
+                    self.close();
+                    return Ok(Message::Closed);
+                }
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    self.partial.extend_from_slice(&payload);
+                    if fin {
+                        let data = std::mem::take(&mut self.partial);
+                        return Ok(Message::Text(String::from_utf8_lossy(&data).into_owned()));
+                    }
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown websocket opcode {opcode}"),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn read_full(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.stream.read(&mut buf[filled..]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(e) if is_timeout(&e) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+    /// A server frame; `masked` is for checking the client copes with it.
+    fn frame(fin: bool, opcode: u8, masked: bool, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![if fin { 0x80 } else { 0 } | opcode];
+        let bit = if masked { 0x80 } else { 0 };
+        match payload.len() {
+            n if n < 126 => out.push(bit | n as u8),
+            n if n <= u16::MAX as usize => {
+                out.push(bit | 126);
+                out.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            n => {
+                out.push(bit | 127);
+                out.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+        if masked {
+            out.extend_from_slice(&MASK);
+            out.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        } else {
+            out.extend_from_slice(payload);
+        }
+        out
+    }
+
+    /// Reads one short client frame, checking it is masked.
+    fn client_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut hdr = [0u8; 2];
+        stream.read_exact(&mut hdr).unwrap();
+        assert_eq!(hdr[0] & 0x80, 0x80, "client frames are final");
+        assert_eq!(hdr[1] & 0x80, 0x80, "client frames must be masked");
+        let mut mask = [0u8; 4];
+        stream.read_exact(&mut mask).unwrap();
+        let mut payload = vec![0u8; (hdr[1] & 0x7F) as usize];
+        stream.read_exact(&mut payload).unwrap();
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+        (hdr[0] & 0x0F, payload)
+    }
+
+    /// Accepts one client, answers the handshake with `status` and hands the
+    /// connection to `script`.
+    fn serve(
+        status: &'static str,
+        script: impl FnOnce(TcpStream) + Send + 'static,
+    ) -> (u16, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                head.push(byte[0]);
+            }
+            let head = String::from_utf8(head).unwrap();
+            assert!(head.starts_with("GET /ws HTTP/1.1\r\n"), "{head}");
+            assert!(head.contains("Upgrade: websocket\r\n"), "{head}");
+            assert!(head.contains("Sec-WebSocket-Key: "), "{head}");
+            write!(stream, "HTTP/1.1 {status}\r\nUpgrade: websocket\r\n\r\n").unwrap();
+            script(stream);
+        });
+        (port, handle)
+    }
+
+    fn connect(port: u16) -> io::Result<WsClient> {
+        WsClient::connect("127.0.0.1", port, "/ws", Duration::from_millis(200))
+    }
+
+    fn text(client: &mut WsClient) -> String {
+        match client.read().unwrap() {
+            Message::Text(s) => s,
+            Message::Idle => panic!("expected text, got idle"),
+            Message::Closed => panic!("expected text, got close"),
+        }
+    }
+
+    #[test]
+    fn reads_masked_unmasked_and_extended_frames() {
+        let medium = "m".repeat(300);
+        let large = "L".repeat(70_000);
+        let frames = [
+            frame(true, OP_TEXT, false, b"plain"),
+            frame(true, OP_TEXT, true, b"masked"),
+            frame(true, OP_TEXT, false, medium.as_bytes()),
+            frame(true, OP_BINARY, true, large.as_bytes()),
+        ]
+        .concat();
+        let (port, server) = serve("101 Switching Protocols", move |mut s| {
+            s.write_all(&frames).unwrap();
+            assert_eq!(client_frame(&mut s).0, OP_CLOSE);
+        });
+        let mut client = connect(port).unwrap();
+        assert_eq!(text(&mut client), "plain");
+        assert_eq!(text(&mut client), "masked");
+        assert_eq!(text(&mut client), medium);
+        assert_eq!(text(&mut client), large);
+        client.close();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn joins_continuations_and_answers_pings_in_between() {
+        let (port, server) = serve("101 Switching Protocols", |mut s| {
+            s.write_all(&frame(false, OP_TEXT, false, b"{\"a\":"))
+                .unwrap();
+            s.write_all(&frame(true, OP_PING, false, b"beat")).unwrap();
+            s.write_all(&frame(true, OP_PONG, false, b"")).unwrap();
+            s.write_all(&frame(false, OP_CONTINUATION, true, b"1"))
+                .unwrap();
+            s.write_all(&frame(true, OP_CONTINUATION, false, b"}"))
+                .unwrap();
+            assert_eq!(client_frame(&mut s), (OP_PONG, b"beat".to_vec()));
+        });
+        let mut client = connect(port).unwrap();
+        assert_eq!(text(&mut client), "{\"a\":1}");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn close_is_echoed_and_reported() {
+        let (port, server) = serve("101 Switching Protocols", |mut s| {
+            s.write_all(&frame(true, OP_CLOSE, false, &1000u16.to_be_bytes()))
+                .unwrap();
+            assert_eq!(client_frame(&mut s).0, OP_CLOSE);
+        });
+        let mut client = connect(port).unwrap();
+        assert!(matches!(client.read().unwrap(), Message::Closed));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn quiet_connection_is_idle_then_closed_on_eof() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (port, server) = serve("101 Switching Protocols", move |_s| {
+            rx.recv().unwrap();
+        });
+        let mut client = connect(port).unwrap();
+        assert!(matches!(client.read().unwrap(), Message::Idle));
+        tx.send(()).unwrap();
+        server.join().unwrap();
+        assert!(matches!(client.read().unwrap(), Message::Closed));
+    }
+
+    #[test]
+    fn refused_upgrade_and_oversized_frames_are_errors() {
+        let (port, server) = serve("403 Forbidden", |_s| {});
+        let err = connect(port).err().unwrap();
+        assert!(err.to_string().contains("403 Forbidden"), "{err}");
+        server.join().unwrap();
+
+        let (port, server) = serve("101 Switching Protocols", |mut s| {
+            let mut hdr = vec![0x80 | OP_TEXT, 127];
+            hdr.extend_from_slice(&(MAX_MESSAGE as u64 + 1).to_be_bytes());
+            s.write_all(&hdr).unwrap();
+        });
+        let mut client = connect(port).unwrap();
+        let err = client.read().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        server.join().unwrap();
+    }
+}
@@ -4503,6 +4503,40 @@ async function openFile(file, inputEl) {
   }
 }
 
+// Recordings that never got their close (MotionView quit or crashed while
+// connected). Each one is offered for recovery, then for discarding; saying
+// no to both leaves it to be offered again next time.
+async function offerSessionRecovery() {
+  if (!hasInvoke()) return;
+  let sessions = [];
+  try {
+    sessions = await invoke('list_unfinished_sessions');
+  } catch (e) {
+    console.error('MotionView: could not list unfinished recordings:', e);
+    return;
+  }
+  for (const session of sessions) {
+    const what = `The recording "${session.name}" (${session.lines} lines) was not finished.`;
+    try {
+      if (confirm(`${what}\n\nRecover it now?`)) {
+        const report = await invoke('recover_session', { path: session.path });
+        if (report.error) {
+          setStatus(`Could not recover ${session.name}: ${report.error}`);
+          continue;
+        }
+        if (report.run) setData(report.run);
+        setStatus(`Recovered ${session.name}${report.output ? ` to ${report.output}` : ''}`);
+      } else if (confirm(`${what}\n\nDiscard it? This deletes the recording.`)) {
+        await invoke('discard_session', { path: session.path });
+        setStatus(`Discarded ${session.name}`);
+      }
+    } catch (e) {
+      console.error(e);
+      setStatus(`Could not recover ${session.name}: ${e?.message || e}`);
+    }
+  }
+}
+
 // -------- controls wiring --------
 btnFile.addEventListener('click', () => fileEl.click());
 fileEl.addEventListener('change', (e) => {
@@ -4582,8 +4616,9 @@ async function loadSettings() {
   try {
     let settings = null;
     if (invoke) {
+      // The backend returns migrated, validated settings.
       const saved = await invoke('read_settings');
-      if (saved) settings = JSON.parse(saved);
+      if (saved) settings = saved;
       else {
         // Create defaults on first run so the app data dir/file exists.
         await saveSettings();
@@ -4762,7 +4797,8 @@ async function saveSettings() {
     };
     const payload = JSON.stringify(settings);
     if (invoke) {
-      await invoke('write_settings', { contents: payload });
+      const saved = await invoke('write_settings', { contents: payload });
+      if (saved?.issues?.length) console.warn('Settings adjusted on save:', saved.issues);
     } else {
       console.warn('Settings persistence is unavailable (Tauri invoke missing).');
     }
@@ -6091,3 +6127,4 @@ loadRobotImage();
 drawFirstField();
 updatePlanControls();
 void setupExitHandler();
+void offerSessionRecovery();